@@ -18,7 +18,7 @@ fn parse_simfiles_bench(c: &mut Criterion) {
     // Parse it!
     c.bench_function("parse simfile", move |b| {
         b.iter(|| {
-            crate::parse_simfile(&mut reader);
+            let _ = crate::parse_simfile(&mut reader);
         })
     });
 }
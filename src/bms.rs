@@ -0,0 +1,375 @@
+use crate::simfile::{
+    columns_for_chart_type, note_data_from_notes, Chart, ChartDifficulty, NoteType, Simfile, Stop,
+    TimedNote, BPM, ROWS_PER_BEAT,
+};
+use crate::timing::TimingData;
+use crate::SimfileParseError;
+use std::collections::BTreeMap;
+use std::io::BufRead;
+
+/// The BPM used by BMS files that don't set `#BPM`.
+const DEFAULT_BPM: f32 = 130.0;
+
+/// A key on a beatmania controller, the scratch is key 0.
+type Key = usize;
+const SCRATCH: Key = 0;
+
+/// An object placed in a channel, e.g. a note, a BPM change or a measure length.
+struct BmsObject {
+    measure: u32,
+    channel: String,
+    /// Position in the measure, from 0.0 to 1.0
+    position: f32,
+    value: String,
+}
+
+/// Parses a BMS chart (.bms, .bme, .bml), where every `#RANDOM` picks the first branch.
+///
+/// See `parse_bms_with_random` for the details of how the chart is converted.
+pub fn parse_bms<R: BufRead>(reader: &mut R) -> Result<Simfile, SimfileParseError> {
+    parse_bms_with_random(reader, |_| 1)
+}
+
+/// Parses a BMS chart (.bms, .bme, .bml), calling `random` with the max value of every `#RANDOM`
+/// block to pick which `#IF` branch is used. `random` should return a value from 1 to max.
+///
+/// The chart is converted to a "beat-single5", "beat-single7", "beat-double5" or "beat-double7"
+/// chart depending on the keys used, or "kb7-single" if 7 keys are used without the scratch.
+/// The scratch is the first lane for player 1 and the last lane for player 2, followed (or
+/// preceded) by the keys in order. Measure length changes are applied to the note positions,
+/// since the note data always uses 4 beats per measure. The `#WAVxx` keysounds are kept in
+/// `Simfile::keysounds` at their index (`#WAV0Z` is keysound 35), unused indices are empty.
+pub fn parse_bms_with_random<R: BufRead, F: FnMut(u32) -> u32>(
+    reader: &mut R,
+    mut random: F,
+) -> Result<Simfile, SimfileParseError> {
+    let mut data = vec![];
+    if reader.read_to_end(&mut data).is_err() {
+        return Err(SimfileParseError::BufReadError);
+    }
+    // BMS files are often Shift-JIS encoded, only the ASCII parts are guaranteed to survive
+    let data = String::from_utf8_lossy(&data);
+
+    let mut headers: BTreeMap<String, String> = BTreeMap::new();
+    let mut objects: Vec<BmsObject> = vec![];
+    let mut random_values: Vec<u32> = vec![];
+    let mut skipped_blocks: Vec<bool> = vec![];
+
+    for line in data.lines() {
+        let line = line.trim();
+        if !line.starts_with('#') {
+            continue;
+        }
+
+        let (key, value) = match line.find(|c: char| c.is_whitespace() || c == ':') {
+            Some(i) => (line[1..i].to_uppercase(), line[i + 1..].trim()),
+            None => (line[1..].to_uppercase(), ""),
+        };
+        let skipping = skipped_blocks.iter().any(|s| *s);
+
+        match key.as_ref() {
+            "RANDOM" | "SETRANDOM" => {
+                let max = value.parse().unwrap_or(1);
+                match (skipping, key.as_ref()) {
+                    (true, _) => random_values.push(0),
+                    (false, "RANDOM") => random_values.push(random(max)),
+                    (false, _) => random_values.push(max),
+                }
+            }
+            "ENDRANDOM" => {
+                random_values.pop();
+            }
+            "IF" => {
+                let current = random_values.last().cloned().unwrap_or(0);
+                skipped_blocks.push(value.parse::<u32>().ok() != Some(current));
+            }
+            "ENDIF" => {
+                skipped_blocks.pop();
+            }
+            _ if skipping => {}
+            _ if is_channel_key(&key) => {
+                objects.extend(parse_channel(&key, value)?);
+            }
+            _ => {
+                headers.insert(key, value.to_string());
+            }
+        }
+    }
+
+    convert_to_simfile(&headers, objects)
+}
+
+/// Returns true if the key is a measure and channel, e.g. "00111".
+fn is_channel_key(key: &str) -> bool {
+    let bytes = key.as_bytes();
+    bytes.len() == 5
+        && bytes[..3].iter().all(|c| c.is_ascii_digit())
+        && bytes[3..].iter().all(|c| c.is_ascii_alphanumeric())
+}
+
+fn parse_channel(key: &str, value: &str) -> Result<Vec<BmsObject>, SimfileParseError> {
+    let measure = key[..3].parse().unwrap();
+    let channel = key[3..].to_string();
+
+    // The measure length is a single number instead of a list of objects
+    if channel == "02" {
+        return Ok(vec![BmsObject {
+            measure,
+            channel,
+            position: 0.0,
+            value: value.to_string(),
+        }]);
+    }
+
+    let value: Vec<char> = value.chars().filter(|c| !c.is_whitespace()).collect();
+    if value.len() % 2 != 0 {
+        return Err(SimfileParseError::InvalidBmsData);
+    }
+
+    let count = value.len() / 2;
+    Ok(value
+        .chunks(2)
+        .enumerate()
+        .filter(|(_, v)| v != &['0', '0'])
+        .map(|(i, v)| BmsObject {
+            measure,
+            channel: channel.clone(),
+            position: i as f32 / count as f32,
+            value: v.iter().collect::<String>().to_uppercase(),
+        })
+        .collect())
+}
+
+fn convert_to_simfile(
+    headers: &BTreeMap<String, String>,
+    objects: Vec<BmsObject>,
+) -> Result<Simfile, SimfileParseError> {
+    let mut sim = Simfile::new();
+    let header = |key: &str| headers.get(key).filter(|v| !v.is_empty()).cloned();
+
+    sim.title = header("TITLE");
+    sim.subtitle = header("SUBTITLE");
+    sim.artist = header("ARTIST");
+    sim.genre = header("GENRE");
+    sim.banner_path = header("BANNER");
+    sim.background_path = header("STAGEFILE").or_else(|| header("BACKBMP"));
+    sim.offset = Some(0.0);
+
+    // Keysounds are numbered in base 36, e.g. #WAV0Z, and keep their number as the index
+    for (key, value) in headers.iter() {
+        if key.len() != 5 || !key.starts_with("WAV") || value.is_empty() {
+            continue;
+        }
+        if let Ok(index) = usize::from_str_radix(&key[3..], 36) {
+            if sim.keysounds.len() <= index {
+                sim.keysounds.resize(index + 1, String::new());
+            }
+            sim.keysounds[index] = value.clone();
+        }
+    }
+
+    // Find the beat every measure starts on, measures are 4 beats long unless changed
+    let measure_count = objects.iter().map(|o| o.measure + 1).max().unwrap_or(1);
+    let mut measure_beats = vec![0.0];
+    for measure in 0..measure_count {
+        let length = objects
+            .iter()
+            .find(|o| o.measure == measure && o.channel == "02")
+            .map(|o| o.value.parse::<f32>())
+            .unwrap_or(Ok(1.0));
+        match length {
+            Ok(length) => measure_beats.push(measure_beats[measure as usize] + length * 4.0),
+            Err(_) => return Err(SimfileParseError::InvalidBmsData),
+        }
+    }
+    let beat_of = |o: &BmsObject| {
+        let start = measure_beats[o.measure as usize];
+        start + (measure_beats[o.measure as usize + 1] - start) * o.position
+    };
+
+    let initial_bpm = match header("BPM") {
+        Some(bpm) => match bpm.parse() {
+            Ok(bpm) => bpm,
+            Err(_) => return Err(SimfileParseError::FailedToParseBPMs),
+        },
+        None => DEFAULT_BPM,
+    };
+    sim.bpms.push(BPM {
+        beat: 0.0,
+        bpm: initial_bpm,
+    });
+
+    for object in objects.iter() {
+        let bpm = match object.channel.as_ref() {
+            "03" => u32::from_str_radix(&object.value, 16)
+                .ok()
+                .map(|b| b as f32),
+            "08" => header(&format!("BPM{}", object.value)).and_then(|b| b.parse().ok()),
+            _ => continue,
+        };
+        match bpm {
+            Some(bpm) => sim.bpms.push(BPM {
+                beat: beat_of(object),
+                bpm,
+            }),
+            None => return Err(SimfileParseError::FailedToParseBPMs),
+        }
+    }
+    sim.bpms.sort_by(|a, b| a.beat.total_cmp(&b.beat));
+
+    // Stops are measured in 192nds of a 4/4 measure, which needs to be converted to seconds
+    let timing = TimingData::new(0.0, sim.bpms.clone(), vec![]);
+    for object in objects.iter().filter(|o| o.channel == "09") {
+        let length = header(&format!("STOP{}", object.value)).and_then(|s| s.parse::<f32>().ok());
+        match length {
+            Some(length) => {
+                let beat = beat_of(object);
+                sim.stops.push(Stop {
+                    beat,
+                    time: length / ROWS_PER_BEAT as f32 * 60.0 / timing.bpm_at_beat(beat),
+                })
+            }
+            None => return Err(SimfileParseError::FailedToParseStops),
+        }
+    }
+
+    let lanes = find_notes(&objects, header("LNOBJ"), &beat_of);
+    if !lanes.is_empty() {
+        sim.charts.push(create_chart(headers, lanes)?);
+    }
+
+    Ok(sim)
+}
+
+/// Finds the notes of every lane, keyed by player (1 or 2) and key.
+fn find_notes<F: Fn(&BmsObject) -> f32>(
+    objects: &[BmsObject],
+    ln_object: Option<String>,
+    beat_of: &F,
+) -> BTreeMap<(usize, Key), Vec<(u32, NoteType)>> {
+    let mut lanes: BTreeMap<(usize, Key), Vec<(u32, NoteType)>> = BTreeMap::new();
+
+    let mut sorted: Vec<&BmsObject> = objects.iter().collect();
+    sorted.sort_by(|a, b| beat_of(a).total_cmp(&beat_of(b)));
+
+    for object in sorted {
+        let mut channel = object.channel.chars();
+        let (kind, lane) = match (channel.next(), channel.next().and_then(|c| c.to_digit(10))) {
+            (Some(kind), Some(lane)) => (kind, lane),
+            _ => continue,
+        };
+        let (player, note_type) = match kind {
+            '1' => (1, NoteType::Normal),
+            '2' => (2, NoteType::Normal),
+            '5' => (1, NoteType::HoldHead),
+            '6' => (2, NoteType::HoldHead),
+            'D' => (1, NoteType::Mine),
+            'E' => (2, NoteType::Mine),
+            _ => continue,
+        };
+        let key = match lane {
+            1..=5 => lane as usize,
+            6 => SCRATCH,
+            8 | 9 => lane as usize - 2,
+            _ => continue,
+        };
+
+        let row = (beat_of(object) * ROWS_PER_BEAT as f32).round() as u32;
+        let notes = lanes.entry((player, key)).or_default();
+        let last_is_head = notes.last().map(|n| n.1 == NoteType::HoldHead) == Some(true);
+
+        if note_type == NoteType::HoldHead && last_is_head {
+            // Long note channels alternate between the start and the end of the hold
+            notes.push((row, NoteType::HoldOrRollTail));
+        } else if note_type == NoteType::Normal && ln_object.as_ref() == Some(&object.value) {
+            // With #LNOBJ, the end of a hold is a special note after the start
+            if let Some(last) = notes.last_mut().filter(|n| n.1 == NoteType::Normal) {
+                last.1 = NoteType::HoldHead;
+                notes.push((row, NoteType::HoldOrRollTail));
+            }
+        } else {
+            notes.push((row, note_type));
+        }
+    }
+
+    // Holds that never end are turned into normal notes
+    for notes in lanes.values_mut() {
+        if let Some(last) = notes.last_mut() {
+            if last.1 == NoteType::HoldHead {
+                last.1 = NoteType::Normal;
+            }
+        }
+    }
+
+    lanes
+}
+
+fn create_chart(
+    headers: &BTreeMap<String, String>,
+    lanes: BTreeMap<(usize, Key), Vec<(u32, NoteType)>>,
+) -> Result<Chart, SimfileParseError> {
+    let double = lanes.keys().any(|(player, _)| *player == 2);
+    let seven_keys = lanes.keys().any(|(_, key)| *key > 5);
+    let scratch = lanes.keys().any(|(_, key)| *key == SCRATCH);
+
+    let chart_type = match (double, seven_keys) {
+        (false, true) if !scratch => "kb7-single",
+        (false, true) => "beat-single7",
+        (false, false) => "beat-single5",
+        (true, true) => "beat-double7",
+        (true, false) => "beat-double5",
+    };
+    let columns = columns_for_chart_type(chart_type).unwrap();
+    let keys = match chart_type {
+        "kb7-single" => 7,
+        _ => columns / if double { 2 } else { 1 } - 1,
+    };
+
+    let mut notes = vec![];
+    for ((player, key), lane_notes) in lanes {
+        let column = match (player, key) {
+            (1, _) if chart_type == "kb7-single" => key - 1,
+            (1, _) => key,
+            (_, SCRATCH) => columns - 1,
+            (_, _) => keys + key,
+        };
+        notes.extend(lane_notes.into_iter().map(|(row, note_type)| TimedNote {
+            row,
+            column,
+            note_type,
+        }));
+    }
+    notes.sort_by_key(|n| (n.row, n.column));
+
+    let meter = match headers.get("PLAYLEVEL") {
+        Some(level) => match level.parse() {
+            Ok(i) => i,
+            Err(_) => return Err(SimfileParseError::FailedToParseChartMeter),
+        },
+        None => 1,
+    };
+
+    // #DIFFICULTY goes from 1 (Beginner) to 5 (Insane)
+    let difficulty_name = headers.get("DIFFICULTY").cloned().unwrap_or_default();
+    let difficulty = match difficulty_name.as_ref() {
+        "1" => ChartDifficulty::Beginner,
+        "2" => ChartDifficulty::Easy,
+        "3" => ChartDifficulty::Medium,
+        "4" => ChartDifficulty::Hard,
+        "5" => ChartDifficulty::Challenge,
+        _ => ChartDifficulty::from_meter(meter),
+    };
+
+    Ok(Chart {
+        chart_type: chart_type.to_string(),
+        author: None,
+        difficulty,
+        difficulty_name,
+        meter,
+        radar_values: Vec::new(),
+        note_data: note_data_from_notes(columns, &notes),
+        lasers: Vec::new(),
+        timing: None,
+        credit: None,
+    })
+}
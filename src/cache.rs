@@ -0,0 +1,585 @@
+use crate::format::{parse_any, SimfileFormat};
+use crate::simfile::{
+    BgChange, Chart, ChartDifficulty, ChartTiming, Combo, Delay, DisplayBPM, Fake, FgChange,
+    InstrumentTrack, Label, Laser, LaserPoint, LaserSide, NoteType, RadarValues, Scroll,
+    Selectable, Simfile, Speed, SpeedUnit, Stop, TickCount, TimeSignature, Warp, BPM,
+};
+use crate::SimfileParseError;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::Split;
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+
+/// The first line of a cache file, followed by "charts" or "headers".
+const CACHE_HEADER: &str = "sm_parser song cache 3";
+
+/// A simfile in the cache, with the file it was loaded from when it was cached.
+#[derive(Debug, Clone)]
+struct CacheEntry {
+    size: u64,
+    /// The modification time in nanoseconds since the Unix epoch, if the file system has one
+    modified: Option<u128>,
+    hash: u64,
+    simfile: Simfile,
+}
+
+/// A cache of parsed simfiles that can be saved to a file, to avoid parsing every simfile again
+/// when the same songs are loaded the next time.
+///
+/// A simfile is only parsed again when its file changed. A file with the same size and
+/// modification time as when it was cached is taken from the cache without reading it, and one
+/// with a different modification time is read and compared by the hash of its contents.
+///
+/// The cache keeps the parsed simfiles, and saves every value of them, so a simfile from the
+/// cache (or from a saved cache) is the same as one loaded with `load_simfile` without parsing
+/// its file again. When the cache only keeps the headers, the simfiles it returns don't have any
+/// charts, and a saved cache doesn't have their note data either.
+#[derive(Debug)]
+pub struct SongCache {
+    include_charts: bool,
+    entries: Mutex<HashMap<PathBuf, CacheEntry>>,
+}
+
+impl SongCache {
+    /// Creates an empty cache, which keeps the charts of the simfiles if `include_charts` is set
+    /// and only their headers otherwise.
+    pub fn new(include_charts: bool) -> SongCache {
+        SongCache {
+            include_charts,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Loads a cache from a file saved with `save`. Returns an empty cache if the file doesn't
+    /// exist, is damaged, or was saved with a different `include_charts`.
+    pub fn load<P: AsRef<Path>>(path: P, include_charts: bool) -> SongCache {
+        let cache = SongCache::new(include_charts);
+        if let Some(entries) = fs::read(path)
+            .ok()
+            .and_then(|data| read_entries(&data, include_charts))
+        {
+            *cache.entries.lock().unwrap() = entries;
+        }
+
+        cache
+    }
+
+    /// Saves the cache to a file. The file is replaced at once, so a cache that is being loaded
+    /// at the same time is never half written.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut temp_path = path.as_os_str().to_owned();
+        temp_path.push(".tmp");
+
+        let mut data = vec![];
+        writeln!(data, "{} {}", CACHE_HEADER, kind(self.include_charts))?;
+        let entries = self.entries.lock().unwrap();
+        // Paths are stored on a line, so paths with a line break can't be cached
+        let mut paths: Vec<&PathBuf> = entries
+            .keys()
+            .filter(|p| !p.to_string_lossy().contains('\n'))
+            .collect();
+        paths.sort();
+        for path in paths {
+            let entry = &entries[path];
+            let mut simfile = String::new();
+            entry.simfile.encode(&mut simfile);
+            writeln!(
+                data,
+                "{:016x} {} {} {} {}",
+                entry.hash,
+                entry.size,
+                entry.modified.map_or("-".to_string(), |m| m.to_string()),
+                simfile.len(),
+                path.display()
+            )?;
+            data.extend_from_slice(simfile.as_bytes());
+            data.push(b'\n');
+        }
+
+        fs::write(&temp_path, data)?;
+        fs::rename(&temp_path, path)
+    }
+
+    /// Returns the amount of simfiles in the cache.
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Removes the simfiles whose files don't exist anymore.
+    pub fn prune(&self) {
+        self.entries
+            .lock()
+            .unwrap()
+            .retain(|path, _| path.is_file());
+    }
+
+    /// Loads a simfile from the cache, or from its file when it isn't cached or the file changed
+    /// since it was cached. The format is picked like `load_simfile` does.
+    pub fn load_simfile<P: AsRef<Path>>(&self, path: P) -> Result<Simfile, SimfileParseError> {
+        let path = path.as_ref();
+        let metadata = match fs::metadata(path) {
+            Ok(metadata) => metadata,
+            Err(_) => return Err(SimfileParseError::BufReadError),
+        };
+        let size = metadata.len();
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|m| m.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_nanos());
+
+        let cached = self.cached(path, |e| {
+            e.size == size && e.modified.is_some() && e.modified == modified
+        });
+        if let Some(simfile) = cached {
+            return Ok(simfile);
+        }
+
+        let contents = match fs::read(path) {
+            Ok(contents) => contents,
+            Err(_) => return Err(SimfileParseError::BufReadError),
+        };
+        let hash = fnv1a(&contents);
+
+        if let Some(simfile) = self.cached(path, |e| e.size == size && e.hash == hash) {
+            if let Some(entry) = self.entries.lock().unwrap().get_mut(path) {
+                entry.modified = modified;
+            }
+            return Ok(simfile);
+        }
+
+        let simfile = self.parse(path, &contents)?;
+        self.entries.lock().unwrap().insert(
+            path.to_path_buf(),
+            CacheEntry {
+                size,
+                modified,
+                hash,
+                simfile: simfile.clone(),
+            },
+        );
+        Ok(simfile)
+    }
+
+    /// Returns the cached simfile of a file if there's an entry for it that `matches`.
+    fn cached<F>(&self, path: &Path, matches: F) -> Option<Simfile>
+    where
+        F: Fn(&CacheEntry) -> bool,
+    {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .get(path)
+            .filter(|e| matches(e))
+            .map(|e| e.simfile.clone())
+    }
+
+    /// Parses the contents of a file, picking the format like `load_simfile` does.
+    fn parse(&self, path: &Path, contents: &[u8]) -> Result<Simfile, SimfileParseError> {
+        let hint = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .and_then(SimfileFormat::from_extension);
+        let mut simfile = parse_any(&mut &contents[..], hint)?;
+        if !self.include_charts {
+            simfile.charts.clear();
+        }
+
+        Ok(simfile)
+    }
+}
+
+/// Reads the entries of a cache file, every entry is a line with the hash, size, modification
+/// time, length of the simfile and path, followed by the simfile as written by `Encode`. Returns
+/// `None` if the file is damaged or doesn't match `include_charts`.
+fn read_entries(data: &[u8], include_charts: bool) -> Option<HashMap<PathBuf, CacheEntry>> {
+    let mut entries = HashMap::new();
+    let mut rest = data;
+
+    let (header, after) = split_line(rest)?;
+    if header != format!("{} {}", CACHE_HEADER, kind(include_charts)) {
+        return None;
+    }
+    rest = after;
+
+    while !rest.is_empty() {
+        let (line, after) = split_line(rest)?;
+        let fields: Vec<&str> = line.splitn(5, ' ').collect();
+        if fields.len() != 5 {
+            return None;
+        }
+        let length: usize = fields[3].parse().ok()?;
+        if after.len() <= length || after[length] != b'\n' {
+            return None;
+        }
+
+        let mut lines = std::str::from_utf8(&after[..length]).ok()?.split('\n');
+        let simfile = Simfile::decode(&mut lines)?;
+        // Every value ends with a line break, so only an empty line is left after the simfile
+        if lines.next() != Some("") || lines.next().is_some() {
+            return None;
+        }
+
+        let entry = CacheEntry {
+            hash: u64::from_str_radix(fields[0], 16).ok()?,
+            size: fields[1].parse().ok()?,
+            modified: match fields[2] {
+                "-" => None,
+                modified => Some(modified.parse().ok()?),
+            },
+            simfile,
+        };
+        entries.insert(PathBuf::from(fields[4]), entry);
+        rest = &after[length + 1..];
+    }
+
+    Some(entries)
+}
+
+/// Returns what a cache keeps of the simfiles, written after `CACHE_HEADER`.
+fn kind(include_charts: bool) -> &'static str {
+    if include_charts {
+        "charts"
+    } else {
+        "headers"
+    }
+}
+
+/// Splits the first line off, returning `None` if there's no full line.
+fn split_line(data: &[u8]) -> Option<(&str, &[u8])> {
+    let end = data.iter().position(|b| *b == b'\n')?;
+    let line = std::str::from_utf8(&data[..end]).ok()?;
+    Some((line, &data[end + 1..]))
+}
+
+/// The 64-bit FNV-1a hash, used to see if the contents of a file changed.
+fn fnv1a(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+/// The lines of a simfile in a cache file.
+type Lines<'a> = Split<'a, char>;
+
+/// Writes a value to a cache file, as one or more lines.
+trait Encode {
+    fn encode(&self, out: &mut String);
+}
+
+/// Reads a value written by `Encode`, returning `None` if the lines don't hold one.
+trait Decode: Sized {
+    fn decode(lines: &mut Lines) -> Option<Self>;
+}
+
+/// Implements `Encode` and `Decode` for a value that is written on a line as text.
+macro_rules! encode_as_text {
+    ($($value:ty),*) => {$(
+        impl Encode for $value {
+            fn encode(&self, out: &mut String) {
+                out.push_str(&self.to_string());
+                out.push('\n');
+            }
+        }
+
+        impl Decode for $value {
+            fn decode(lines: &mut Lines) -> Option<Self> {
+                lines.next()?.parse().ok()
+            }
+        }
+    )*};
+}
+
+/// Implements `Encode` and `Decode` for a struct, which is written as its fields in order. Every
+/// field has to be listed, or decoding it won't compile.
+macro_rules! encode_fields {
+    ($($name:ident { $($field:ident),* })*) => {$(
+        impl Encode for $name {
+            fn encode(&self, out: &mut String) {
+                $(self.$field.encode(out);)*
+            }
+        }
+
+        impl Decode for $name {
+            fn decode(lines: &mut Lines) -> Option<Self> {
+                Some($name {
+                    $($field: Decode::decode(lines)?,)*
+                })
+            }
+        }
+    )*};
+}
+
+// Floats are written with the shortest text that's read back as the same number
+encode_as_text!(bool, i8, i32, u16, u32, usize, f32);
+
+encode_fields! {
+    BPM { beat, bpm }
+    Stop { beat, time }
+    Delay { beat, time }
+    TimeSignature { beat, numerator, denominator }
+    Scroll { beat, factor }
+    TickCount { beat, ticks }
+    Speed { beat, ratio, duration, unit }
+    Warp { beat, length }
+    Fake { beat, length }
+    Combo { beat, hit_multiplier, miss_multiplier }
+    Label { beat, label }
+    ChartTiming {
+        offset, bpms, stops, delays, scrolls, time_signatures, tick_counts, speeds, warps, fakes,
+        combos, labels
+    }
+    BgChange {
+        start_beat, file_name, play_rate, transition_type, effect_flag, second_effect_flag,
+        effect_file, second_effect_file, transition_file, color_string, second_color_string
+    }
+    FgChange { start_beat, path }
+    InstrumentTrack { instrument, path }
+    RadarValues {
+        stream, voltage, air, freeze, chaos, notes, taps, jumps, holds, mines, hands, rolls,
+        lifts, fakes
+    }
+    LaserPoint { row, position }
+    Laser { side, points }
+    Chart {
+        chart_type, author, difficulty, difficulty_name, meter, radar_values, note_data, lasers,
+        timing, credit
+    }
+    Simfile {
+        version, title, subtitle, artist, title_translit, subtitle_translit, artist_translit,
+        genre, origin, credit, banner_path, background_path, preview_video_path, jacket_path,
+        cd_image_path, disc_image_path, lyrics_path, cd_title_path, music_path, preview_path,
+        instrument_tracks, music_length, last_beat_hint, last_second_hint, offset, sample_start,
+        sample_length, selectable, bpms, display_bpm, bpm_lock, stops, delays, scrolls,
+        time_signatures, tick_counts, speeds, warps, fakes, combos, labels, bg_changes,
+        fg_changes, keysounds, charts
+    }
+}
+
+/// Strings are written on a line, with line breaks and backslashes escaped.
+impl Encode for String {
+    fn encode(&self, out: &mut String) {
+        out.push_str(&self.replace('\\', "\\\\").replace('\n', "\\n"));
+        out.push('\n');
+    }
+}
+
+impl Decode for String {
+    fn decode(lines: &mut Lines) -> Option<Self> {
+        let mut text = String::new();
+        let mut chars = lines.next()?.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => match chars.next()? {
+                    '\\' => text.push('\\'),
+                    'n' => text.push('\n'),
+                    _ => return None,
+                },
+                c => text.push(c),
+            }
+        }
+        Some(text)
+    }
+}
+
+/// Written as "-" for `None`, or "+" followed by the value.
+impl<T: Encode> Encode for Option<T> {
+    fn encode(&self, out: &mut String) {
+        match self {
+            Some(value) => {
+                out.push_str("+\n");
+                value.encode(out);
+            }
+            None => out.push_str("-\n"),
+        }
+    }
+}
+
+impl<T: Decode> Decode for Option<T> {
+    fn decode(lines: &mut Lines) -> Option<Self> {
+        match lines.next()? {
+            "+" => Some(Some(T::decode(lines)?)),
+            "-" => Some(None),
+            _ => None,
+        }
+    }
+}
+
+/// Written as the amount of values, followed by the values.
+impl<T: Encode> Encode for Vec<T> {
+    fn encode(&self, out: &mut String) {
+        self.len().encode(out);
+        for value in self {
+            value.encode(out);
+        }
+    }
+}
+
+impl<T: Decode> Decode for Vec<T> {
+    fn decode(lines: &mut Lines) -> Option<Self> {
+        let len = usize::decode(lines)?;
+        // The length isn't trusted for the capacity, since the file could be damaged
+        let mut values = Vec::new();
+        for _ in 0..len {
+            values.push(T::decode(lines)?);
+        }
+        Some(values)
+    }
+}
+
+/// Every measure is written on a single line, with a character for every note.
+impl Encode for Vec<Vec<NoteType>> {
+    fn encode(&self, out: &mut String) {
+        self.len().encode(out);
+        for measure in self {
+            out.extend(measure.iter().map(|note| match note {
+                NoteType::None => '0',
+                NoteType::Normal => '1',
+                NoteType::HoldHead => '2',
+                NoteType::HoldOrRollTail => '3',
+                NoteType::RollHead => '4',
+                NoteType::Mine => 'M',
+                NoteType::AutomaticKeysound => 'K',
+                NoteType::LiftNote => 'L',
+                NoteType::FakeNote => 'F',
+                NoteType::InvalidNote => '?',
+            }));
+            out.push('\n');
+        }
+    }
+}
+
+impl Decode for Vec<Vec<NoteType>> {
+    fn decode(lines: &mut Lines) -> Option<Self> {
+        let len = usize::decode(lines)?;
+        let mut measures = Vec::new();
+        for _ in 0..len {
+            let measure = lines.next()?.chars().map(|c| match c {
+                '0' => Some(NoteType::None),
+                '1' => Some(NoteType::Normal),
+                '2' => Some(NoteType::HoldHead),
+                '3' => Some(NoteType::HoldOrRollTail),
+                '4' => Some(NoteType::RollHead),
+                'M' => Some(NoteType::Mine),
+                'K' => Some(NoteType::AutomaticKeysound),
+                'L' => Some(NoteType::LiftNote),
+                'F' => Some(NoteType::FakeNote),
+                '?' => Some(NoteType::InvalidNote),
+                _ => None,
+            });
+            measures.push(measure.collect::<Option<Vec<NoteType>>>()?);
+        }
+        Some(measures)
+    }
+}
+
+/// Enums are written as the name of the variant, followed by its values.
+impl Encode for SpeedUnit {
+    fn encode(&self, out: &mut String) {
+        format!("{:?}", self).encode(out);
+    }
+}
+
+impl Decode for SpeedUnit {
+    fn decode(lines: &mut Lines) -> Option<Self> {
+        match lines.next()? {
+            "Beats" => Some(SpeedUnit::Beats),
+            "Seconds" => Some(SpeedUnit::Seconds),
+            _ => None,
+        }
+    }
+}
+
+impl Encode for LaserSide {
+    fn encode(&self, out: &mut String) {
+        format!("{:?}", self).encode(out);
+    }
+}
+
+impl Decode for LaserSide {
+    fn decode(lines: &mut Lines) -> Option<Self> {
+        match lines.next()? {
+            "Left" => Some(LaserSide::Left),
+            "Right" => Some(LaserSide::Right),
+            _ => None,
+        }
+    }
+}
+
+impl Encode for ChartDifficulty {
+    fn encode(&self, out: &mut String) {
+        self.to_string().encode(out);
+    }
+}
+
+impl Decode for ChartDifficulty {
+    fn decode(lines: &mut Lines) -> Option<Self> {
+        ChartDifficulty::from_name(lines.next()?)
+    }
+}
+
+impl Encode for DisplayBPM {
+    fn encode(&self, out: &mut String) {
+        match self {
+            DisplayBPM::Single(bpm) => {
+                "Single".to_string().encode(out);
+                bpm.encode(out);
+            }
+            DisplayBPM::Range(min, max) => {
+                "Range".to_string().encode(out);
+                min.encode(out);
+                max.encode(out);
+            }
+            DisplayBPM::Random => "Random".to_string().encode(out),
+        }
+    }
+}
+
+impl Decode for DisplayBPM {
+    fn decode(lines: &mut Lines) -> Option<Self> {
+        match lines.next()? {
+            "Single" => Some(DisplayBPM::Single(f32::decode(lines)?)),
+            "Range" => Some(DisplayBPM::Range(f32::decode(lines)?, f32::decode(lines)?)),
+            "Random" => Some(DisplayBPM::Random),
+            _ => None,
+        }
+    }
+}
+
+impl Encode for Selectable {
+    fn encode(&self, out: &mut String) {
+        match self {
+            Selectable::Numeric(value) => {
+                "Numeric".to_string().encode(out);
+                value.encode(out);
+            }
+            Selectable::Unknown(value) => {
+                "Unknown".to_string().encode(out);
+                value.encode(out);
+            }
+            selectable => format!("{:?}", selectable).encode(out),
+        }
+    }
+}
+
+impl Decode for Selectable {
+    fn decode(lines: &mut Lines) -> Option<Self> {
+        match lines.next()? {
+            "Yes" => Some(Selectable::Yes),
+            "No" => Some(Selectable::No),
+            "Roulette" => Some(Selectable::Roulette),
+            "Es" => Some(Selectable::Es),
+            "Omes" => Some(Selectable::Omes),
+            "Numeric" => Some(Selectable::Numeric(i32::decode(lines)?)),
+            "Unknown" => Some(Selectable::Unknown(String::decode(lines)?)),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,267 @@
+use crate::cache::SongCache;
+use crate::course::LoadedSong;
+use crate::song_directory::SongDirectory;
+use crate::{read_sections, SimfileParseError};
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+const IMAGE_EXTENSIONS: [&str; 5] = ["png", "jpg", "jpeg", "gif", "bmp"];
+
+/// The metadata of a group from its Group.ini file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GroupInfo {
+    /// The name that is shown for the group instead of the folder name
+    pub display_title: Option<String>,
+    pub sort_title: Option<String>,
+    pub translit_title: Option<String>,
+    pub series: Option<String>,
+    /// The offset in seconds that the songs of the group were synced with, 0.009 for "ITG" and 0
+    /// for "NULL"
+    pub sync_offset: Option<f32>,
+}
+
+/// A group folder and the songs that were loaded from it.
+#[derive(Debug, Clone)]
+pub struct SongGroup {
+    /// The name of the group folder
+    pub name: String,
+    pub path: PathBuf,
+    pub info: GroupInfo,
+    /// The first image in the group folder
+    pub banner: Option<PathBuf>,
+    /// The songs of the group, sorted by folder name
+    pub songs: Vec<SongDirectory>,
+}
+
+/// A file or folder that couldn't be loaded.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub path: PathBuf,
+    pub error: SimfileParseError,
+}
+
+/// All the songs in a songs folder.
+#[derive(Debug, Clone, Default)]
+pub struct Catalogue {
+    /// The groups, sorted by folder name
+    pub groups: Vec<SongGroup>,
+    /// The song folders and Group.ini files that couldn't be loaded
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Parses the Group.ini file of a group.
+pub fn parse_group_ini<R: BufRead>(reader: &mut R) -> Result<GroupInfo, SimfileParseError> {
+    let mut info = GroupInfo::default();
+
+    for (key, value) in read_sections(reader)? {
+        match key.as_ref() {
+            "DISPLAYTITLE" => info.display_title = value,
+            "SORTTITLE" => info.sort_title = value,
+            "TRANSLITTITLE" => info.translit_title = value,
+            "SERIES" => info.series = value,
+            "SYNCOFFSET" => {
+                info.sync_offset = match value.unwrap_or_default().trim().to_uppercase().as_ref() {
+                    "" => None,
+                    "ITG" => Some(0.009),
+                    "NULL" => Some(0.0),
+                    offset => match offset.parse() {
+                        Ok(offset) => Some(offset),
+                        Err(_) => return Err(SimfileParseError::InvalidGroupData),
+                    },
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(info)
+}
+
+/// Loads every song of a songs folder laid out like Stepmania's, `<path>/<Group>/<Song>/`.
+///
+/// The songs are loaded on `threads` threads, or on as many threads as the machine can run at
+/// once when it's 0. Songs and Group.ini files that fail to load are left out and reported in
+/// `Catalogue::diagnostics` instead of stopping the scan. Returns `BufReadError` only if `path`
+/// itself can't be read.
+pub fn scan_songs_dir<P: AsRef<Path>>(
+    path: P,
+    threads: usize,
+) -> Result<Catalogue, SimfileParseError> {
+    scan(path.as_ref(), threads, None)
+}
+
+/// Loads every song of a songs folder like `scan_songs_dir`, but only parses the simfiles that
+/// changed since they were added to the cache.
+pub fn scan_songs_dir_cached<P: AsRef<Path>>(
+    path: P,
+    threads: usize,
+    cache: &SongCache,
+) -> Result<Catalogue, SimfileParseError> {
+    scan(path.as_ref(), threads, Some(cache))
+}
+
+fn scan(
+    path: &Path,
+    threads: usize,
+    cache: Option<&SongCache>,
+) -> Result<Catalogue, SimfileParseError> {
+    let mut catalogue = Catalogue::default();
+    let mut song_paths = vec![];
+
+    for group_path in list_dir(path)?.into_iter().filter(|p| p.is_dir()) {
+        let entries = match list_dir(&group_path) {
+            Ok(entries) => entries,
+            Err(error) => {
+                catalogue.diagnostics.push(Diagnostic {
+                    path: group_path,
+                    error,
+                });
+                continue;
+            }
+        };
+
+        let info = match entries.iter().find(|p| is_group_ini(p)) {
+            Some(ini_path) => match load_group_ini(ini_path) {
+                Ok(info) => info,
+                Err(error) => {
+                    catalogue.diagnostics.push(Diagnostic {
+                        path: ini_path.clone(),
+                        error,
+                    });
+                    GroupInfo::default()
+                }
+            },
+            None => GroupInfo::default(),
+        };
+        let banner = entries.iter().find(|p| is_image(p)).cloned();
+
+        let group = catalogue.groups.len();
+        song_paths.extend(
+            entries
+                .into_iter()
+                .filter(|p| p.is_dir())
+                .map(|p| (group, p)),
+        );
+        catalogue.groups.push(SongGroup {
+            name: file_name(&group_path),
+            path: group_path,
+            info,
+            banner,
+            songs: vec![],
+        });
+    }
+
+    let results = load_songs(&song_paths, threads, cache);
+    for ((group, song_path), result) in song_paths.into_iter().zip(results) {
+        match result {
+            Ok(song) => catalogue.groups[group].songs.push(song),
+            Err(error) => catalogue.diagnostics.push(Diagnostic {
+                path: song_path,
+                error,
+            }),
+        }
+    }
+
+    Ok(catalogue)
+}
+
+/// Loads the song folders on a pool of threads, returning the results in the same order.
+fn load_songs(
+    song_paths: &[(usize, PathBuf)],
+    threads: usize,
+    cache: Option<&SongCache>,
+) -> Vec<Result<SongDirectory, SimfileParseError>> {
+    let threads = match threads {
+        0 => thread::available_parallelism().map_or(1, |n| n.get()),
+        threads => threads,
+    };
+    if threads == 1 {
+        return song_paths
+            .iter()
+            .map(|(_, p)| SongDirectory::load_with(p, cache))
+            .collect();
+    }
+
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::with_capacity(song_paths.len()));
+    thread::scope(|scope| {
+        for _ in 0..threads.min(song_paths.len()) {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let (_, path) = match song_paths.get(i) {
+                    Some(song_path) => song_path,
+                    None => break,
+                };
+                let song = SongDirectory::load_with(path, cache);
+                results.lock().unwrap().push((i, song));
+            });
+        }
+    });
+
+    let mut results = results.into_inner().unwrap();
+    results.sort_by_key(|(i, _)| *i);
+    results.into_iter().map(|(_, song)| song).collect()
+}
+
+fn load_group_ini(path: &Path) -> Result<GroupInfo, SimfileParseError> {
+    match File::open(path) {
+        Ok(file) => parse_group_ini(&mut BufReader::new(file)),
+        Err(_) => Err(SimfileParseError::BufReadError),
+    }
+}
+
+/// Returns the files and folders in a folder, sorted by name.
+fn list_dir(path: &Path) -> Result<Vec<PathBuf>, SimfileParseError> {
+    let entries = match fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(_) => return Err(SimfileParseError::BufReadError),
+    };
+    let mut paths: Vec<PathBuf> = entries.filter_map(|e| e.ok()).map(|e| e.path()).collect();
+    paths.sort();
+
+    Ok(paths)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn is_group_ini(path: &Path) -> bool {
+    path.is_file() && file_name(path).eq_ignore_ascii_case("group.ini")
+}
+
+fn is_image(path: &Path) -> bool {
+    path.is_file()
+        && path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| IMAGE_EXTENSIONS.contains(&e.to_lowercase().as_ref()))
+}
+
+impl Catalogue {
+    /// Returns every song of every group.
+    pub fn songs(&self) -> impl Iterator<Item = &SongDirectory> {
+        self.groups.iter().flat_map(|g| g.songs.iter())
+    }
+
+    /// Returns every song together with the folders it was loaded from, e.g. to find the songs
+    /// of a course.
+    pub fn loaded_songs(&self) -> Vec<LoadedSong<'_>> {
+        self.groups
+            .iter()
+            .flat_map(|group| {
+                group.songs.iter().map(move |song| LoadedSong {
+                    group: &group.name,
+                    folder: song.path.file_name().and_then(|n| n.to_str()).unwrap_or(""),
+                    simfile: &song.simfile,
+                })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,288 @@
+use crate::simfile::{Chart, ChartDifficulty, Simfile};
+use crate::{read_sections, SimfileParseError};
+use std::io::BufRead;
+
+/// Represents a parsed Stepmania course (.crs), a list of songs that are played in a row.
+#[derive(Debug, Clone, Default)]
+pub struct Course {
+    /// The name of the course.
+    pub name: Option<String>,
+    /// The transliterated name of the course.
+    pub name_translit: Option<String>,
+    /// The person who made the course.
+    pub scripter: Option<String>,
+    pub description: Option<String>,
+    /// Path to the Banner. (Relative from the course file.)
+    pub banner_path: Option<String>,
+    /// Path to the Background. (Relative from the course file.)
+    pub background_path: Option<String>,
+    /// Whether the course starts over after the last song, for endless courses.
+    pub repeat: bool,
+    /// The amount of lives when the course is played with a life battery instead of a life bar.
+    pub lives: Option<u32>,
+    /// The meter of the course for each difficulty it sets one for.
+    pub meters: Vec<(ChartDifficulty, u16)>,
+    pub entries: Vec<CourseEntry>,
+}
+
+/// A song of a course.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CourseEntry {
+    pub song: CourseSong,
+    /// The chart that is played, or `None` for the difficulty the course is played on.
+    pub chart: Option<CourseChart>,
+    /// The modifiers used for the whole song, e.g. "1.5x" or "noshowcourse".
+    pub modifiers: Vec<String>,
+    /// The modifiers that are turned on during the song.
+    pub attacks: Vec<Attack>,
+    /// The seconds added to the timer when the song is started, for survival courses.
+    pub gain_seconds: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CourseSong {
+    /// A specific song, as "Group/Song" or only "Song"
+    Fixed(String),
+    /// A random song from a group
+    RandomInGroup(String),
+    /// A random song from any group
+    Random,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CourseChart {
+    /// The chart with the given difficulty
+    Difficulty(ChartDifficulty),
+    /// A chart with a meter between the two values, including both
+    MeterRange(u16, u16),
+}
+
+/// Modifiers that are turned on for a while during a song.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attack {
+    /// The time the modifiers are turned on, in seconds from the start of the song
+    pub start: f32,
+    /// How long the modifiers stay on in seconds
+    pub length: f32,
+    pub modifiers: Vec<String>,
+}
+
+/// A loaded song that a course can pick, together with the folders it was loaded from.
+#[derive(Debug, Clone, Copy)]
+pub struct LoadedSong<'a> {
+    /// The name of the group folder the song is in
+    pub group: &'a str,
+    /// The name of the folder of the song
+    pub folder: &'a str,
+    pub simfile: &'a Simfile,
+}
+
+/// Parses a Stepmania course (.crs).
+///
+/// `#GAINSECONDS` applies to the `#SONG` entries after it, and the attacks of `#MODS` tags are
+/// added to the next `#SONG` entry.
+pub fn parse_course<R: BufRead>(reader: &mut R) -> Result<Course, SimfileParseError> {
+    let mut course = Course::default();
+    let mut gain_seconds = 0.0;
+    let mut attacks = vec![];
+
+    for (key, value) in read_sections(reader)? {
+        match key.as_ref() {
+            "COURSE" => course.name = value,
+            "COURSETRANSLIT" => course.name_translit = value,
+            "SCRIPTER" => course.scripter = value,
+            "DESCRIPTION" => course.description = value,
+            "BANNER" => course.banner_path = value,
+            "BACKGROUND" => course.background_path = value,
+            "REPEAT" => course.repeat = value.is_some_and(|v| v.eq_ignore_ascii_case("YES")),
+            "LIVES" => course.lives = Some(parse_number(&value.unwrap_or_default())? as u32),
+            "METER" => course.meters.push(parse_meter(&value.unwrap_or_default())?),
+            "GAINSECONDS" => gain_seconds = parse_number(&value.unwrap_or_default())?,
+            "MODS" => attacks.extend(parse_attacks(&value.unwrap_or_default())?),
+            "SONG" => {
+                let mut entry = parse_song(&value.unwrap_or_default())?;
+                entry.gain_seconds = gain_seconds;
+                entry.attacks = std::mem::take(&mut attacks);
+                course.entries.push(entry);
+            }
+            _ => {}
+        }
+    }
+
+    Ok(course)
+}
+
+fn parse_number(value: &str) -> Result<f32, SimfileParseError> {
+    match value.trim().parse() {
+        Ok(number) => Ok(number),
+        Err(_) => Err(SimfileParseError::InvalidCourseData),
+    }
+}
+
+/// Parses a meter, "Difficulty:meter", or only the meter for the medium difficulty.
+fn parse_meter(value: &str) -> Result<(ChartDifficulty, u16), SimfileParseError> {
+    let values: Vec<&str> = value.split(':').collect();
+    let difficulty = match values.len() {
+        1 => ChartDifficulty::Medium,
+        2 => match ChartDifficulty::from_name(values[0]) {
+            Some(difficulty) => difficulty,
+            None => return Err(SimfileParseError::InvalidCourseData),
+        },
+        _ => return Err(SimfileParseError::InvalidCourseData),
+    };
+
+    Ok((difficulty, parse_number(values[values.len() - 1])? as u16))
+}
+
+/// Parses a song entry, "song:difficulty:modifiers", where the difficulty can also be a meter
+/// range like "5..7".
+fn parse_song(value: &str) -> Result<CourseEntry, SimfileParseError> {
+    let values: Vec<&str> = value.split(':').map(|v| v.trim()).collect();
+
+    let song = match values[0] {
+        "" => return Err(SimfileParseError::InvalidCourseData),
+        "*" => CourseSong::Random,
+        song => match song.strip_suffix("/*") {
+            Some(group) => CourseSong::RandomInGroup(group.to_string()),
+            None => CourseSong::Fixed(song.to_string()),
+        },
+    };
+
+    let chart = match values.get(1).cloned().unwrap_or("") {
+        "" => None,
+        chart => match chart.find("..") {
+            Some(i) => Some(CourseChart::MeterRange(
+                parse_number(&chart[..i])? as u16,
+                parse_number(&chart[i + 2..])? as u16,
+            )),
+            None => match ChartDifficulty::from_name(chart) {
+                Some(difficulty) => Some(CourseChart::Difficulty(difficulty)),
+                None => return Err(SimfileParseError::InvalidCourseData),
+            },
+        },
+    };
+
+    Ok(CourseEntry {
+        song,
+        chart,
+        modifiers: split_modifiers(values.get(2).cloned().unwrap_or("")),
+        attacks: Vec::new(),
+        gain_seconds: 0.0,
+    })
+}
+
+/// Parses attacks, e.g. "TIME=10:LEN=5:MODS=drunk,2x:TIME=20:END=30:MODS=dark". Every attack
+/// ends with its modifiers.
+fn parse_attacks(value: &str) -> Result<Vec<Attack>, SimfileParseError> {
+    let mut attacks = vec![];
+    let mut start = 0.0;
+    let mut length = 0.0;
+
+    for part in value.split(':').map(|p| p.trim()).filter(|p| !p.is_empty()) {
+        let (key, value) = match part.find('=') {
+            Some(i) => (part[..i].trim().to_uppercase(), &part[i + 1..]),
+            None => return Err(SimfileParseError::InvalidCourseData),
+        };
+
+        match key.as_ref() {
+            "TIME" => start = parse_number(value)?,
+            "LEN" => length = parse_number(value)?,
+            "END" => length = parse_number(value)? - start,
+            "MODS" => attacks.push(Attack {
+                start,
+                length,
+                modifiers: split_modifiers(value),
+            }),
+            _ => return Err(SimfileParseError::InvalidCourseData),
+        }
+    }
+
+    Ok(attacks)
+}
+
+/// Returns true if `path`, "Group/Song" or only "Song", refers to the song loaded from the
+/// `group` and `folder` folders. The song is matched by its folder or title, ignoring case.
+pub(crate) fn song_path_matches(path: &str, group: &str, folder: &str, simfile: &Simfile) -> bool {
+    let (path_group, name) = match path.rfind('/') {
+        Some(i) => (Some(&path[..i]), &path[i + 1..]),
+        None => (None, path),
+    };
+
+    path_group.map_or(true, |g| g.eq_ignore_ascii_case(group))
+        && (folder.eq_ignore_ascii_case(name)
+            || simfile
+                .title
+                .as_ref()
+                .is_some_and(|t| t.eq_ignore_ascii_case(name)))
+}
+
+fn split_modifiers(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|m| m.trim())
+        .filter(|m| !m.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+impl Course {
+    /// Returns the meter of the course for a difficulty, if it sets one.
+    pub fn meter(&self, difficulty: ChartDifficulty) -> Option<u16> {
+        self.meters
+            .iter()
+            .find(|(d, _)| *d == difficulty)
+            .map(|(_, meter)| *meter)
+    }
+
+    /// Returns the entries with a fixed song that isn't in `songs`.
+    pub fn missing_songs(&self, songs: &[LoadedSong]) -> Vec<&CourseEntry> {
+        self.entries
+            .iter()
+            .filter(|e| matches!(e.song, CourseSong::Fixed(_)) && e.find_songs(songs).is_empty())
+            .collect()
+    }
+}
+
+impl CourseEntry {
+    /// Returns the songs that can be played for this entry, which is a single song for a fixed
+    /// song and every song that can be picked for a random song.
+    ///
+    /// Songs are matched by their folder or title, ignoring case.
+    pub fn find_songs<'a>(&self, songs: &[LoadedSong<'a>]) -> Vec<LoadedSong<'a>> {
+        let found = songs.iter().filter(|song| match &self.song {
+            CourseSong::Fixed(path) => {
+                song_path_matches(path, song.group, song.folder, song.simfile)
+            }
+            CourseSong::RandomInGroup(group) => song.group.eq_ignore_ascii_case(group),
+            CourseSong::Random => true,
+        });
+
+        // A fixed song only ever plays one song, even if there are several with the same name
+        match self.song {
+            CourseSong::Fixed(_) => found.take(1).cloned().collect(),
+            _ => found.cloned().collect(),
+        }
+    }
+
+    /// Returns the charts of a song that can be played for this entry, when the course is
+    /// played on `difficulty` with charts of `chart_type`.
+    pub fn find_charts<'a>(
+        &self,
+        simfile: &'a Simfile,
+        chart_type: &str,
+        difficulty: ChartDifficulty,
+    ) -> Vec<&'a Chart> {
+        simfile
+            .charts
+            .iter()
+            .filter(|chart| chart.chart_type == chart_type)
+            .filter(|chart| match &self.chart {
+                Some(CourseChart::Difficulty(d)) => chart.difficulty == *d,
+                Some(CourseChart::MeterRange(low, high)) => {
+                    chart.meter >= *low && chart.meter <= *high
+                }
+                None => chart.difficulty == difficulty,
+            })
+            .collect()
+    }
+}
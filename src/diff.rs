@@ -0,0 +1,379 @@
+use crate::simfile::{
+    Chart, ChartDifficulty, ChartTiming, DisplayBPM, NoteType, Simfile, ROWS_PER_BEAT,
+};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// The changes between two versions of a simfile.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SimfileDiff {
+    /// The song tags that changed, like the title or the offset
+    pub headers: Vec<FieldChange>,
+    /// The BPM changes, stops and delays of the song timing that changed
+    pub timing: Vec<TimingChange>,
+    /// The charts that were added, removed or changed
+    pub charts: Vec<ChartDiff>,
+}
+
+/// A tag that changed, with the values formatted as text, or `None` when it isn't set.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldChange {
+    /// The name of the tag, e.g. "TITLE"
+    pub field: &'static str,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimingKind {
+    Bpm,
+    Stop,
+    Delay,
+}
+
+/// A BPM change, stop or delay that was added, removed or changed. The value is the BPM or the
+/// length in seconds, or `None` when there's none on the beat.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimingChange {
+    pub kind: TimingKind,
+    pub beat: f32,
+    pub old: Option<f32>,
+    pub new: Option<f32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChartStatus {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// The changes to a chart, which is matched between the simfiles by its chart type and
+/// difficulty, and also by its author for edits.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChartDiff {
+    pub chart_type: String,
+    pub difficulty: ChartDifficulty,
+    pub author: Option<String>,
+    pub status: ChartStatus,
+    /// The tags of the chart that changed, like the meter
+    pub fields: Vec<FieldChange>,
+    /// The changes to the timing of the chart, when either version has its own timing
+    pub timing: Vec<TimingChange>,
+    pub notes: Vec<NoteEdit>,
+}
+
+/// A note that was added, removed or replaced, `NoteType::None` is used for no note.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NoteEdit {
+    pub beat: f32,
+    pub column: usize,
+    pub old: NoteType,
+    pub new: NoteType,
+}
+
+/// Compares two versions of a simfile.
+///
+/// Charts are matched by chart type and difficulty, and also by author for edits. Notes are
+/// compared by beat and column, so a measure that is written with more rows but has the same
+/// notes isn't a change.
+pub fn diff_simfiles(old: &Simfile, new: &Simfile) -> SimfileDiff {
+    let mut diff = SimfileDiff {
+        headers: diff_fields(&header_fields(old), &header_fields(new)),
+        timing: diff_timing(
+            &ChartTiming::from_simfile(old),
+            &ChartTiming::from_simfile(new),
+        ),
+        charts: vec![],
+    };
+
+    let mut unmatched: Vec<&Chart> = new.charts.iter().collect();
+    for old_chart in &old.charts {
+        match unmatched.iter().position(|c| same_chart(old_chart, c)) {
+            Some(i) => {
+                let new_chart = unmatched.remove(i);
+                let chart_diff = diff_charts(old, old_chart, new, new_chart);
+                if !chart_diff.fields.is_empty()
+                    || !chart_diff.timing.is_empty()
+                    || !chart_diff.notes.is_empty()
+                {
+                    diff.charts.push(chart_diff);
+                }
+            }
+            None => diff
+                .charts
+                .push(chart_diff(old_chart, ChartStatus::Removed)),
+        }
+    }
+    for new_chart in unmatched {
+        diff.charts.push(chart_diff(new_chart, ChartStatus::Added));
+    }
+
+    diff
+}
+
+impl SimfileDiff {
+    /// Returns true if the simfiles are the same.
+    pub fn is_empty(&self) -> bool {
+        self.headers.is_empty() && self.timing.is_empty() && self.charts.is_empty()
+    }
+}
+
+/// Returns true if the charts are versions of the same chart.
+pub(crate) fn same_chart(old: &Chart, new: &Chart) -> bool {
+    old.chart_type == new.chart_type
+        && old.difficulty == new.difficulty
+        && (old.difficulty != ChartDifficulty::Edit || old.author == new.author)
+}
+
+fn chart_diff(chart: &Chart, status: ChartStatus) -> ChartDiff {
+    ChartDiff {
+        chart_type: chart.chart_type.clone(),
+        difficulty: chart.difficulty.clone(),
+        author: chart.author.clone(),
+        status,
+        fields: vec![],
+        timing: vec![],
+        notes: vec![],
+    }
+}
+
+fn diff_charts(old_sim: &Simfile, old: &Chart, new_sim: &Simfile, new: &Chart) -> ChartDiff {
+    let mut diff = chart_diff(new, ChartStatus::Changed);
+    diff.fields = diff_fields(&chart_fields(old), &chart_fields(new));
+
+    if old.timing.is_some() || new.timing.is_some() {
+        let timing = |sim: &Simfile, chart: &Chart| {
+            chart
+                .timing
+                .clone()
+                .unwrap_or_else(|| ChartTiming::from_simfile(sim))
+        };
+        diff.timing = diff_timing(&timing(old_sim, old), &timing(new_sim, new));
+    }
+
+    let notes = |chart: &Chart| -> BTreeMap<(u32, usize), NoteType> {
+        chart
+            .notes()
+            .into_iter()
+            .map(|n| ((n.row, n.column), n.note_type))
+            .collect()
+    };
+    let (old_notes, new_notes) = (notes(old), notes(new));
+    let mut positions: Vec<&(u32, usize)> = old_notes.keys().chain(new_notes.keys()).collect();
+    positions.sort();
+    positions.dedup();
+
+    for position in positions {
+        let old_note = old_notes.get(position).unwrap_or(&NoteType::None);
+        let new_note = new_notes.get(position).unwrap_or(&NoteType::None);
+        if old_note != new_note {
+            diff.notes.push(NoteEdit {
+                beat: position.0 as f32 / ROWS_PER_BEAT as f32,
+                column: position.1,
+                old: old_note.clone(),
+                new: new_note.clone(),
+            });
+        }
+    }
+
+    diff
+}
+
+fn diff_fields(
+    old: &[(&'static str, Option<String>)],
+    new: &[(&'static str, Option<String>)],
+) -> Vec<FieldChange> {
+    old.iter()
+        .zip(new.iter())
+        .filter(|((_, old), (_, new))| old != new)
+        .map(|((field, old), (_, new))| FieldChange {
+            field,
+            old: old.clone(),
+            new: new.clone(),
+        })
+        .collect()
+}
+
+fn header_fields(sim: &Simfile) -> Vec<(&'static str, Option<String>)> {
+    let number = |value: Option<f32>| value.map(|v| v.to_string());
+    vec![
+        ("TITLE", sim.title.clone()),
+        ("SUBTITLE", sim.subtitle.clone()),
+        ("ARTIST", sim.artist.clone()),
+        ("TITLETRANSLIT", sim.title_translit.clone()),
+        ("SUBTITLETRANSLIT", sim.subtitle_translit.clone()),
+        ("ARTISTTRANSLIT", sim.artist_translit.clone()),
+        ("GENRE", sim.genre.clone()),
+        ("ORIGIN", sim.origin.clone()),
+        ("CREDIT", sim.credit.clone()),
+        ("BANNER", sim.banner_path.clone()),
+        ("BACKGROUND", sim.background_path.clone()),
+        ("PREVIEWVID", sim.preview_video_path.clone()),
+        ("JACKET", sim.jacket_path.clone()),
+        ("CDIMAGE", sim.cd_image_path.clone()),
+        ("DISCIMAGE", sim.disc_image_path.clone()),
+        ("LYRICSPATH", sim.lyrics_path.clone()),
+        ("CDTITLE", sim.cd_title_path.clone()),
+        ("MUSIC", sim.music_path.clone()),
+        ("PREVIEW", sim.preview_path.clone()),
+        ("OFFSET", number(sim.offset)),
+        ("SAMPLESTART", number(sim.sample_start)),
+        ("SAMPLELENGTH", number(sim.sample_length)),
+        ("SELECTABLE", sim.selectable.as_ref().map(|s| s.to_string())),
+        (
+            "DISPLAYBPM",
+            sim.display_bpm.as_ref().map(|bpm| match bpm {
+                DisplayBPM::Single(bpm) => bpm.to_string(),
+                DisplayBPM::Range(min, max) => format!("{}:{}", min, max),
+                DisplayBPM::Random => "*".to_string(),
+            }),
+        ),
+        ("BPMLOCK", sim.bpm_lock.map(|lock| lock.to_string())),
+    ]
+}
+
+fn chart_fields(chart: &Chart) -> Vec<(&'static str, Option<String>)> {
+    vec![
+        ("DESCRIPTION", chart.author.clone()),
+        ("DIFFICULTY", Some(chart.difficulty_name.clone())),
+        ("METER", Some(chart.meter.to_string())),
+        ("CREDIT", chart.credit.clone()),
+    ]
+}
+
+fn diff_timing(old: &ChartTiming, new: &ChartTiming) -> Vec<TimingChange> {
+    let bpms = |t: &ChartTiming| t.bpms.iter().map(|b| (b.beat, b.bpm)).collect();
+    let stops = |t: &ChartTiming| t.stops.iter().map(|s| (s.beat, s.time)).collect();
+    let delays = |t: &ChartTiming| t.delays.iter().map(|d| (d.beat, d.time)).collect();
+
+    let mut changes = diff_events(TimingKind::Bpm, bpms(old), bpms(new));
+    changes.extend(diff_events(TimingKind::Stop, stops(old), stops(new)));
+    changes.extend(diff_events(TimingKind::Delay, delays(old), delays(new)));
+    changes
+}
+
+/// Compares timing events by beat, rounded to the nearest row so that tiny differences from
+/// writing the beats with a different precision aren't changes.
+fn diff_events(kind: TimingKind, old: Vec<(f32, f32)>, new: Vec<(f32, f32)>) -> Vec<TimingChange> {
+    let by_row = |events: Vec<(f32, f32)>| -> BTreeMap<i64, (f32, f32)> {
+        events
+            .into_iter()
+            .map(|(beat, value)| ((beat * ROWS_PER_BEAT as f32).round() as i64, (beat, value)))
+            .collect()
+    };
+    let (old, new) = (by_row(old), by_row(new));
+    let mut rows: Vec<&i64> = old.keys().chain(new.keys()).collect();
+    rows.sort();
+    rows.dedup();
+
+    rows.into_iter()
+        .filter_map(|row| {
+            let (old, new) = (old.get(row), new.get(row));
+            let beat = old.or(new).map(|(beat, _)| *beat)?;
+            let (old, new) = (old.map(|(_, v)| *v), new.map(|(_, v)| *v));
+            if old == new {
+                return None;
+            }
+            Some(TimingChange {
+                kind,
+                beat,
+                old,
+                new,
+            })
+        })
+        .collect()
+}
+
+fn note_name(note: &NoteType) -> &'static str {
+    match note {
+        NoteType::None => "empty",
+        NoteType::Normal => "tap",
+        NoteType::HoldHead => "hold head",
+        NoteType::HoldOrRollTail => "tail",
+        NoteType::RollHead => "roll head",
+        NoteType::Mine => "mine",
+        NoteType::AutomaticKeysound => "keysound",
+        NoteType::LiftNote => "lift",
+        NoteType::FakeNote => "fake",
+        NoteType::InvalidNote => "invalid",
+    }
+}
+
+fn write_field(f: &mut fmt::Formatter, indent: &str, change: &FieldChange) -> fmt::Result {
+    let value = |value: &Option<String>| match value {
+        Some(value) => format!("{:?}", value),
+        None => "none".to_string(),
+    };
+    writeln!(
+        f,
+        "{}{}: {} -> {}",
+        indent,
+        change.field,
+        value(&change.old),
+        value(&change.new)
+    )
+}
+
+fn write_timing(f: &mut fmt::Formatter, indent: &str, change: &TimingChange) -> fmt::Result {
+    let value = |value: Option<f32>| value.map_or("none".to_string(), |v| v.to_string());
+    let kind = match change.kind {
+        TimingKind::Bpm => "BPM",
+        TimingKind::Stop => "Stop",
+        TimingKind::Delay => "Delay",
+    };
+    writeln!(
+        f,
+        "{}{} at beat {}: {} -> {}",
+        indent,
+        kind,
+        change.beat,
+        value(change.old),
+        value(change.new)
+    )
+}
+
+/// Formats the changes with one change on every line, and the changes to a chart indented below
+/// the chart.
+impl fmt::Display for SimfileDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for change in &self.headers {
+            write_field(f, "", change)?;
+        }
+        for change in &self.timing {
+            write_timing(f, "", change)?;
+        }
+
+        for chart in &self.charts {
+            write!(f, "{} {}", chart.chart_type, chart.difficulty)?;
+            if let Some(author) = &chart.author {
+                write!(f, " ({})", author)?;
+            }
+            let status = match chart.status {
+                ChartStatus::Added => "added",
+                ChartStatus::Removed => "removed",
+                ChartStatus::Changed => "changed",
+            };
+            writeln!(f, ": {}", status)?;
+
+            for change in &chart.fields {
+                write_field(f, "  ", change)?;
+            }
+            for change in &chart.timing {
+                write_timing(f, "  ", change)?;
+            }
+            for edit in &chart.notes {
+                writeln!(
+                    f,
+                    "  beat {}, column {}: {} -> {}",
+                    edit.beat,
+                    edit.column,
+                    note_name(&edit.old),
+                    note_name(&edit.new)
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,272 @@
+use crate::simfile::{Chart, NoteType, Simfile};
+use crate::timing::TimingData;
+
+/// How far apart in seconds two notes can be to be counted as the same note.
+const MATCH_TOLERANCE: f32 = 0.01;
+
+/// Offsets closer than this many seconds are counted as the same.
+const OFFSET_TOLERANCE: f32 = 0.0005;
+
+/// Identifies a chart among the simfiles given to `find_duplicates`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChartId {
+    /// The position of the simfile in the list
+    pub simfile: usize,
+    /// The position of the chart in `Simfile::charts`
+    pub chart: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DuplicateKind {
+    /// The charts have the same notes at the same times
+    Exact,
+    /// The charts have the same notes, but the song offset is different
+    OffsetShifted,
+    /// More notes match when the lanes of one chart are flipped
+    Mirrored,
+    /// The charts share most of their notes
+    Similar,
+}
+
+/// How much two charts look alike.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChartComparison {
+    pub kind: DuplicateKind,
+    /// The share of the notes of both charts that are in the other chart, from 0 to 1
+    pub similarity: f32,
+    /// The offset of the second chart minus the offset of the first, in seconds
+    pub offset_difference: f32,
+}
+
+/// Two charts that are duplicates of each other.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Duplicate {
+    pub first: ChartId,
+    pub second: ChartId,
+    pub comparison: ChartComparison,
+}
+
+/// The kinds of notes that are compared, other notes like hold tails or fakes are left out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum NoteClass {
+    Tap,
+    Hold,
+    Mine,
+}
+
+/// The notes of a chart as times from beat 0, so the offset doesn't change them.
+struct Fingerprint {
+    chart_type: String,
+    columns: usize,
+    offset: f32,
+    /// The times of the notes, by column and class
+    notes: Vec<(usize, NoteClass, f32)>,
+}
+
+impl Fingerprint {
+    fn new(simfile: &Simfile, chart: &Chart) -> Option<Fingerprint> {
+        let columns = chart.columns()?;
+        let mut timing = TimingData::from_chart(simfile, chart);
+        let offset = timing.offset;
+        timing.offset = 0.0;
+
+        let mut notes: Vec<(usize, NoteClass, f32)> = chart
+            .notes()
+            .iter()
+            .filter_map(|note| {
+                let class = match note.note_type {
+                    NoteType::Normal | NoteType::LiftNote => NoteClass::Tap,
+                    NoteType::HoldHead | NoteType::RollHead => NoteClass::Hold,
+                    NoteType::Mine => NoteClass::Mine,
+                    _ => return None,
+                };
+                Some((note.column, class, timing.seconds_at_beat(note.beat())))
+            })
+            .collect();
+        sort_notes(&mut notes);
+
+        Some(Fingerprint {
+            chart_type: chart.chart_type.trim().to_lowercase(),
+            columns,
+            offset,
+            notes,
+        })
+    }
+
+    /// Returns the amount of notes that are in both charts, with the lanes of the other chart
+    /// flipped if `mirrored` is set.
+    fn matching_notes(&self, other: &Fingerprint, mirrored: bool) -> usize {
+        let mut other_notes: Vec<(usize, NoteClass, f32)> = other
+            .notes
+            .iter()
+            .map(|&(column, class, time)| {
+                if mirrored {
+                    (other.columns - 1 - column, class, time)
+                } else {
+                    (column, class, time)
+                }
+            })
+            .collect();
+        if mirrored {
+            sort_notes(&mut other_notes);
+        }
+
+        // Both lists are sorted by lane, class and time, so they can be walked side by side
+        let (mut i, mut j, mut matches) = (0, 0, 0);
+        while i < self.notes.len() && j < other_notes.len() {
+            let (a, b) = (self.notes[i], other_notes[j]);
+            if (a.0, a.1) != (b.0, b.1) {
+                if (a.0, a.1) < (b.0, b.1) {
+                    i += 1;
+                } else {
+                    j += 1;
+                }
+            } else if (a.2 - b.2).abs() <= MATCH_TOLERANCE {
+                matches += 1;
+                i += 1;
+                j += 1;
+            } else if a.2 < b.2 {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+
+        matches
+    }
+
+    fn compare(&self, other: &Fingerprint) -> ChartComparison {
+        let total = self.notes.len() + other.notes.len();
+        let straight = self.matching_notes(other, false);
+        let mirrored = self.matching_notes(other, true);
+        let offset_difference = other.offset - self.offset;
+
+        let matches = straight.max(mirrored);
+        let kind = if mirrored > straight {
+            DuplicateKind::Mirrored
+        } else if 2 * matches < total {
+            DuplicateKind::Similar
+        } else if offset_difference.abs() > OFFSET_TOLERANCE {
+            DuplicateKind::OffsetShifted
+        } else {
+            DuplicateKind::Exact
+        };
+
+        ChartComparison {
+            kind,
+            similarity: match total {
+                0 => 1.0,
+                _ => 2.0 * matches as f32 / total as f32,
+            },
+            offset_difference,
+        }
+    }
+}
+
+/// Sorts notes by lane, class and time.
+fn sort_notes(notes: &mut [(usize, NoteClass, f32)]) {
+    notes.sort_by(|a, b| {
+        (a.0, a.1)
+            .cmp(&(b.0, b.1))
+            .then(a.2.partial_cmp(&b.2).unwrap())
+    });
+}
+
+/// Compares two charts by their notes and timing. Returns `None` if the charts have different
+/// chart types or a chart type is unknown.
+///
+/// Notes are compared by the time they are hit from beat 0, so charts with different timing that
+/// line up still match, and by lane and kind (taps, hold or roll heads and mines). A difference
+/// in offset is reported apart instead of making the notes not match.
+pub fn compare_charts(
+    first_simfile: &Simfile,
+    first: &Chart,
+    second_simfile: &Simfile,
+    second: &Chart,
+) -> Option<ChartComparison> {
+    let first = Fingerprint::new(first_simfile, first)?;
+    let second = Fingerprint::new(second_simfile, second)?;
+    if first.chart_type != second.chart_type {
+        return None;
+    }
+
+    Some(first.compare(&second))
+}
+
+/// Finds the charts of the same chart type that are duplicates of each other, with a similarity
+/// (see `compare_charts`) of at least `min_similarity`. Charts without any notes are left out.
+///
+/// Charts are only compared when their amounts of notes are close enough to reach
+/// `min_similarity`, so a high `min_similarity` is a lot faster on large libraries.
+pub fn find_duplicates<'a, I>(simfiles: I, min_similarity: f32) -> Vec<Duplicate>
+where
+    I: IntoIterator<Item = &'a Simfile>,
+{
+    let mut charts: Vec<(ChartId, Fingerprint)> = simfiles
+        .into_iter()
+        .enumerate()
+        .flat_map(|(s, simfile)| {
+            simfile
+                .charts
+                .iter()
+                .enumerate()
+                .filter_map(move |(c, chart)| {
+                    let id = ChartId {
+                        simfile: s,
+                        chart: c,
+                    };
+                    Fingerprint::new(simfile, chart).map(|f| (id, f))
+                })
+        })
+        .filter(|(_, f)| !f.notes.is_empty())
+        .collect();
+    charts.sort_by(|(_, a), (_, b)| {
+        (&a.chart_type, a.notes.len()).cmp(&(&b.chart_type, b.notes.len()))
+    });
+
+    let mut duplicates = vec![];
+    for (i, (first_id, first)) in charts.iter().enumerate() {
+        for (second_id, second) in &charts[i + 1..] {
+            // The similarity can't be higher than this, and it only gets lower for the next
+            // charts since they have more notes
+            let best =
+                2.0 * first.notes.len() as f32 / (first.notes.len() + second.notes.len()) as f32;
+            if second.chart_type != first.chart_type || best < min_similarity {
+                break;
+            }
+
+            let comparison = first.compare(second);
+            if comparison.similarity >= min_similarity {
+                // Charts are sorted by notes here, but are reported in the order they were given
+                let in_order =
+                    (first_id.simfile, first_id.chart) < (second_id.simfile, second_id.chart);
+                duplicates.push(if in_order {
+                    Duplicate {
+                        first: *first_id,
+                        second: *second_id,
+                        comparison,
+                    }
+                } else {
+                    Duplicate {
+                        first: *second_id,
+                        second: *first_id,
+                        comparison: ChartComparison {
+                            offset_difference: -comparison.offset_difference,
+                            ..comparison
+                        },
+                    }
+                });
+            }
+        }
+    }
+
+    duplicates.sort_by_key(|d| {
+        (
+            d.first.simfile,
+            d.first.chart,
+            d.second.simfile,
+            d.second.chart,
+        )
+    });
+    duplicates
+}
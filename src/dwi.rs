@@ -0,0 +1,313 @@
+use crate::simfile::{
+    columns_for_chart_type, note_data_from_notes, Chart, ChartDifficulty, DisplayBPM, NoteType,
+    Simfile, Stop, TimedNote, BPM, ROWS_PER_BEAT,
+};
+use crate::{parse_key_value_list, read_sections, SimfileParseError};
+use std::collections::BTreeMap;
+use std::io::BufRead;
+
+/// The panels of a dance pad, in the order of the lanes in a "dance-solo" chart.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Panel {
+    Left,
+    UpLeft,
+    Down,
+    Up,
+    UpRight,
+    Right,
+}
+
+const SINGLE_PANELS: [Panel; 4] = [Panel::Left, Panel::Down, Panel::Up, Panel::Right];
+const SOLO_PANELS: [Panel; 6] = [
+    Panel::Left,
+    Panel::UpLeft,
+    Panel::Down,
+    Panel::Up,
+    Panel::UpRight,
+    Panel::Right,
+];
+
+/// Parses a Dance With Intensity simfile (.dwi).
+///
+/// DWI positions BPM changes and freezes in 16th notes, and the freeze length and gap in
+/// milliseconds, these are converted to beats and seconds like in a .sm file.
+pub fn parse_dwi<R: BufRead>(reader: &mut R) -> Result<Simfile, SimfileParseError> {
+    let mut sim = Simfile::new();
+    let mut bpms: Vec<BPM> = vec![];
+
+    for (key, value) in read_sections(reader)? {
+        match key.to_uppercase().as_ref() {
+            "TITLE" => {
+                if let Some(title) = value {
+                    let (title, subtitle) = split_title(&title);
+                    sim.title = Some(title);
+                    sim.subtitle = subtitle;
+                }
+            }
+            "ARTIST" => sim.artist = value,
+            "GENRE" => sim.genre = value,
+            "CDTITLE" => sim.cd_title_path = value,
+            "FILE" => sim.music_path = value,
+            "BPM" => match value.unwrap_or_default().parse() {
+                Ok(bpm) => bpms.insert(0, BPM { beat: 0.0, bpm }),
+                Err(_) => return Err(SimfileParseError::FailedToParseBPMs),
+            },
+            "CHANGEBPM" | "BPMCHANGE" => match parse_key_value_list(value) {
+                Ok(list) => bpms.extend(list.into_iter().map(|x| BPM {
+                    beat: x.key / 4.0,
+                    bpm: x.value,
+                })),
+                Err(_) => return Err(SimfileParseError::FailedToParseBPMs),
+            },
+            "FREEZE" => match parse_key_value_list(value) {
+                Ok(list) => {
+                    sim.stops = list
+                        .into_iter()
+                        .map(|x| Stop {
+                            beat: x.key / 4.0,
+                            time: x.value / 1000.0,
+                        })
+                        .collect()
+                }
+                Err(_) => return Err(SimfileParseError::FailedToParseStops),
+            },
+            "DISPLAYBPM" => sim.display_bpm = parse_display_bpm(value)?,
+            "GAP" => {
+                sim.offset = value
+                    .and_then(|v| v.parse::<f32>().ok())
+                    .map(|gap| -gap / 1000.0)
+            }
+            "SAMPLESTART" => sim.sample_start = value.and_then(|v| parse_time(&v)),
+            "SAMPLELENGTH" => sim.sample_length = value.and_then(|v| parse_time(&v)),
+            "SINGLE" | "DOUBLE" | "COUPLE" | "SOLO" => {
+                sim.charts.push(parse_chart(&key.to_uppercase(), value)?)
+            }
+            _ => {}
+        }
+    }
+
+    bpms.sort_by(|a, b| a.beat.partial_cmp(&b.beat).unwrap());
+    sim.bpms = bpms;
+
+    Ok(sim)
+}
+
+/// Splits a DWI title into the title and subtitle, the same way Stepmania does.
+fn split_title(full_title: &str) -> (String, Option<String>) {
+    for separator in &["\t", " -", " ~", " (", " ["] {
+        if let Some(i) = full_title.find(separator) {
+            let subtitle = full_title[i + 1..].trim().to_string();
+            return (full_title[..i].to_string(), Some(subtitle));
+        }
+    }
+
+    (full_title.to_string(), None)
+}
+
+/// Parses a time in either seconds or the "mm:ss.xx" format.
+fn parse_time(value: &str) -> Option<f32> {
+    let mut seconds = 0.0;
+    for part in value.split(':') {
+        seconds = seconds * 60.0 + part.trim().parse::<f32>().ok()?;
+    }
+    Some(seconds)
+}
+
+fn parse_display_bpm(value: Option<String>) -> Result<Option<DisplayBPM>, SimfileParseError> {
+    let value = match value {
+        Some(v) => v,
+        None => return Ok(None),
+    };
+
+    let parse_bpm = |value: &str| match value.trim().parse::<f32>() {
+        Ok(i) => Ok(i),
+        Err(_) => Err(SimfileParseError::FailedToParseDisplayBPM),
+    };
+
+    let display_bpm = match value.find("..") {
+        Some(i) => DisplayBPM::Range(parse_bpm(&value[..i])?, parse_bpm(&value[i + 2..])?),
+        None if value == "*" => DisplayBPM::Random,
+        None => DisplayBPM::Single(parse_bpm(&value)?),
+    };
+
+    Ok(Some(display_bpm))
+}
+
+fn parse_chart(style: &str, value: Option<String>) -> Result<Chart, SimfileParseError> {
+    let value = match value {
+        Some(v) => v,
+        None => return Err(SimfileParseError::EmptyNotesSection),
+    };
+    let values: Vec<&str> = value.split(':').map(|v| v.trim()).collect();
+
+    let (chart_type, panels, pads): (&str, &[Panel], usize) = match style {
+        "SINGLE" => ("dance-single", &SINGLE_PANELS, 1),
+        "DOUBLE" => ("dance-double", &SINGLE_PANELS, 2),
+        "COUPLE" => ("dance-couple", &SINGLE_PANELS, 2),
+        _ => ("dance-solo", &SOLO_PANELS, 1),
+    };
+
+    if values.len() != 2 + pads {
+        return Err(SimfileParseError::InvalidChartFormat);
+    }
+
+    let difficulty = match ChartDifficulty::from_name(values[0]) {
+        Some(d) => d,
+        None => return Err(SimfileParseError::UnknownChartDifficulty),
+    };
+
+    let meter = match values[1].parse() {
+        Ok(i) => i,
+        Err(_) => return Err(SimfileParseError::FailedToParseChartMeter),
+    };
+
+    let mut notes = vec![];
+    for (pad, data) in values[2..].iter().enumerate() {
+        notes.extend(parse_steps(data, panels, pad * panels.len())?);
+    }
+
+    Ok(Chart {
+        chart_type: chart_type.to_string(),
+        author: None,
+        difficulty,
+        difficulty_name: values[0].to_string(),
+        meter,
+        radar_values: Vec::new(),
+        note_data: note_data_from_notes(columns_for_chart_type(chart_type).unwrap(), &notes),
+        lasers: Vec::new(),
+        timing: None,
+        credit: None,
+    })
+}
+
+/// Returns the panels that a DWI step character corresponds to.
+fn panels_for_step(step: char) -> Result<Vec<Panel>, SimfileParseError> {
+    use self::Panel::*;
+
+    let panels = match step.to_ascii_uppercase() {
+        '0' | '5' => vec![],
+        '1' => vec![Left, Down],
+        '2' => vec![Down],
+        '3' => vec![Down, Right],
+        '4' => vec![Left],
+        '6' => vec![Right],
+        '7' => vec![Left, Up],
+        '8' => vec![Up],
+        '9' => vec![Up, Right],
+        'A' => vec![Up, Down],
+        'B' => vec![Left, Right],
+        'C' => vec![UpLeft],
+        'D' => vec![UpRight],
+        'E' => vec![Left, UpLeft],
+        'F' => vec![UpLeft, Down],
+        'G' => vec![UpLeft, Up],
+        'H' => vec![UpLeft, Right],
+        'I' => vec![Left, UpRight],
+        'J' => vec![Down, UpRight],
+        'K' => vec![Up, UpRight],
+        'L' => vec![UpRight, Right],
+        'M' => vec![UpLeft, UpRight],
+        _ => return Err(SimfileParseError::InvalidDwiNoteData),
+    };
+
+    Ok(panels)
+}
+
+/// Parses the steps for one pad, `column_offset` is added to the column of every note.
+///
+/// Steps are 8th notes unless they are inside brackets: `(` for 16ths, `[` for 24ths, `{` for
+/// 64ths and `` ` `` for 192nds. Steps inside `<>` are hit at the same time, and a `!` after a
+/// step marks which of its panels are holds, which end at the next step on the same panel.
+fn parse_steps(
+    data: &str,
+    panels: &[Panel],
+    column_offset: usize,
+) -> Result<Vec<TimedNote>, SimfileParseError> {
+    let chars: Vec<char> = data.chars().filter(|c| !c.is_whitespace()).collect();
+    let mut steps: BTreeMap<(u32, usize), NoteType> = BTreeMap::new();
+    let mut row = 0;
+    let mut increment = ROWS_PER_BEAT / 2;
+
+    let mut add_steps = |row: u32, step: char, note_type: NoteType| {
+        for panel in panels_for_step(step)? {
+            if let Some(column) = panels.iter().position(|p| *p == panel) {
+                steps.insert((row, column + column_offset), note_type.clone());
+            }
+        }
+        Ok(())
+    };
+
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        i += 1;
+
+        match c {
+            '(' => increment = ROWS_PER_BEAT / 4,
+            '[' => increment = ROWS_PER_BEAT / 6,
+            '{' => increment = ROWS_PER_BEAT / 16,
+            '`' => increment = 1,
+            ')' | ']' | '}' | '\'' => increment = ROWS_PER_BEAT / 2,
+            _ => {
+                let jump = c == '<';
+                let mut step = c;
+                loop {
+                    if jump {
+                        match chars.get(i) {
+                            Some('>') | None => {
+                                i += 1;
+                                break;
+                            }
+                            Some(s) => step = *s,
+                        }
+                        i += 1;
+                    }
+
+                    add_steps(row, step, NoteType::Normal)?;
+                    if chars.get(i) == Some(&'!') {
+                        if let Some(hold) = chars.get(i + 1) {
+                            add_steps(row, *hold, NoteType::HoldHead)?;
+                        }
+                        i += 2;
+                    }
+
+                    if !jump {
+                        break;
+                    }
+                }
+                row += increment;
+            }
+        }
+    }
+
+    Ok(end_holds(steps))
+}
+
+/// Ends every hold at the next step on the same column, the step is replaced by the tail. Holds
+/// that never end are turned into normal notes.
+fn end_holds(steps: BTreeMap<(u32, usize), NoteType>) -> Vec<TimedNote> {
+    let mut notes: Vec<TimedNote> = steps
+        .into_iter()
+        .map(|((row, column), note_type)| TimedNote {
+            row,
+            column,
+            note_type,
+        })
+        .collect();
+
+    let mut open_holds: BTreeMap<usize, usize> = BTreeMap::new();
+    for (i, note) in notes.iter_mut().enumerate() {
+        if open_holds.remove(&note.column).is_some() {
+            note.note_type = NoteType::HoldOrRollTail;
+        } else if note.note_type == NoteType::HoldHead {
+            open_holds.insert(note.column, i);
+        }
+    }
+
+    for (_, i) in open_holds {
+        notes[i].note_type = NoteType::Normal;
+    }
+
+    notes.sort_by_key(|n| (n.row, n.column));
+    notes
+}
@@ -0,0 +1,76 @@
+use crate::course::song_path_matches;
+use crate::simfile::{Chart, ChartDifficulty, Simfile};
+use crate::{parse_chart, read_sections, SimfileParseError};
+use std::io::BufRead;
+
+/// Represents a parsed Stepmania edit file (.edit), which holds charts made for a song that is
+/// loaded from somewhere else.
+#[derive(Debug, Clone)]
+pub struct EditFile {
+    /// The song the charts were made for, as "Group/Song".
+    pub song: String,
+    /// The charts, which are always edits.
+    pub charts: Vec<Chart>,
+}
+
+/// Parses a Stepmania edit file (.edit).
+///
+/// The charts use the same `#NOTES` sections as .sm files, but are always edits no matter the
+/// difficulty they were saved with. Returns `MissingEditSong` if the file doesn't say which song
+/// it's for.
+pub fn parse_edit<R: BufRead>(reader: &mut R) -> Result<EditFile, SimfileParseError> {
+    let mut song = None;
+    let mut charts = vec![];
+
+    for (key, value) in read_sections(reader)? {
+        match key.as_ref() {
+            "SONG" => song = value,
+            "NOTES" => {
+                let mut chart = parse_chart(value)?;
+                chart.difficulty = ChartDifficulty::Edit;
+                charts.push(chart);
+            }
+            _ => {}
+        }
+    }
+
+    match song {
+        Some(song) => Ok(EditFile { song, charts }),
+        None => Err(SimfileParseError::MissingEditSong),
+    }
+}
+
+impl EditFile {
+    /// Returns true if the edit was made for the song loaded from the `group` and `folder`
+    /// folders. The song is matched by its folder or title, ignoring case.
+    pub fn is_for(&self, group: &str, folder: &str, simfile: &Simfile) -> bool {
+        song_path_matches(&self.song, group, folder, simfile)
+    }
+
+    /// Adds the charts of the edit to a simfile.
+    pub fn attach_to(&self, simfile: &mut Simfile) {
+        simfile.charts.extend(self.charts.iter().cloned());
+    }
+}
+
+/// Adds edits to the songs they were made for, out of `songs` given as the group folder, the song
+/// folder and the simfile of every song. Returns the edits whose song couldn't be found.
+pub fn merge_edits<'a, 'e, I>(edits: &'e [EditFile], songs: I) -> Vec<&'e EditFile>
+where
+    I: IntoIterator<Item = (&'a str, &'a str, &'a mut Simfile)>,
+{
+    let mut songs: Vec<(&str, &str, &mut Simfile)> = songs.into_iter().collect();
+    let mut missing = vec![];
+
+    for edit in edits {
+        match songs
+            .iter_mut()
+            .find(|(group, folder, simfile)| edit.is_for(group, folder, simfile))
+        {
+            Some((_, _, simfile)) => edit.attach_to(simfile),
+            None => missing.push(edit),
+        }
+    }
+
+    missing
+}
@@ -0,0 +1,145 @@
+use crate::simfile::Simfile;
+use crate::{bms, dwi, ksh, osu, parse_simfile, qua, sma, ssc, ucs, SimfileParseError};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// The file formats a simfile can be read from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SimfileFormat {
+    /// Stepmania (.sm)
+    Sm,
+    /// Stepmania 5 (.ssc)
+    Ssc,
+    /// StepMania AMX (.sma)
+    Sma,
+    /// Dance With Intensity (.dwi)
+    Dwi,
+    /// Be-Music Source (.bms, .bme, .bml)
+    Bms,
+    /// osu!mania (.osu)
+    Osu,
+    /// Quaver (.qua)
+    Qua,
+    /// K-Shoot Mania (.ksh)
+    Ksh,
+    /// Pump It Up (.ucs)
+    Ucs,
+}
+
+impl SimfileFormat {
+    /// Returns the format that uses the given file extension, ignoring case.
+    pub fn from_extension(extension: &str) -> Option<SimfileFormat> {
+        let format = match extension.to_lowercase().as_ref() {
+            "sm" => SimfileFormat::Sm,
+            "ssc" => SimfileFormat::Ssc,
+            "sma" => SimfileFormat::Sma,
+            "dwi" => SimfileFormat::Dwi,
+            "bms" | "bme" | "bml" => SimfileFormat::Bms,
+            "osu" => SimfileFormat::Osu,
+            "qua" => SimfileFormat::Qua,
+            "ksh" => SimfileFormat::Ksh,
+            "ucs" => SimfileFormat::Ucs,
+            _ => return None,
+        };
+
+        Some(format)
+    }
+
+    /// Guesses the format of a file from its contents, by looking for tags that only appear in
+    /// one format. Returns `None` if it doesn't look like any of the formats.
+    pub fn detect(data: &[u8]) -> Option<SimfileFormat> {
+        let data = String::from_utf8_lossy(data);
+        let data = data.trim_start_matches('\u{feff}').trim_start();
+        let lines = || data.lines().map(|l| l.trim());
+        let has_tag = |tag: &str| lines().any(|l| l.to_uppercase().starts_with(tag));
+
+        let format = if data.starts_with("osu file format") {
+            SimfileFormat::Osu
+        } else if has_tag("#NOTEDATA") {
+            SimfileFormat::Ssc
+        } else if ["#SINGLE:", "#DOUBLE:", "#COUPLE:", "#SOLO:"]
+            .iter()
+            .any(|t| has_tag(t))
+        {
+            SimfileFormat::Dwi
+        } else if has_tag("#ROWSPERBEAT") || has_tag("#BEATSPERMEASURE") {
+            SimfileFormat::Sma
+        } else if has_tag("#NOTES") {
+            SimfileFormat::Sm
+        } else if has_tag(":FORMAT=") || has_tag(":MODE=") {
+            SimfileFormat::Ucs
+        } else if lines().any(|l| l.starts_with("Mode: Keys")) {
+            SimfileFormat::Qua
+        } else if lines().any(|l| l == "--") && lines().any(|l| l.matches('|').count() == 2) {
+            SimfileFormat::Ksh
+        } else if has_tag("#PLAYER") || lines().any(is_bms_channel) {
+            SimfileFormat::Bms
+        } else {
+            return None;
+        };
+
+        Some(format)
+    }
+
+    /// Parses a simfile in this format.
+    pub fn parse<R: BufRead>(self, reader: &mut R) -> Result<Simfile, SimfileParseError> {
+        match self {
+            SimfileFormat::Sm => parse_simfile(reader),
+            SimfileFormat::Ssc => ssc::parse_ssc(reader),
+            SimfileFormat::Sma => sma::parse_sma(reader),
+            SimfileFormat::Dwi => dwi::parse_dwi(reader),
+            SimfileFormat::Bms => bms::parse_bms(reader),
+            SimfileFormat::Osu => osu::parse_osu(reader),
+            SimfileFormat::Qua => qua::parse_qua(reader),
+            SimfileFormat::Ksh => ksh::parse_ksh(reader),
+            SimfileFormat::Ucs => ucs::parse_ucs(reader),
+        }
+    }
+}
+
+/// Returns true for a line of BMS note data, e.g. "#00111:01000100".
+fn is_bms_channel(line: &str) -> bool {
+    let bytes = line.as_bytes();
+    bytes.len() > 7
+        && bytes[0] == b'#'
+        && bytes[1..6].iter().all(|b| b.is_ascii_digit())
+        && bytes[6] == b':'
+}
+
+/// Parses a simfile in any of the supported formats.
+///
+/// The format is `hint` when it's given, usually taken from the file extension, otherwise it's
+/// detected from the contents with `SimfileFormat::detect`. Returns `UnknownSimfileFormat` if the
+/// format can't be detected.
+pub fn parse_any<R: BufRead>(
+    reader: &mut R,
+    hint: Option<SimfileFormat>,
+) -> Result<Simfile, SimfileParseError> {
+    let mut data = vec![];
+    if reader.read_to_end(&mut data).is_err() {
+        return Err(SimfileParseError::BufReadError);
+    }
+
+    let format = match hint.or_else(|| SimfileFormat::detect(&data)) {
+        Some(format) => format,
+        None => return Err(SimfileParseError::UnknownSimfileFormat),
+    };
+    format.parse(&mut data.as_slice())
+}
+
+/// Loads a simfile from a file in any of the supported formats, using the file extension to pick
+/// the format and the contents of the file when the extension is unknown.
+pub fn load_simfile<P: AsRef<Path>>(path: P) -> Result<Simfile, SimfileParseError> {
+    let path = path.as_ref();
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(_) => return Err(SimfileParseError::BufReadError),
+    };
+    let hint = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .and_then(SimfileFormat::from_extension);
+
+    parse_any(&mut BufReader::new(file), hint)
+}
@@ -0,0 +1,175 @@
+use crate::simfile::{columns_for_chart_type, Chart, NoteType, Simfile, BPM};
+use crate::ssc::note_char;
+
+/// The amount of hex digits of the SHA-1 hash that are used as the hash of a chart.
+const HASH_LENGTH: usize = 16;
+
+/// Returns the hash GrooveStats uses to identify a chart, the first 16 hex digits of the SHA-1
+/// hash of the normalized note data followed by the normalized BPMs.
+///
+/// The BPMs are those of the chart when it has its own timing and those of the song otherwise.
+/// Returns `None` if the chart type is unknown, since the note data can't be split into rows, or
+/// if the chart has notes that aren't known, see `normalized_note_data`.
+pub fn chart_hash(simfile: &Simfile, chart: &Chart) -> Option<String> {
+    let bpms = match &chart.timing {
+        Some(timing) => &timing.bpms,
+        None => &simfile.bpms,
+    };
+    let data = normalized_note_data(chart)? + &normalized_bpms(bpms);
+
+    let hash: String = sha1(data.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    Some(hash[..HASH_LENGTH].to_string())
+}
+
+/// Returns the note data of a chart the way GrooveStats hashes it: one row per line, with a ","
+/// line between measures and every measure as short as it can be made by repeatedly dropping
+/// every other row while those rows are empty.
+///
+/// Returns `None` if the chart type is unknown, or if the chart has notes that aren't known.
+/// GrooveStats hashes the characters as they're written in the file, which aren't kept for those
+/// notes, so their hash would never match.
+pub fn normalized_note_data(chart: &Chart) -> Option<String> {
+    let columns = columns_for_chart_type(&chart.chart_type)?;
+    if chart
+        .note_data
+        .iter()
+        .flatten()
+        .any(|n| *n == NoteType::InvalidNote)
+    {
+        return None;
+    }
+
+    let lines: Vec<String> = chart
+        .note_data
+        .iter()
+        .enumerate()
+        .flat_map(|(i, measure)| {
+            let rows: Vec<String> = measure
+                .chunks(columns)
+                .map(|row| row.iter().map(note_char).collect())
+                .collect();
+            let separator = if i + 1 < chart.note_data.len() {
+                Some(",".to_string())
+            } else {
+                None
+            };
+            minimize_measure(rows).into_iter().chain(separator)
+        })
+        .collect();
+    Some(lines.join("\n"))
+}
+
+/// Returns the BPMs the way GrooveStats hashes them, "beat=bpm" with both rounded to 3 decimals
+/// and separated by ",".
+pub fn normalized_bpms(bpms: &[BPM]) -> String {
+    bpms.iter()
+        .map(|b| format!("{}={}", normalize_decimal(b.beat), normalize_decimal(b.bpm)))
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+/// Halves a measure while every other row is empty.
+fn minimize_measure(mut rows: Vec<String>) -> Vec<String> {
+    while !rows.is_empty()
+        && rows.len() % 2 == 0
+        && rows
+            .iter()
+            .skip(1)
+            .step_by(2)
+            .all(|r| r.chars().all(|c| c == '0'))
+    {
+        rows = rows.into_iter().step_by(2).collect();
+    }
+
+    rows
+}
+
+/// Rounds a number to 3 decimals, rounding halves up, and formats it with 3 decimals.
+fn normalize_decimal(value: f32) -> String {
+    // GrooveStats rounds the decimal from the file as a double, which the shortest decimal that
+    // gives back the same f32 is closest to
+    let value: f64 = value.to_string().parse().unwrap_or(0.0);
+    let rounded = (value * 1000.0 + 0.5).floor() / 1000.0;
+    // Avoids "-0.000"
+    format!("{:.3}", rounded + 0.0)
+}
+
+/// The SHA-1 hash of some data.
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut state: [u32; 5] = [
+        0x6745_2301,
+        0xefcd_ab89,
+        0x98ba_dcfe,
+        0x1032_5476,
+        0xc3d2_e1f0,
+    ];
+
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&(data.len() as u64 * 8).to_be_bytes());
+
+    for block in message.chunks(64) {
+        let mut words = [0u32; 80];
+        for (i, word) in block.chunks(4).enumerate() {
+            words[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..80 {
+            words[i] = (words[i - 3] ^ words[i - 8] ^ words[i - 14] ^ words[i - 16]).rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = state;
+        for (i, word) in words.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5a82_7999),
+                20..=39 => (b ^ c ^ d, 0x6ed9_eba1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8f1b_bcdc),
+                _ => (b ^ c ^ d, 0xca62_c1d6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(*word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+
+        for (s, v) in state.iter_mut().zip([a, b, c, d, e].iter()) {
+            *s = s.wrapping_add(*v);
+        }
+    }
+
+    let mut hash = [0; 20];
+    for (i, s) in state.iter().enumerate() {
+        hash[i * 4..i * 4 + 4].copy_from_slice(&s.to_be_bytes());
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sha1_matches_known_hashes() {
+        let hex =
+            |data: &[u8]| -> String { sha1(data).iter().map(|b| format!("{:02x}", b)).collect() };
+
+        assert_eq!(hex(b""), "da39a3ee5e6b4b0d3255bfef95601890afd80709");
+        assert_eq!(hex(b"abc"), "a9993e364706816aba3e25717850c26c9cd0d89d");
+        assert_eq!(
+            hex(&[b'a'; 1000]),
+            "291e9a6c66994949b57ba5e650361e98fc36b1ba"
+        );
+    }
+}
@@ -0,0 +1,318 @@
+use crate::simfile::{
+    columns_for_chart_type, note_data_from_notes, Chart, ChartDifficulty, DisplayBPM, Laser,
+    LaserPoint, LaserSide, NoteType, Simfile, Stop, TimeSignature, TimedNote, BPM, ROWS_PER_BEAT,
+};
+use crate::timing::TimingData;
+use crate::SimfileParseError;
+use std::collections::BTreeMap;
+use std::io::BufRead;
+
+/// The chart type used for K-Shoot Mania charts.
+const CHART_TYPE: &str = "sdvx-single";
+
+/// The characters used for the positions of an analog laser, from the left to the right.
+const LASER_POSITIONS: &str = "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmno";
+
+/// What is placed in a BT or FX lane on a line.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum LaneState {
+    Empty,
+    Chip,
+    Long,
+}
+
+/// The notes of a chart, built one line at a time.
+struct ChartBuilder {
+    notes: Vec<TimedNote>,
+    /// The row every long note that is currently held started on
+    holds: Vec<Option<u32>>,
+    lasers: Vec<Laser>,
+    /// The lasers that are currently active, left and right
+    active_lasers: [Option<Laser>; 2],
+}
+
+/// Parses a K-Shoot Mania chart (.ksh) into a simfile with a single "sdvx-single" chart.
+///
+/// The four BT lanes are the first four columns and the two FX lanes are the last two columns.
+/// The analog lasers are stored in `Chart::lasers` instead of the note data. Every measure is
+/// split evenly between its lines, with a length set by the `beat=` time signature, the positions
+/// are then converted to beats since the note data always uses 4 beats per measure. `t=` and
+/// `stop=` lines change the timing on the position of the next line.
+pub fn parse_ksh<R: BufRead>(reader: &mut R) -> Result<Simfile, SimfileParseError> {
+    let mut data = vec![];
+    if reader.read_to_end(&mut data).is_err() {
+        return Err(SimfileParseError::BufReadError);
+    }
+    let data = String::from_utf8_lossy(&data);
+    // Lines starting with "#" define custom effects, which aren't used
+    let mut lines = data
+        .lines()
+        .map(|l| l.trim().trim_start_matches('\u{feff}'))
+        .filter(|l| !l.is_empty() && !l.starts_with("//") && !l.starts_with('#'));
+
+    let mut headers: BTreeMap<&str, &str> = BTreeMap::new();
+    for line in lines.by_ref() {
+        if line == "--" {
+            break;
+        }
+        if let Some(i) = line.find('=') {
+            headers.insert(&line[..i], &line[i + 1..]);
+        }
+    }
+
+    let mut sim = Simfile::new();
+    let header = |key: &str| {
+        headers
+            .get(key)
+            .filter(|v| !v.is_empty())
+            .map(|v| v.to_string())
+    };
+    sim.title = header("title");
+    sim.artist = header("artist");
+    sim.credit = header("effect");
+    sim.jacket_path = header("jacket");
+    sim.music_path = header("m").and_then(|m| m.split(';').next().map(|m| m.to_string()));
+    sim.offset = Some(-parse_number(&header("o").unwrap_or_default(), 0.0)? / 1000.0);
+    sim.sample_start = header("po")
+        .map(|t| parse_number(&t, 0.0).map(|t| t / 1000.0))
+        .transpose()?;
+    sim.sample_length = header("plength")
+        .map(|t| parse_number(&t, 0.0).map(|t| t / 1000.0))
+        .transpose()?;
+
+    // The BPM in the header is a range when the song changes BPM
+    if let Some(bpm) = header("t") {
+        match bpm.find('-') {
+            Some(i) => {
+                let min = parse_number(&bpm[..i], 0.0)?;
+                let max = parse_number(&bpm[i + 1..], 0.0)?;
+                sim.display_bpm = Some(DisplayBPM::Range(min, max));
+                sim.bpms.push(BPM {
+                    beat: 0.0,
+                    bpm: min,
+                });
+            }
+            None => sim.bpms.push(BPM {
+                beat: 0.0,
+                bpm: parse_number(&bpm, 0.0)?,
+            }),
+        }
+    }
+
+    let columns = columns_for_chart_type(CHART_TYPE).unwrap();
+    let mut builder = ChartBuilder {
+        notes: vec![],
+        holds: vec![None; columns],
+        lasers: vec![],
+        active_lasers: [None, None],
+    };
+    let mut stops: Vec<(f32, f32)> = vec![];
+    let mut measure_start = 0.0;
+    let mut measure: Vec<&str> = vec![];
+
+    for line in lines.chain(std::iter::once("--")) {
+        if line != "--" {
+            measure.push(line);
+            continue;
+        }
+        if measure.is_empty() {
+            continue;
+        }
+
+        // The time signature applies to the whole measure it's in
+        for signature in measure.iter().filter_map(|l| l.strip_prefix("beat=")) {
+            let values: Vec<&str> = signature.split('/').collect();
+            match (values.first(), values.get(1)) {
+                (Some(n), Some(d)) => sim.time_signatures.push(TimeSignature {
+                    beat: measure_start,
+                    numerator: parse_number(n, 0.0)? as u32,
+                    denominator: parse_number(d, 0.0)? as u32,
+                }),
+                _ => return Err(SimfileParseError::InvalidKshData),
+            }
+        }
+        let length = match sim.time_signatures.last() {
+            Some(s) if s.denominator > 0 => 4.0 * s.numerator as f32 / s.denominator as f32,
+            _ => 4.0,
+        };
+
+        let line_count = measure.iter().filter(|l| l.contains('|')).count().max(1);
+        let mut line_index = 0;
+        for line in measure.drain(..) {
+            let beat = measure_start + length * line_index as f32 / line_count as f32;
+            let row = (beat * ROWS_PER_BEAT as f32).round() as u32;
+
+            if line.contains('|') {
+                builder.add_line(row, line)?;
+                line_index += 1;
+            } else if let Some(bpm) = line.strip_prefix("t=") {
+                let bpm = parse_number(bpm, 0.0)?;
+                match sim.bpms.last_mut() {
+                    Some(last) if last.beat == beat => last.bpm = bpm,
+                    _ => sim.bpms.push(BPM { beat, bpm }),
+                }
+            } else if let Some(stop) = line.strip_prefix("stop=") {
+                stops.push((beat, parse_number(stop, 0.0)?));
+            }
+        }
+
+        measure_start += length;
+    }
+
+    let end_row = (measure_start * ROWS_PER_BEAT as f32).round() as u32;
+    let (notes, lasers) = builder.finish(end_row);
+
+    // Stops are measured in 192nds of a 4/4 measure, which needs to be converted to seconds
+    let timing = TimingData::new(0.0, sim.bpms.clone(), vec![]);
+    sim.stops = stops
+        .into_iter()
+        .map(|(beat, length)| Stop {
+            beat,
+            time: length / ROWS_PER_BEAT as f32 * 60.0 / timing.bpm_at_beat(beat),
+        })
+        .collect();
+
+    let difficulty_name = header("difficulty").unwrap_or_default();
+    let difficulty = match difficulty_name.as_ref() {
+        "light" => ChartDifficulty::Easy,
+        "challenge" => ChartDifficulty::Medium,
+        "extended" => ChartDifficulty::Hard,
+        "infinite" => ChartDifficulty::Challenge,
+        name => ChartDifficulty::from_name(name).unwrap_or(ChartDifficulty::Edit),
+    };
+
+    sim.charts.push(Chart {
+        chart_type: CHART_TYPE.to_string(),
+        author: header("effect"),
+        difficulty,
+        difficulty_name,
+        meter: parse_number(&header("level").unwrap_or_default(), 1.0)? as u16,
+        radar_values: Vec::new(),
+        note_data: note_data_from_notes(columns, &notes),
+        lasers,
+        timing: None,
+        credit: None,
+    });
+
+    Ok(sim)
+}
+
+/// Parses a number, an empty value returns `default`.
+fn parse_number(value: &str, default: f32) -> Result<f32, SimfileParseError> {
+    match value.trim() {
+        "" => Ok(default),
+        value => match value.parse() {
+            Ok(number) => Ok(number),
+            Err(_) => Err(SimfileParseError::InvalidKshData),
+        },
+    }
+}
+
+impl ChartBuilder {
+    /// Adds a line of the chart, e.g. "1020|02|0-", which is placed on `row`.
+    fn add_line(&mut self, row: u32, line: &str) -> Result<(), SimfileParseError> {
+        let parts: Vec<&str> = line.split('|').collect();
+        if parts.len() < 3 || parts[0].len() != 4 || parts[1].len() != 2 || parts[2].len() < 2 {
+            return Err(SimfileParseError::InvalidKshData);
+        }
+
+        // BT long notes are "2", FX long notes are "1" or a letter for the effect that is used
+        let bt = parts[0].chars().map(|c| match c {
+            '0' => Ok(LaneState::Empty),
+            '1' => Ok(LaneState::Chip),
+            '2' => Ok(LaneState::Long),
+            _ => Err(SimfileParseError::InvalidKshData),
+        });
+        let fx = parts[1].chars().map(|c| match c {
+            '0' => Ok(LaneState::Empty),
+            '2' => Ok(LaneState::Chip),
+            _ => Ok(LaneState::Long),
+        });
+
+        for (column, state) in bt.chain(fx).enumerate() {
+            self.add_note(row, column, state?);
+        }
+
+        // The laser part can be followed by a spin, e.g. "--@(192"
+        for (i, c) in parts[2].chars().take(2).enumerate() {
+            let side = match i {
+                0 => LaserSide::Left,
+                _ => LaserSide::Right,
+            };
+            match c {
+                '-' => {
+                    if let Some(laser) = self.active_lasers[i].take() {
+                        self.lasers.push(laser);
+                    }
+                }
+                ':' => {}
+                _ => {
+                    let position = match LASER_POSITIONS.find(c) {
+                        Some(p) => p as f32 / (LASER_POSITIONS.len() - 1) as f32,
+                        None => return Err(SimfileParseError::InvalidKshData),
+                    };
+                    self.active_lasers[i]
+                        .get_or_insert_with(|| Laser {
+                            side,
+                            points: vec![],
+                        })
+                        .points
+                        .push(LaserPoint { row, position });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn add_note(&mut self, row: u32, column: usize, state: LaneState) {
+        match (state, self.holds[column]) {
+            (LaneState::Long, Some(_)) => {}
+            (LaneState::Long, None) => self.holds[column] = Some(row),
+            (_, Some(start)) => {
+                // A chip right after a long note would replace its tail, so it's left out
+                self.end_hold(column, start, row);
+            }
+            (LaneState::Chip, None) => self.notes.push(TimedNote {
+                row,
+                column,
+                note_type: NoteType::Normal,
+            }),
+            (LaneState::Empty, None) => {}
+        }
+    }
+
+    fn end_hold(&mut self, column: usize, start: u32, end: u32) {
+        self.holds[column] = None;
+        self.notes.push(TimedNote {
+            row: start,
+            column,
+            note_type: NoteType::HoldHead,
+        });
+        self.notes.push(TimedNote {
+            row: end,
+            column,
+            note_type: NoteType::HoldOrRollTail,
+        });
+    }
+
+    /// Ends the long notes and lasers that are still active at `end_row`, and returns the notes
+    /// ordered by row and column, and the lasers.
+    fn finish(mut self, end_row: u32) -> (Vec<TimedNote>, Vec<Laser>) {
+        for column in 0..self.holds.len() {
+            if let Some(start) = self.holds[column] {
+                self.end_hold(column, start, end_row);
+            }
+        }
+        for laser in self.active_lasers.iter_mut() {
+            if let Some(laser) = laser.take() {
+                self.lasers.push(laser);
+            }
+        }
+
+        self.notes.sort_by_key(|n| (n.row, n.column));
+        self.lasers
+            .sort_by_key(|l| l.points.first().map(|p| p.row).unwrap_or(0));
+        (self.notes, self.lasers)
+    }
+}
@@ -1,5 +1,31 @@
+pub mod bms;
+pub mod cache;
+pub mod catalogue;
+pub mod course;
+pub mod diff;
+pub mod duplicates;
+pub mod dwi;
+pub mod edit;
+pub mod format;
+pub mod groovestats;
+pub mod ksh;
+pub mod lyrics;
+pub mod merge;
+pub mod osu;
+pub mod qua;
+pub mod radar;
 pub mod simfile;
-use simfile::{BPMDisplayType, Chart, ChartDifficulty, DisplayBPM, NoteType, Simfile, Stop, BPM, BgChange};
+pub mod sma;
+pub mod song_directory;
+pub mod ssc;
+pub mod timing;
+pub mod ucs;
+pub use format::{load_simfile, parse_any, SimfileFormat};
+use simfile::{
+    BgChange, Chart, ChartDifficulty, Combo, Delay, DisplayBPM, Fake, FgChange, InstrumentTrack,
+    Label, NoteType, RadarValues, Scroll, Selectable, Simfile, Speed, SpeedUnit, Stop, TickCount,
+    TimeSignature, Warp, BPM,
+};
 use std::io::{BufRead, BufReader};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -7,14 +33,40 @@ pub enum SimfileParseError {
     BufReadError,
     FailedToParseBPMs,
     FailedToParseStops,
+    FailedToParseDelays,
+    FailedToParseScrolls,
+    FailedToParseTimeSignatures,
+    FailedToParseTickCounts,
+    FailedToParseSpeeds,
+    FailedToParseWarps,
+    FailedToParseFakes,
+    FailedToParseCombos,
+    FailedToParseLabels,
     TooManyValuesInDisplayBPM,
+    FailedToParseDisplayBPM,
     EmptyNotesSection,
     InvalidChartFormat,
     InvalidBgChangeFormat,
+    InvalidFgChangeFormat,
+    InvalidInstrumentTrackFormat,
     UnknownChartDifficulty,
     FailedToParseChartMeter,
     UnsupportedNoteType,
     FailedToParseRadarValues,
+    InvalidDwiNoteData,
+    InvalidBmsData,
+    NotAnOsuManiaBeatmap,
+    InvalidOsuData,
+    UnsupportedKeyCount,
+    InvalidQuaData,
+    InvalidKshData,
+    InvalidUcsData,
+    UnknownSimfileFormat,
+    InvalidCourseData,
+    MissingEditSong,
+    InvalidLyricsData,
+    NoSimfileInDirectory,
+    InvalidGroupData,
 }
 
 // TODO: Check if having a semicolon in the middle of a value is supported
@@ -22,9 +74,23 @@ pub enum SimfileParseError {
 // TODO: Check if having a comment at the end of fields like title is supported
 // in Stepmania e.g "#TITLE: This is a //very cool title"
 // TODO: Handle non-UTF-8 streams! (at the moment they will return BufReadError)
+#[allow(clippy::needless_return)]
 pub fn parse_simfile<R: BufRead>(reader: &mut R) -> Result<Simfile, SimfileParseError> {
     let mut sim = Simfile::new();
 
+    for (key, value) in read_sections(reader)? {
+        parse_section(&mut sim, &key, value)?;
+    }
+
+    return Ok(sim);
+}
+
+/// Reads all sections (`#KEY:value;`) from the reader, returning the key and the trimmed value of
+/// each section. Comments are removed, and empty values are returned as `None`.
+#[allow(unused_variables)]
+pub(crate) fn read_sections<R: BufRead>(
+    reader: &mut R,
+) -> Result<Vec<(String, Option<String>)>, SimfileParseError> {
     // Clean data by removing comments and unneccesary whitespace
     let mut cleaned_data = String::new();
     loop {
@@ -52,6 +118,7 @@ pub fn parse_simfile<R: BufRead>(reader: &mut R) -> Result<Simfile, SimfileParse
     }
 
     let mut reader = BufReader::new(cleaned_data.as_bytes());
+    let mut sections = vec![];
 
     loop {
         let mut buf = vec![];
@@ -61,7 +128,9 @@ pub fn parse_simfile<R: BufRead>(reader: &mut R) -> Result<Simfile, SimfileParse
                     break;
                 }
                 let section = std::string::String::from_utf8_lossy(&buf);
-                parse_section(&mut sim, &section)?;
+                if let Some(section) = split_section(&section) {
+                    sections.push(section);
+                }
             }
             Err(e) => {
                 return Err(SimfileParseError::BufReadError);
@@ -69,36 +138,38 @@ pub fn parse_simfile<R: BufRead>(reader: &mut R) -> Result<Simfile, SimfileParse
         };
     }
 
-    return Ok(sim);
+    Ok(sections)
 }
 
-fn parse_section(simfile: &mut Simfile, section: &str) -> Result<(), SimfileParseError> {
+#[allow(clippy::len_zero)]
+fn split_section(section: &str) -> Option<(String, Option<String>)> {
     // Get start of the section (#KEY: value;)
-    let section_start_index = match section.find('#') {
-        Some(i) => i + 1,
-        None => return Ok(()),
-    };
+    let section_start_index = section.find('#')? + 1;
     let section = &section[section_start_index..];
 
     // Get the end of the key
-    let key_end_index = match section.find(':') {
-        Some(i) => i,
-        None => return Ok(()),
-    };
-
-    let value_end_index = section.len() - 1;
+    let key_end_index = section.find(':')?;
 
     let key = &section[..key_end_index];
-    let val = &section[key_end_index + 1..value_end_index].trim();
+    let val = section[key_end_index + 1..].trim();
+    let val = val.strip_suffix(';').unwrap_or(val).trim();
     let value = if val.trim().len() > 0 {
         Some(val.to_string())
     } else {
         None
     };
 
-    // TODO: Parse BGCHANGE section
-    // TODO: Parse FGCHANGE section
+    Some((key.to_string(), value))
+}
+
+#[allow(clippy::needless_return)]
+pub(crate) fn parse_section(
+    simfile: &mut Simfile,
+    key: &str,
+    value: Option<String>,
+) -> Result<(), SimfileParseError> {
     match key {
+        "VERSION" => simfile.version = parse_float(value),
         "TITLE" => simfile.title = value,
         "SUBTITLE" => simfile.subtitle = value,
         "ARTIST" => simfile.artist = value,
@@ -106,18 +177,30 @@ fn parse_section(simfile: &mut Simfile, section: &str) -> Result<(), SimfilePars
         "SUBTITLETRANSLIT" => simfile.subtitle_translit = value,
         "ARTISTTRANSLIT" => simfile.artist_translit = value,
         "GENRE" => simfile.genre = value,
+        "ORIGIN" => simfile.origin = value,
         "CREDIT" => simfile.credit = value,
         "BANNER" => simfile.banner_path = value,
+        "PREVIEWVID" => simfile.preview_video_path = value,
         "JACKET" => simfile.jacket_path = value,
+        "CDIMAGE" => simfile.cd_image_path = value,
+        "DISCIMAGE" => simfile.disc_image_path = value,
         "BACKGROUND" => simfile.background_path = value,
         "LYRICSPATH" => simfile.lyrics_path = value,
         "CDTITLE" => simfile.cd_title_path = value,
         "MUSIC" => simfile.music_path = value,
+        "PREVIEW" => simfile.preview_path = value,
+        "INSTRUMENTTRACK" => simfile.instrument_tracks = parse_instrument_tracks(value)?,
+        "MUSICLENGTH" => simfile.music_length = parse_float(value),
+        "LASTBEATHINT" => simfile.last_beat_hint = parse_float(value),
+        "LASTSECONDHINT" => simfile.last_second_hint = parse_float(value),
         "OFFSET" => simfile.offset = parse_float(value),
         "SAMPLESTART" => simfile.sample_start = parse_float(value),
         "SAMPLELENGTH" => simfile.sample_length = parse_float(value),
-        "SELECTABLE" => simfile.selectable = parse_bool(value),
-        "BGCHANGES" => simfile.bg_changes = parse_bg_changes(value)?,
+        "SELECTABLE" => simfile.selectable = parse_selectable(value),
+        // ANIMATIONS is the name BGCHANGES had in older versions of Stepmania
+        "BGCHANGES" | "ANIMATIONS" => simfile.bg_changes = parse_bg_changes(value)?,
+        "FGCHANGES" => simfile.fg_changes = parse_fg_changes(value)?,
+        "KEYSOUNDS" => simfile.keysounds = parse_list(value),
         "BPMS" => {
             simfile.bpms = {
                 match parse_key_value_list(value) {
@@ -135,6 +218,7 @@ fn parse_section(simfile: &mut Simfile, section: &str) -> Result<(), SimfilePars
             }
         }
         "DISPLAYBPM" => simfile.display_bpm = parse_display_bpm(value)?,
+        "BPMLOCK" => simfile.bpm_lock = parse_bool(value),
         "STOPS" => {
             simfile.stops = {
                 match parse_key_value_list(value) {
@@ -151,6 +235,70 @@ fn parse_section(simfile: &mut Simfile, section: &str) -> Result<(), SimfilePars
                 }
             }
         }
+        "DELAYS" => match parse_key_value_list(value) {
+            Ok(list) => {
+                simfile.delays = list
+                    .into_iter()
+                    .map(|x| Delay {
+                        beat: x.key,
+                        time: x.value,
+                    })
+                    .collect()
+            }
+            Err(_) => return Err(SimfileParseError::FailedToParseDelays),
+        },
+        "SCROLLS" => match parse_key_value_list(value) {
+            Ok(list) => {
+                simfile.scrolls = list
+                    .into_iter()
+                    .map(|x| Scroll {
+                        beat: x.key,
+                        factor: x.value,
+                    })
+                    .collect()
+            }
+            Err(_) => return Err(SimfileParseError::FailedToParseScrolls),
+        },
+        "TIMESIGNATURES" => simfile.time_signatures = parse_time_signatures(value)?,
+        "TICKCOUNTS" => match parse_key_value_list(value) {
+            Ok(list) => {
+                simfile.tick_counts = list
+                    .into_iter()
+                    .map(|x| TickCount {
+                        beat: x.key,
+                        ticks: x.value as u32,
+                    })
+                    .collect()
+            }
+            Err(_) => return Err(SimfileParseError::FailedToParseTickCounts),
+        },
+        "SPEEDS" => simfile.speeds = parse_speeds(value)?,
+        "WARPS" => match parse_key_value_list(value) {
+            Ok(list) => {
+                simfile.warps = list
+                    .into_iter()
+                    .map(|x| Warp {
+                        beat: x.key,
+                        length: x.value,
+                    })
+                    .collect()
+            }
+            Err(_) => return Err(SimfileParseError::FailedToParseWarps),
+        },
+        "FAKES" => match parse_key_value_list(value) {
+            Ok(list) => {
+                simfile.fakes = list
+                    .into_iter()
+                    .map(|x| Fake {
+                        beat: x.key,
+                        length: x.value,
+                    })
+                    .collect()
+            }
+            Err(_) => return Err(SimfileParseError::FailedToParseFakes),
+        },
+        "COMBOS" => simfile.combos = parse_combos(value)?,
+        "LABELS" => simfile.labels = parse_labels(value)?,
         "NOTES" => match parse_chart(value) {
             Ok(chart) => simfile.charts.push(chart),
             Err(e) => return Err(e),
@@ -161,6 +309,7 @@ fn parse_section(simfile: &mut Simfile, section: &str) -> Result<(), SimfilePars
     return Ok(());
 }
 
+#[allow(clippy::manual_ok_err)]
 fn parse_float(value: Option<String>) -> Option<f32> {
     match value.unwrap_or_default().as_str().parse() {
         Ok(i) => Some(i),
@@ -170,51 +319,190 @@ fn parse_float(value: Option<String>) -> Option<f32> {
 
 fn parse_bool(value: Option<String>) -> Option<bool> {
     match value {
-        Some(i) => match i.as_ref() {
-            "YES" => Some(true),
-            "NO" => Some(false),
+        Some(i) => match i.to_uppercase().as_ref() {
+            "YES" | "1" => Some(true),
+            "NO" | "0" => Some(false),
             _ => None,
         },
         None => None,
     }
 }
 
+fn parse_selectable(value: Option<String>) -> Option<Selectable> {
+    let value = value?;
+    let selectable = match value.to_uppercase().as_ref() {
+        "YES" => Selectable::Yes,
+        "NO" => Selectable::No,
+        "ROULETTE" => Selectable::Roulette,
+        "ES" => Selectable::Es,
+        "OMES" => Selectable::Omes,
+        _ => match value.parse::<i32>() {
+            Ok(i) => Selectable::Numeric(i),
+            Err(_) => Selectable::Unknown(value),
+        },
+    };
+
+    Some(selectable)
+}
+
+fn parse_list(value: Option<String>) -> Vec<String> {
+    match value {
+        Some(v) => v
+            .split(',')
+            .map(|v| v.trim())
+            .filter(|v| !v.is_empty())
+            .map(String::from)
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+fn parse_instrument_tracks(
+    value: Option<String>,
+) -> Result<Vec<InstrumentTrack>, SimfileParseError> {
+    let mut tracks = Vec::new();
+
+    for entry in parse_list(value) {
+        let values: Vec<&str> = entry.split('=').map(|v| v.trim()).collect();
+        if values.len() != 2 || values[0].is_empty() || values[1].is_empty() {
+            return Err(SimfileParseError::InvalidInstrumentTrackFormat);
+        }
+
+        tracks.push(InstrumentTrack {
+            instrument: values[0].to_string(),
+            path: values[1].to_string(),
+        });
+    }
+
+    Ok(tracks)
+}
+
+fn parse_time_signatures(value: Option<String>) -> Result<Vec<TimeSignature>, SimfileParseError> {
+    let mut time_signatures = Vec::new();
+
+    for entry in parse_list(value) {
+        let values: Vec<&str> = entry.split('=').map(|v| v.trim()).collect();
+        if values.len() != 3 {
+            return Err(SimfileParseError::FailedToParseTimeSignatures);
+        }
+
+        match (values[0].parse(), values[1].parse(), values[2].parse()) {
+            (Ok(beat), Ok(numerator), Ok(denominator)) => time_signatures.push(TimeSignature {
+                beat,
+                numerator,
+                denominator,
+            }),
+            _ => return Err(SimfileParseError::FailedToParseTimeSignatures),
+        }
+    }
+
+    Ok(time_signatures)
+}
+
+fn parse_speeds(value: Option<String>) -> Result<Vec<Speed>, SimfileParseError> {
+    let mut speeds = Vec::new();
+
+    for entry in parse_list(value) {
+        // The unit is optional, and is 1 when the duration is in seconds
+        let values: Vec<&str> = entry.split('=').map(|v| v.trim()).collect();
+        if values.len() != 3 && values.len() != 4 {
+            return Err(SimfileParseError::FailedToParseSpeeds);
+        }
+
+        let unit = match values.get(3) {
+            None | Some(&"0") => SpeedUnit::Beats,
+            Some(&"1") => SpeedUnit::Seconds,
+            _ => return Err(SimfileParseError::FailedToParseSpeeds),
+        };
+        match (values[0].parse(), values[1].parse(), values[2].parse()) {
+            (Ok(beat), Ok(ratio), Ok(duration)) => speeds.push(Speed {
+                beat,
+                ratio,
+                duration,
+                unit,
+            }),
+            _ => return Err(SimfileParseError::FailedToParseSpeeds),
+        }
+    }
+
+    Ok(speeds)
+}
+
+fn parse_combos(value: Option<String>) -> Result<Vec<Combo>, SimfileParseError> {
+    let mut combos = Vec::new();
+
+    for entry in parse_list(value) {
+        // The miss multiplier is optional, and is the same as the hit multiplier when left out
+        let values: Vec<&str> = entry.split('=').map(|v| v.trim()).collect();
+        if values.len() != 2 && values.len() != 3 {
+            return Err(SimfileParseError::FailedToParseCombos);
+        }
+
+        let miss_multiplier = values.get(2).unwrap_or(&values[1]).parse();
+        match (values[0].parse(), values[1].parse(), miss_multiplier) {
+            (Ok(beat), Ok(hit_multiplier), Ok(miss_multiplier)) => combos.push(Combo {
+                beat,
+                hit_multiplier,
+                miss_multiplier,
+            }),
+            _ => return Err(SimfileParseError::FailedToParseCombos),
+        }
+    }
+
+    Ok(combos)
+}
+
+fn parse_labels(value: Option<String>) -> Result<Vec<Label>, SimfileParseError> {
+    let mut labels = Vec::new();
+
+    for entry in parse_list(value) {
+        let (beat, label) = match entry.split_once('=') {
+            Some((beat, label)) => (beat.trim(), label.trim()),
+            None => return Err(SimfileParseError::FailedToParseLabels),
+        };
+
+        match beat.parse() {
+            Ok(beat) => labels.push(Label {
+                beat,
+                label: label.to_string(),
+            }),
+            Err(_) => return Err(SimfileParseError::FailedToParseLabels),
+        }
+    }
+
+    Ok(labels)
+}
+
+#[allow(clippy::needless_return)]
 fn parse_display_bpm(value: Option<String>) -> Result<Option<DisplayBPM>, SimfileParseError> {
     let value = match value {
         Some(i) => i,
         None => return Ok(None),
     };
 
-    let values: Vec<&str> = value.trim().split(":").collect();
+    let values: Vec<&str> = value.trim().split(':').map(|v| v.trim()).collect();
+
+    let parse_bpm = |value: &str| match value.parse::<f32>() {
+        Ok(i) => Ok(i),
+        Err(_) => Err(SimfileParseError::FailedToParseDisplayBPM),
+    };
 
     // Check how many values (1 = Single/Random, 2 = Range)
     let display_bpm = match values.len() {
         1 => match values[0].parse() {
             // Could parse value as float, assume its a Single
-            Ok(i) => DisplayBPM {
-                display_type: BPMDisplayType::Single,
-                value: i,
-                value2: 0.0,
-            },
+            Ok(i) => DisplayBPM::Single(i),
             // Could NOT parse value as float, assume its a Random
-            Err(_) => DisplayBPM {
-                display_type: BPMDisplayType::Random,
-                value: 0.0,
-                value2: 0.0,
-            },
-        },
-        2 => DisplayBPM {
-            display_type: BPMDisplayType::Range,
-            value: values[0].parse().unwrap(),
-            value2: values[1].parse().unwrap(),
+            Err(_) => DisplayBPM::Random,
         },
+        2 => DisplayBPM::Range(parse_bpm(values[0])?, parse_bpm(values[1])?),
         _ => return Err(SimfileParseError::TooManyValuesInDisplayBPM),
     };
 
     return Ok(Some(display_bpm));
 }
 
-fn parse_chart(value: Option<String>) -> Result<Chart, SimfileParseError> {
+pub(crate) fn parse_chart(value: Option<String>) -> Result<Chart, SimfileParseError> {
     let value = match value {
         Some(v) => v,
         None => return Err(SimfileParseError::EmptyNotesSection),
@@ -226,34 +514,50 @@ fn parse_chart(value: Option<String>) -> Result<Chart, SimfileParseError> {
         return Err(SimfileParseError::InvalidChartFormat);
     }
 
+    // Some simfiles don't have a meter on certain charts, Stepmania makes it 1 in these instances
+    let meter = match values[3] {
+        "" => 1,
+        v => match v.parse() {
+            Ok(i) => i,
+            Err(_) => return Err(SimfileParseError::FailedToParseChartMeter),
+        },
+    };
+
     let chart = Chart {
         chart_type: values[0].to_string(),
         author: match values[1].is_empty() {
             true => None,
             false => Some(values[1].to_string()),
         },
-        difficulty: match values[2] {
-            "Beginner" => ChartDifficulty::Beginner,
-            "Easy" => ChartDifficulty::Easy,
-            "Medium" => ChartDifficulty::Medium,
-            "Hard" => ChartDifficulty::Hard,
-            "Challenge" => ChartDifficulty::Challenge,
-            "Edit" => ChartDifficulty::Edit,
-            _ => {
-                return Err(SimfileParseError::UnknownChartDifficulty);
-            }
-        },
-        meter: match values[3].parse() {
-            Ok(i) => i,
-            Err(_) => return Err(SimfileParseError::FailedToParseChartMeter),
-        },
+        difficulty: parse_chart_difficulty(values[2], values[1], meter),
+        difficulty_name: values[2].to_string(),
+        meter,
         radar_values: parse_radar_values(values[4])?,
         note_data: parse_chart_data(values[5])?,
+        lasers: Vec::new(),
+        timing: None,
+        credit: None,
     };
 
     Ok(chart)
 }
 
+pub(crate) fn parse_chart_difficulty(name: &str, description: &str, meter: u16) -> ChartDifficulty {
+    let description = description.to_lowercase();
+
+    match ChartDifficulty::from_name(name) {
+        // Before Challenge was a difficulty, Smaniac and Challenge charts were Hard charts with
+        // a special description
+        Some(ChartDifficulty::Hard) if description == "smaniac" || description == "challenge" => {
+            ChartDifficulty::Challenge
+        }
+        Some(difficulty) => difficulty,
+        // Charts without a known difficulty get it from the description, or failing that the meter
+        None => ChartDifficulty::from_name(&description)
+            .unwrap_or_else(|| ChartDifficulty::from_meter(meter)),
+    }
+}
+
 fn parse_bg_changes(value: Option<String>) -> Result<Vec<BgChange>, SimfileParseError> {
     let value = match value {
         Some(v) => v,
@@ -315,9 +619,11 @@ fn parse_bg_change(value: &str) -> Result<BgChange, SimfileParseError> {
         Err(_) => return Err(SimfileParseError::InvalidBgChangeFormat)
     };
 
-    // TODO: Add support for the rest of the fields
-    //       in BGCHANGES (effect_file, second_effect_file, transition_file, color_string, second_color_string)
-
+    // The rest of the values are optional, and empty values are treated as not set
+    let optional_value = |index: usize| match values.get(index) {
+        Some(v) if !v.trim().is_empty() => Some(v.trim().to_string()),
+        _ => None,
+    };
 
     Ok(BgChange {
         start_beat,
@@ -326,29 +632,101 @@ fn parse_bg_change(value: &str) -> Result<BgChange, SimfileParseError> {
         transition_type,
         effect_flag,
         second_effect_flag,
-        effect_file: None,
-        second_effect_file: None,
-        transition_file: None,
-        color_string: None,
-        second_color_string: None,
+        effect_file: optional_value(6),
+        second_effect_file: optional_value(7),
+        transition_file: optional_value(8),
+        color_string: optional_value(9),
+        second_color_string: optional_value(10),
     })
 }
 
-fn parse_radar_values(data: &str) -> Result<Vec<f32>, SimfileParseError> {
-    let values: Vec<&str> = data.split(",").collect();
-    let values: Vec<f32> = values
-        .into_iter()
-        .map(|v| match v.trim().parse() {
+fn parse_fg_changes(value: Option<String>) -> Result<Vec<FgChange>, SimfileParseError> {
+    let value = match value {
+        Some(v) => v,
+        None => return Ok(Vec::new()),
+    };
+
+    let mut fg_changes = Vec::new();
+    for entry in value.replace("\n", "").replace("\r", "").split(',') {
+        // Foreground changes use the same format as background changes, but only the start
+        // beat and the path is used by Stepmania
+        let values: Vec<&str> = entry.split('=').collect();
+        if values.len() < 2 {
+            return Err(SimfileParseError::InvalidFgChangeFormat);
+        }
+
+        let start_beat = match values[0].trim().parse::<f32>() {
             Ok(v) => v,
-            Err(_) => 0.0,
-        })
-        .collect();
+            Err(_) => return Err(SimfileParseError::InvalidFgChangeFormat),
+        };
+
+        fg_changes.push(FgChange {
+            start_beat,
+            path: values[1].trim().to_string(),
+        });
+    }
 
-    Ok(values)
+    Ok(fg_changes)
+}
+
+pub(crate) fn parse_radar_values(data: &str) -> Result<Vec<RadarValues>, SimfileParseError> {
+    let data = data.trim();
+    if data.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut values: Vec<f32> = vec![];
+    for value in data.split(',') {
+        match value.trim().parse() {
+            Ok(v) => values.push(v),
+            Err(_) => return Err(SimfileParseError::FailedToParseRadarValues),
+        }
+    }
+
+    // Stepmania 5 saves the values once for each player, so if the amount of values doesn't
+    // match any of the known formats, check if it's two sets of values
+    let per_player = match values.len() {
+        5 | 11 | 13 | 14 => values.len(),
+        10 | 22 | 26 | 28 => values.len() / 2,
+        _ => return Err(SimfileParseError::FailedToParseRadarValues),
+    };
+
+    Ok(values
+        .chunks(per_player)
+        .map(radar_values_from_slice)
+        .collect())
+}
+
+fn radar_values_from_slice(values: &[f32]) -> RadarValues {
+    let value = |i: usize| values.get(i).cloned();
+    // Stepmania 5.1 added the total number of notes after the five main categories, it saves
+    // all 14 values in .ssc files but stops after the hands in .sm files. The 13 value format
+    // is from Stepmania 5.0, before the total number of notes was added.
+    let (notes, counts) = match values.len() {
+        11 | 14 => (value(5), 6),
+        _ => (None, 5),
+    };
+
+    RadarValues {
+        stream: values[0],
+        voltage: values[1],
+        air: values[2],
+        freeze: values[3],
+        chaos: values[4],
+        notes,
+        taps: value(counts),
+        jumps: value(counts + 1),
+        holds: value(counts + 2),
+        mines: value(counts + 3),
+        hands: value(counts + 4),
+        rolls: value(counts + 5),
+        lifts: value(counts + 6),
+        fakes: value(counts + 7),
+    }
 }
 
 // TODO: Figure out what 'H' corresponds to, and if Stepmania recognizes this.
-fn parse_chart_data(data: &str) -> Result<Vec<Vec<NoteType>>, SimfileParseError> {
+pub(crate) fn parse_chart_data(data: &str) -> Result<Vec<Vec<NoteType>>, SimfileParseError> {
     let measures: Vec<&str> = data.split(",").collect();
     let mut chart: Vec<Vec<NoteType>> = vec![];
 
@@ -386,19 +764,22 @@ fn parse_chart_data(data: &str) -> Result<Vec<Vec<NoteType>>, SimfileParseError>
     Ok(chart)
 }
 
-struct KeyValue {
-    key: f32,
-    value: f32,
+pub(crate) struct KeyValue {
+    pub(crate) key: f32,
+    pub(crate) value: f32,
 }
 
-enum ParseKeyValueError {
+pub(crate) enum ParseKeyValueError {
     FailedToParseKeyValue,
     FailedToParseFloat,
 }
 
 // TODO: Check how Stepmania handles empty values in a keyvalue list:
 // E.g (#BPMS:0.0=120.0;;10.0=150.0)
-fn parse_key_value_list(value: Option<String>) -> Result<Vec<KeyValue>, ParseKeyValueError> {
+#[allow(clippy::redundant_field_names)]
+pub(crate) fn parse_key_value_list(
+    value: Option<String>,
+) -> Result<Vec<KeyValue>, ParseKeyValueError> {
     let value = match value {
         Some(i) => i,
         None => return Ok(vec![]),
@@ -0,0 +1,151 @@
+use crate::simfile::Simfile;
+use crate::song_directory::resolve_path;
+use crate::timing::TimingData;
+use crate::SimfileParseError;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// A line of lyrics.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LyricLine {
+    /// The time the line is shown, in seconds from the start of the music
+    pub time: f32,
+    /// The text of the line, "|" in the file starts a new line
+    pub text: String,
+    /// The color of the line, set by the last `[COLOUR]` tag before it
+    pub color: Option<LyricColor>,
+}
+
+/// A color of lyrics, with every channel from 0 to 1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LyricColor {
+    pub red: f32,
+    pub green: f32,
+    pub blue: f32,
+    pub alpha: f32,
+}
+
+/// Parses a lyrics file (.lrc), as used by `#LYRICSPATH`.
+///
+/// Every line starts with one or more `[mm:ss.xx]` timestamps, the line is shown at each of them.
+/// `[COLOUR]` (or `[COLOR]`) tags set the color of the lines after them, either as "0xRRGGBB" or as
+/// "red,green,blue[,alpha]" from 0 to 1, and `[offset:ms]` moves every line earlier by the given
+/// milliseconds. Other tags like `[ti:...]` are ignored. The lines are sorted by time.
+pub fn parse_lrc<R: BufRead>(reader: &mut R) -> Result<Vec<LyricLine>, SimfileParseError> {
+    let mut data = vec![];
+    if reader.read_to_end(&mut data).is_err() {
+        return Err(SimfileParseError::BufReadError);
+    }
+    let data = String::from_utf8_lossy(&data);
+
+    let mut lines = vec![];
+    let mut color = None;
+    let mut offset = 0.0;
+
+    for line in data
+        .lines()
+        .map(|l| l.trim().trim_start_matches('\u{feff}'))
+    {
+        let mut rest = line;
+        let mut times = vec![];
+
+        while let Some(tag_end) = rest.strip_prefix('[').and_then(|r| r.find(']')) {
+            let tag = &rest[1..=tag_end];
+            let value = &rest[tag_end + 2..];
+            let (key, tag_value) = match tag.find(':') {
+                Some(i) => (&tag[..i], &tag[i + 1..]),
+                None => (tag, ""),
+            };
+
+            if key.eq_ignore_ascii_case("COLOUR") || key.eq_ignore_ascii_case("COLOR") {
+                color = Some(parse_color(value)?);
+                break;
+            } else if key.eq_ignore_ascii_case("OFFSET") {
+                offset = parse_number(tag_value)? / 1000.0;
+            } else if key.starts_with(|c: char| c.is_ascii_digit()) {
+                times.push(parse_number(key)? * 60.0 + parse_number(tag_value)?);
+            }
+            rest = value;
+        }
+
+        let text = rest.trim().replace('|', "\n");
+        lines.extend(times.into_iter().map(|time| LyricLine {
+            time,
+            text: text.clone(),
+            color,
+        }));
+    }
+
+    for line in &mut lines {
+        line.time -= offset;
+    }
+    lines.sort_by(|a, b| a.time.total_cmp(&b.time));
+    Ok(lines)
+}
+
+/// Parses a number, "NaN" and "inf" aren't times or colors so they're invalid.
+fn parse_number(value: &str) -> Result<f32, SimfileParseError> {
+    match value.trim().trim_start_matches('+').parse::<f32>() {
+        Ok(number) if number.is_finite() => Ok(number),
+        _ => Err(SimfileParseError::InvalidLyricsData),
+    }
+}
+
+/// Parses a color, "0xRRGGBB", "#RRGGBB" or "red,green,blue[,alpha]" from 0 to 1.
+fn parse_color(value: &str) -> Result<LyricColor, SimfileParseError> {
+    let value = value.trim();
+    let hex = value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+        .or_else(|| value.strip_prefix('#'));
+
+    if let Some(hex) = hex {
+        return match u32::from_str_radix(hex, 16) {
+            Ok(rgb) if hex.len() == 6 => Ok(LyricColor {
+                red: (rgb >> 16 & 0xff) as f32 / 255.0,
+                green: (rgb >> 8 & 0xff) as f32 / 255.0,
+                blue: (rgb & 0xff) as f32 / 255.0,
+                alpha: 1.0,
+            }),
+            _ => Err(SimfileParseError::InvalidLyricsData),
+        };
+    }
+
+    let channels = value
+        .split(',')
+        .map(parse_number)
+        .collect::<Result<Vec<f32>, _>>()?;
+    match channels.len() {
+        3 | 4 => Ok(LyricColor {
+            red: channels[0],
+            green: channels[1],
+            blue: channels[2],
+            alpha: channels.get(3).cloned().unwrap_or(1.0),
+        }),
+        _ => Err(SimfileParseError::InvalidLyricsData),
+    }
+}
+
+/// Loads the lyrics of a simfile from its `#LYRICSPATH`, relative to the folder of the song and
+/// matched ignoring case. Returns `None` if the simfile has no lyrics.
+pub fn load_lyrics<P: AsRef<Path>>(
+    simfile: &Simfile,
+    song_dir: P,
+) -> Option<Result<Vec<LyricLine>, SimfileParseError>> {
+    let lyrics_path = simfile.lyrics_path.as_ref().filter(|p| !p.is_empty())?;
+
+    Some(
+        match resolve_path(song_dir, lyrics_path).and_then(|p| File::open(p).ok()) {
+            Some(file) => parse_lrc(&mut BufReader::new(file)),
+            None => Err(SimfileParseError::BufReadError),
+        },
+    )
+}
+
+impl LyricLine {
+    /// Returns the beat the line is shown on.
+    pub fn beat(&self, timing: &TimingData) -> f32 {
+        timing.beat_at_seconds(self.time)
+    }
+}
@@ -0,0 +1,457 @@
+use crate::diff::same_chart;
+use crate::simfile::{
+    note_data_from_notes, Chart, ChartDifficulty, ChartTiming, NoteType, Simfile, TimedNote,
+    ROWS_PER_MEASURE,
+};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+/// The result of merging two versions of a simfile.
+#[derive(Debug, Clone)]
+pub struct MergeResult {
+    /// The merged simfile, which has our version of everything that conflicts
+    pub simfile: Simfile,
+    pub conflicts: Vec<Conflict>,
+}
+
+impl MergeResult {
+    /// Returns true if the versions were merged without conflicts.
+    pub fn is_clean(&self) -> bool {
+        self.conflicts.is_empty()
+    }
+}
+
+/// A part of the simfile that both sides changed differently.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Conflict {
+    /// A song tag, e.g. "TITLE"
+    Tag(&'static str),
+    /// A part of a chart, which is matched like in `diff_simfiles`
+    Chart {
+        chart_type: String,
+        difficulty: ChartDifficulty,
+        author: Option<String>,
+        region: ChartRegion,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChartRegion {
+    /// The chart was removed on one side and changed on the other, or it was added on both sides
+    /// with different content
+    Chart,
+    /// A tag of the chart, e.g. "METER"
+    Tag(&'static str),
+    /// The notes of a measure, starting from 0
+    Measure(usize),
+}
+
+/// Merges the changes that `ours` and `theirs` made to `base`.
+///
+/// Every song tag, chart tag and measure of notes is merged on its own: a change from one side is
+/// kept, and when both sides changed it differently it is reported as a conflict and our version
+/// is kept. A chart removed on one side is removed, unless the other side changed it, then the
+/// changed chart is kept and reported as a conflict. A hold that one side changed is never split
+/// between the sides, if the other side changed its lane every measure of the hold conflicts.
+pub fn merge_simfiles(base: &Simfile, ours: &Simfile, theirs: &Simfile) -> MergeResult {
+    let mut tags = Merger::new(base, ours, theirs);
+    let mut simfile = Simfile {
+        version: tags.tag("VERSION", |s| &s.version),
+        title: tags.tag("TITLE", |s| &s.title),
+        subtitle: tags.tag("SUBTITLE", |s| &s.subtitle),
+        artist: tags.tag("ARTIST", |s| &s.artist),
+        title_translit: tags.tag("TITLETRANSLIT", |s| &s.title_translit),
+        subtitle_translit: tags.tag("SUBTITLETRANSLIT", |s| &s.subtitle_translit),
+        artist_translit: tags.tag("ARTISTTRANSLIT", |s| &s.artist_translit),
+        genre: tags.tag("GENRE", |s| &s.genre),
+        origin: tags.tag("ORIGIN", |s| &s.origin),
+        credit: tags.tag("CREDIT", |s| &s.credit),
+        banner_path: tags.tag("BANNER", |s| &s.banner_path),
+        background_path: tags.tag("BACKGROUND", |s| &s.background_path),
+        preview_video_path: tags.tag("PREVIEWVID", |s| &s.preview_video_path),
+        jacket_path: tags.tag("JACKET", |s| &s.jacket_path),
+        cd_image_path: tags.tag("CDIMAGE", |s| &s.cd_image_path),
+        disc_image_path: tags.tag("DISCIMAGE", |s| &s.disc_image_path),
+        lyrics_path: tags.tag("LYRICSPATH", |s| &s.lyrics_path),
+        cd_title_path: tags.tag("CDTITLE", |s| &s.cd_title_path),
+        music_path: tags.tag("MUSIC", |s| &s.music_path),
+        preview_path: tags.tag("PREVIEW", |s| &s.preview_path),
+        instrument_tracks: tags.tag("INSTRUMENTTRACK", |s| &s.instrument_tracks),
+        music_length: tags.tag("MUSICLENGTH", |s| &s.music_length),
+        last_beat_hint: tags.tag("LASTBEATHINT", |s| &s.last_beat_hint),
+        last_second_hint: tags.tag("LASTSECONDHINT", |s| &s.last_second_hint),
+        offset: tags.tag("OFFSET", |s| &s.offset),
+        sample_start: tags.tag("SAMPLESTART", |s| &s.sample_start),
+        sample_length: tags.tag("SAMPLELENGTH", |s| &s.sample_length),
+        selectable: tags.tag("SELECTABLE", |s| &s.selectable),
+        bpms: tags.tag("BPMS", |s| &s.bpms),
+        display_bpm: tags.tag("DISPLAYBPM", |s| &s.display_bpm),
+        bpm_lock: tags.tag("BPMLOCK", |s| &s.bpm_lock),
+        stops: tags.tag("STOPS", |s| &s.stops),
+        delays: tags.tag("DELAYS", |s| &s.delays),
+        scrolls: tags.tag("SCROLLS", |s| &s.scrolls),
+        time_signatures: tags.tag("TIMESIGNATURES", |s| &s.time_signatures),
+        tick_counts: tags.tag("TICKCOUNTS", |s| &s.tick_counts),
+        speeds: tags.tag("SPEEDS", |s| &s.speeds),
+        warps: tags.tag("WARPS", |s| &s.warps),
+        fakes: tags.tag("FAKES", |s| &s.fakes),
+        combos: tags.tag("COMBOS", |s| &s.combos),
+        labels: tags.tag("LABELS", |s| &s.labels),
+        bg_changes: tags.tag("BGCHANGES", |s| &s.bg_changes),
+        fg_changes: tags.tag("FGCHANGES", |s| &s.fg_changes),
+        keysounds: tags.tag("KEYSOUNDS", |s| &s.keysounds),
+        charts: vec![],
+    };
+    let mut conflicts: Vec<Conflict> = tags.conflicts.into_iter().map(Conflict::Tag).collect();
+
+    let find = |charts: &'_ [Chart], chart: &Chart| -> Option<usize> {
+        charts.iter().position(|c| same_chart(c, chart))
+    };
+    let chart_conflict = |chart: &Chart, region: ChartRegion| Conflict::Chart {
+        chart_type: chart.chart_type.clone(),
+        difficulty: chart.difficulty.clone(),
+        author: chart.author.clone(),
+        region,
+    };
+
+    for our_chart in &ours.charts {
+        let base_chart = find(&base.charts, our_chart).map(|i| &base.charts[i]);
+        let their_chart = find(&theirs.charts, our_chart).map(|i| &theirs.charts[i]);
+        match (base_chart, their_chart) {
+            (Some(base_chart), Some(their_chart)) => {
+                let (chart, regions) =
+                    merge_charts((base, base_chart), (ours, our_chart), (theirs, their_chart));
+                conflicts.extend(regions.into_iter().map(|r| chart_conflict(&chart, r)));
+                simfile.charts.push(chart);
+            }
+            // Removed by them
+            (Some(base_chart), None) => {
+                if charts_differ(base, base_chart, ours, our_chart) {
+                    conflicts.push(chart_conflict(our_chart, ChartRegion::Chart));
+                    simfile.charts.push(our_chart.clone());
+                }
+            }
+            // Added on both sides
+            (None, Some(their_chart)) => {
+                if charts_differ(ours, our_chart, theirs, their_chart) {
+                    conflicts.push(chart_conflict(our_chart, ChartRegion::Chart));
+                }
+                simfile.charts.push(our_chart.clone());
+            }
+            (None, None) => simfile.charts.push(our_chart.clone()),
+        }
+    }
+
+    for their_chart in &theirs.charts {
+        if find(&ours.charts, their_chart).is_some() {
+            continue;
+        }
+        match find(&base.charts, their_chart).map(|i| &base.charts[i]) {
+            // Removed by us
+            Some(base_chart) => {
+                if charts_differ(base, base_chart, theirs, their_chart) {
+                    conflicts.push(chart_conflict(their_chart, ChartRegion::Chart));
+                    simfile.charts.push(their_chart.clone());
+                }
+            }
+            None => simfile.charts.push(their_chart.clone()),
+        }
+    }
+
+    MergeResult { simfile, conflicts }
+}
+
+/// Merges the values of three versions of something one tag at a time, and keeps track of the
+/// tags that conflict.
+struct Merger<'a, T> {
+    base: &'a T,
+    ours: &'a T,
+    theirs: &'a T,
+    conflicts: Vec<&'static str>,
+}
+
+impl<'a, T> Merger<'a, T> {
+    fn new(base: &'a T, ours: &'a T, theirs: &'a T) -> Merger<'a, T> {
+        Merger {
+            base,
+            ours,
+            theirs,
+            conflicts: vec![],
+        }
+    }
+
+    fn tag<V: PartialEq + Clone>(&mut self, tag: &'static str, get: impl Fn(&T) -> &V) -> V {
+        let (value, conflict) = merge_value(get(self.base), get(self.ours), get(self.theirs));
+        if conflict {
+            self.conflicts.push(tag);
+        }
+        value.clone()
+    }
+}
+
+/// Returns the merged value and whether both sides changed it differently, in which case our
+/// value is returned.
+fn merge_value<'v, V: PartialEq>(base: &'v V, ours: &'v V, theirs: &'v V) -> (&'v V, bool) {
+    if ours == base {
+        (theirs, false)
+    } else {
+        (ours, theirs != base && theirs != ours)
+    }
+}
+
+fn merge_charts(
+    (base_sim, base): (&Simfile, &Chart),
+    (our_sim, ours): (&Simfile, &Chart),
+    (their_sim, theirs): (&Simfile, &Chart),
+) -> (Chart, Vec<ChartRegion>) {
+    let mut tags = Merger::new(base, ours, theirs);
+    let mut chart = Chart {
+        chart_type: ours.chart_type.clone(),
+        author: tags.tag("DESCRIPTION", |c| &c.author),
+        difficulty: ours.difficulty.clone(),
+        difficulty_name: tags.tag("DIFFICULTY", |c| &c.difficulty_name),
+        meter: tags.tag("METER", |c| &c.meter),
+        radar_values: tags.tag("RADARVALUES", |c| &c.radar_values),
+        note_data: vec![],
+        lasers: tags.tag("LASERS", |c| &c.lasers),
+        timing: None,
+        credit: tags.tag("CREDIT", |c| &c.credit),
+    };
+
+    // Whether the chart has its own timing is merged first, so a side that removes it isn't
+    // undone. When the chart keeps its own timing, the sides without it use the song timing
+    let has_timing = |chart: &Chart| chart.timing.is_some();
+    let (&keeps_timing, _) = merge_value(&has_timing(base), &has_timing(ours), &has_timing(theirs));
+    if keeps_timing {
+        let (base_timing, our_timing, their_timing) = (
+            chart_timing(base_sim, base),
+            chart_timing(our_sim, ours),
+            chart_timing(their_sim, theirs),
+        );
+        let mut timing = Merger::new(&base_timing, &our_timing, &their_timing);
+        chart.timing = Some(ChartTiming {
+            offset: timing.tag("OFFSET", |t| &t.offset),
+            bpms: timing.tag("BPMS", |t| &t.bpms),
+            stops: timing.tag("STOPS", |t| &t.stops),
+            delays: timing.tag("DELAYS", |t| &t.delays),
+            scrolls: timing.tag("SCROLLS", |t| &t.scrolls),
+            time_signatures: timing.tag("TIMESIGNATURES", |t| &t.time_signatures),
+            tick_counts: timing.tag("TICKCOUNTS", |t| &t.tick_counts),
+            speeds: timing.tag("SPEEDS", |t| &t.speeds),
+            warps: timing.tag("WARPS", |t| &t.warps),
+            fakes: timing.tag("FAKES", |t| &t.fakes),
+            combos: timing.tag("COMBOS", |t| &t.combos),
+            labels: timing.tag("LABELS", |t| &t.labels),
+        });
+        tags.conflicts.extend(timing.conflicts);
+    } else if let Some(base_timing) = &base.timing {
+        // Removed on one side, which conflicts with changing it on the other side
+        let changed = |chart: &Chart| chart.timing.as_ref().is_some_and(|t| t != base_timing);
+        if changed(ours) || changed(theirs) {
+            tags.conflicts.push("TIMING");
+            chart.timing = ours.timing.clone();
+        }
+    }
+
+    let mut conflicts: Vec<ChartRegion> =
+        tags.conflicts.into_iter().map(ChartRegion::Tag).collect();
+    let (note_data, measures) = merge_notes(base, ours, theirs);
+    chart.note_data = note_data;
+    conflicts.extend(measures);
+
+    (chart, conflicts)
+}
+
+/// Merges the notes measure by measure, measures are compared by their notes so a measure that
+/// is written with more rows isn't a change.
+fn merge_notes(
+    base: &Chart,
+    ours: &Chart,
+    theirs: &Chart,
+) -> (Vec<Vec<NoteType>>, Vec<ChartRegion>) {
+    let columns = match ours.columns() {
+        Some(columns) => columns,
+        // The notes can't be split into rows without the amount of lanes
+        None => {
+            let mut notes = Merger::new(base, ours, theirs);
+            let note_data = notes.tag("NOTES", |c| &c.note_data);
+            return (
+                note_data,
+                notes.conflicts.into_iter().map(ChartRegion::Tag).collect(),
+            );
+        }
+    };
+
+    let (base_measures, our_measures, their_measures) =
+        (measures(base), measures(ours), measures(theirs));
+    // Keep the note data as it was written when at most one side changed the notes
+    if our_measures == base_measures {
+        return (theirs.note_data.clone(), vec![]);
+    }
+    if their_measures == base_measures || their_measures == our_measures {
+        return (ours.note_data.clone(), vec![]);
+    }
+
+    let count = base_measures
+        .len()
+        .max(our_measures.len())
+        .max(their_measures.len());
+    let pad = |mut measures: Vec<Vec<TimedNote>>| {
+        measures.resize(count, vec![]);
+        measures
+    };
+    let (base_measures, our_measures, their_measures) =
+        (pad(base_measures), pad(our_measures), pad(their_measures));
+
+    let mut merged = vec![];
+    let mut conflicts = BTreeSet::new();
+    for i in 0..count {
+        let (measure, conflict) =
+            merge_value(&base_measures[i], &our_measures[i], &their_measures[i]);
+        if conflict {
+            conflicts.insert(i);
+        }
+        merged.push(measure.clone());
+    }
+
+    // A hold that was added, moved or removed on one side spans measures that were merged on
+    // their own, so when the other side changed its lane in any of them, all of the measures of
+    // the hold are a conflict and our version of them is kept
+    let base_holds = holds(&base_measures);
+    let changed_holds = |measures: &[Vec<TimedNote>]| -> Vec<(usize, usize, usize)> {
+        let side_holds = holds(measures);
+        side_holds
+            .iter()
+            .filter(|h| !base_holds.contains(h))
+            .chain(base_holds.iter().filter(|h| !side_holds.contains(h)))
+            .filter(|(_, head, tail)| head / ROWS_PER_MEASURE < tail / ROWS_PER_MEASURE)
+            .map(|&(column, head, tail)| {
+                let (first, last) = (head / ROWS_PER_MEASURE, tail / ROWS_PER_MEASURE);
+                (column, first as usize, last as usize)
+            })
+            .collect()
+    };
+    let sides = [
+        (&our_measures, changed_holds(&our_measures)),
+        (&their_measures, changed_holds(&their_measures)),
+    ];
+
+    // Keeping our measures for a hold can split another hold, so this is repeated until nothing
+    // changes
+    let lane = |measure: &[TimedNote], column: usize| -> Vec<TimedNote> {
+        measure
+            .iter()
+            .filter(|n| n.column == column)
+            .cloned()
+            .collect()
+    };
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (side, side_holds) in &sides {
+            for &(column, first, last) in side_holds {
+                let split =
+                    (first..=last).any(|i| lane(&merged[i], column) != lane(&side[i], column));
+                if !split {
+                    continue;
+                }
+                for i in first..=last {
+                    if conflicts.insert(i) || merged[i] != our_measures[i] {
+                        merged[i] = our_measures[i].clone();
+                        changed = true;
+                    }
+                }
+            }
+        }
+    }
+
+    let notes: Vec<TimedNote> = merged.into_iter().flatten().collect();
+    (
+        note_data_from_notes(columns, &notes),
+        conflicts.into_iter().map(ChartRegion::Measure).collect(),
+    )
+}
+
+/// Returns the holds and rolls in measures of notes as their lane and the rows of their head and
+/// tail.
+fn holds(measures: &[Vec<TimedNote>]) -> Vec<(usize, u32, u32)> {
+    let mut heads: BTreeMap<usize, u32> = BTreeMap::new();
+    let mut holds = vec![];
+    for note in measures.iter().flatten() {
+        match note.note_type {
+            NoteType::HoldHead | NoteType::RollHead => {
+                heads.insert(note.column, note.row);
+            }
+            NoteType::HoldOrRollTail => {
+                if let Some(head) = heads.remove(&note.column) {
+                    holds.push((note.column, head, note.row));
+                }
+            }
+            _ => {}
+        }
+    }
+    holds
+}
+
+/// Returns the notes of a chart split into measures.
+fn measures(chart: &Chart) -> Vec<Vec<TimedNote>> {
+    let mut measures: Vec<Vec<TimedNote>> = vec![];
+    for note in chart.notes() {
+        let measure = (note.row / ROWS_PER_MEASURE) as usize;
+        if measures.len() <= measure {
+            measures.resize(measure + 1, vec![]);
+        }
+        measures[measure].push(note);
+    }
+    measures
+}
+
+fn chart_timing(simfile: &Simfile, chart: &Chart) -> ChartTiming {
+    chart
+        .timing
+        .clone()
+        .unwrap_or_else(|| ChartTiming::from_simfile(simfile))
+}
+
+/// Returns true if anything that is merged differs between the charts.
+fn charts_differ(first_sim: &Simfile, first: &Chart, second_sim: &Simfile, second: &Chart) -> bool {
+    let notes_differ = match first.columns() {
+        Some(_) => measures(first) != measures(second),
+        None => first.note_data != second.note_data,
+    };
+    let timing_differs = first.timing.is_some() != second.timing.is_some()
+        || (first.timing.is_some()
+            && chart_timing(first_sim, first) != chart_timing(second_sim, second));
+
+    first.author != second.author
+        || first.difficulty_name != second.difficulty_name
+        || first.meter != second.meter
+        || first.radar_values != second.radar_values
+        || first.lasers != second.lasers
+        || timing_differs
+        || notes_differ
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Conflict::Tag(tag) => write!(f, "{}", tag),
+            Conflict::Chart {
+                chart_type,
+                difficulty,
+                author,
+                region,
+            } => {
+                write!(f, "{} {}", chart_type, difficulty)?;
+                if let Some(author) = author {
+                    write!(f, " ({})", author)?;
+                }
+                match region {
+                    ChartRegion::Chart => write!(f, ": whole chart"),
+                    ChartRegion::Tag(tag) => write!(f, ": {}", tag),
+                    ChartRegion::Measure(measure) => write!(f, ": measure {}", measure),
+                }
+            }
+        }
+    }
+}
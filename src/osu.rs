@@ -0,0 +1,361 @@
+use crate::simfile::{
+    chart_type_for_columns, columns_for_chart_type, note_data_from_notes, Chart, ChartDifficulty,
+    NoteType, Simfile, TimedNote, ROWS_PER_BEAT,
+};
+use crate::timing::TimingData;
+use crate::SimfileParseError;
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, Write};
+
+/// The width of the osu! playfield, hit objects are placed in a lane by their x position.
+const PLAYFIELD_WIDTH: u32 = 512;
+
+/// The value of `Mode` in an osu!mania beatmap.
+const MANIA_MODE: u32 = 3;
+
+/// The bit set in the type of a hit object for a hold note.
+const HOLD_TYPE: u32 = 128;
+
+struct TimingPoint {
+    time: f32,
+    beat_length: f32,
+    uninherited: bool,
+}
+
+struct HitObject {
+    x: u32,
+    time: f32,
+    end_time: Option<f32>,
+}
+
+/// Parses an osu!mania beatmap (.osu) into a simfile with a single chart.
+///
+/// Beat 0 is placed on the first uninherited timing point, moved back by whole measures if there
+/// are notes before it. The following timing points become BPM changes, and every note is
+/// snapped to the nearest subdivision of a beat with `TimingData::quantized_beat_at_seconds`.
+/// Inherited timing points (scroll speed changes) are ignored. osu! has no difficulty meter, so
+/// the meter is always 1, and the difficulty is found from the name of the beatmap, or `Edit`.
+pub fn parse_osu<R: BufRead>(reader: &mut R) -> Result<Simfile, SimfileParseError> {
+    let mut data = String::new();
+    if reader.read_to_string(&mut data).is_err() {
+        return Err(SimfileParseError::BufReadError);
+    }
+
+    let mut section = "";
+    let mut values: BTreeMap<&str, &str> = BTreeMap::new();
+    let mut background = None;
+    let mut timing_points = vec![];
+    let mut hit_objects = vec![];
+
+    for line in data.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with("//") {
+            continue;
+        }
+        if line.starts_with('[') && line.ends_with(']') {
+            section = &line[1..line.len() - 1];
+            continue;
+        }
+
+        match section {
+            "General" | "Metadata" | "Difficulty" => {
+                if let Some(i) = line.find(':') {
+                    values.insert(line[..i].trim(), line[i + 1..].trim());
+                }
+            }
+            "Events" => {
+                let fields: Vec<&str> = line.split(',').map(|f| f.trim()).collect();
+                if background.is_none() && (fields[0] == "0" || fields[0] == "Background") {
+                    background = fields.get(2).map(|f| f.trim_matches('"').to_string());
+                }
+            }
+            "TimingPoints" => timing_points.push(parse_timing_point(line)?),
+            "HitObjects" => hit_objects.push(parse_hit_object(line)?),
+            _ => {}
+        }
+    }
+
+    let mode = values.get("Mode").and_then(|m| m.parse().ok()).unwrap_or(0);
+    if mode != MANIA_MODE {
+        return Err(SimfileParseError::NotAnOsuManiaBeatmap);
+    }
+
+    let keys = match values.get("CircleSize").map(|c| c.parse::<f32>()) {
+        Some(Ok(keys)) => keys.round() as usize,
+        _ => return Err(SimfileParseError::InvalidOsuData),
+    };
+    let chart_type = match chart_type_for_columns(keys) {
+        Some(chart_type) => chart_type,
+        None => return Err(SimfileParseError::UnsupportedKeyCount),
+    };
+
+    let timing = timing_from_points(&timing_points, &hit_objects)?;
+
+    let value = |key: &str| {
+        values
+            .get(key)
+            .filter(|v| !v.is_empty())
+            .map(|v| v.to_string())
+    };
+    let mut sim = Simfile::new();
+    sim.title = value("TitleUnicode").or_else(|| value("Title"));
+    sim.title_translit = value("Title").filter(|t| Some(t) != sim.title.as_ref());
+    sim.artist = value("ArtistUnicode").or_else(|| value("Artist"));
+    sim.artist_translit = value("Artist").filter(|a| Some(a) != sim.artist.as_ref());
+    sim.credit = value("Creator");
+    sim.origin = value("Source");
+    sim.music_path = value("AudioFilename");
+    sim.background_path = background;
+    sim.sample_start = value("PreviewTime")
+        .and_then(|t| t.parse::<f32>().ok())
+        .filter(|t| *t >= 0.0)
+        .map(|t| t / 1000.0);
+    sim.offset = Some(timing.offset);
+    sim.bpms = timing.bpms.clone();
+
+    let row_at = |time: f32| {
+        (timing.quantized_beat_at_seconds(time / 1000.0) * ROWS_PER_BEAT as f32).round() as u32
+    };
+    let mut notes = vec![];
+    for object in hit_objects {
+        let column = ((object.x * keys as u32 / PLAYFIELD_WIDTH) as usize).min(keys - 1);
+        let row = row_at(object.time);
+        let end_row = object.end_time.map(row_at).filter(|end| *end > row);
+
+        notes.push(TimedNote {
+            row,
+            column,
+            note_type: match end_row {
+                Some(_) => NoteType::HoldHead,
+                None => NoteType::Normal,
+            },
+        });
+        if let Some(end_row) = end_row {
+            notes.push(TimedNote {
+                row: end_row,
+                column,
+                note_type: NoteType::HoldOrRollTail,
+            });
+        }
+    }
+    notes.sort_by_key(|n| (n.row, n.column));
+
+    let difficulty_name = value("Version").unwrap_or_default();
+    sim.charts.push(Chart {
+        chart_type: chart_type.to_string(),
+        author: value("Creator"),
+        difficulty: ChartDifficulty::from_name(&difficulty_name).unwrap_or(ChartDifficulty::Edit),
+        difficulty_name,
+        meter: 1,
+        radar_values: Vec::new(),
+        note_data: note_data_from_notes(keys, &notes),
+        lasers: Vec::new(),
+        timing: None,
+        credit: None,
+    });
+
+    Ok(sim)
+}
+
+fn parse_timing_point(line: &str) -> Result<TimingPoint, SimfileParseError> {
+    let fields: Vec<&str> = line.split(',').map(|f| f.trim()).collect();
+    let number = |i: usize| fields.get(i).and_then(|f| f.parse::<f32>().ok());
+
+    match (number(0), number(1)) {
+        (Some(time), Some(beat_length)) => Ok(TimingPoint {
+            time,
+            beat_length,
+            // Old beatmaps don't have the field, and use negative beat lengths for inherited points
+            uninherited: number(6).map(|u| u != 0.0).unwrap_or(true) && beat_length > 0.0,
+        }),
+        _ => Err(SimfileParseError::InvalidOsuData),
+    }
+}
+
+fn parse_hit_object(line: &str) -> Result<HitObject, SimfileParseError> {
+    let fields: Vec<&str> = line.split(',').map(|f| f.trim()).collect();
+    let number = |i: usize| fields.get(i).and_then(|f| f.parse::<f32>().ok());
+
+    let (x, time, object_type) = match (number(0), number(2), number(3)) {
+        (Some(x), Some(time), Some(object_type)) => (x, time, object_type as u32),
+        _ => return Err(SimfileParseError::InvalidOsuData),
+    };
+
+    // The end time of a hold is the first value of the extra parameters, e.g. "1500:0:0:0:0:"
+    let end_time = if object_type & HOLD_TYPE != 0 {
+        match fields
+            .get(5)
+            .and_then(|f| f.split(':').next()?.parse().ok())
+        {
+            Some(end_time) => Some(end_time),
+            None => return Err(SimfileParseError::InvalidOsuData),
+        }
+    } else {
+        None
+    };
+
+    Ok(HitObject {
+        x: x.max(0.0) as u32,
+        time,
+        end_time,
+    })
+}
+
+/// Creates the timing of the song from the uninherited timing points.
+fn timing_from_points(
+    points: &[TimingPoint],
+    hit_objects: &[HitObject],
+) -> Result<TimingData, SimfileParseError> {
+    let timed_bpms: Vec<(f32, f32)> = points
+        .iter()
+        .filter(|p| p.uninherited)
+        .map(|p| (p.time / 1000.0, 60000.0 / p.beat_length))
+        .collect();
+    let earliest = hit_objects
+        .iter()
+        .map(|o| o.time / 1000.0)
+        .fold(f32::INFINITY, f32::min);
+
+    match TimingData::from_timed_bpms(&timed_bpms, earliest) {
+        Some(timing) => Ok(timing),
+        None => Err(SimfileParseError::InvalidOsuData),
+    }
+}
+
+/// Writes a chart of a simfile as an osu!mania beatmap (.osu).
+///
+/// Every BPM change becomes an uninherited timing point, and since osu! has no stops, another
+/// timing point is added at the end of every stop to keep the measure lines on the beat. Only
+/// normal notes, holds and rolls are written, rolls are written as holds. Returns an
+/// `InvalidInput` error if the amount of lanes can't be found from the chart type.
+pub fn write_osu<W: Write>(simfile: &Simfile, chart: &Chart, writer: &mut W) -> io::Result<()> {
+    let keys = match columns_for_chart_type(&chart.chart_type) {
+        Some(keys) => keys as u32,
+        None => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "unknown chart type",
+            ))
+        }
+    };
+    let timing = TimingData::from_chart(simfile, chart);
+    let text = |value: &Option<String>| value.clone().unwrap_or_default();
+    let milliseconds = |beat: f32| (timing.seconds_at_beat(beat) * 1000.0).round();
+
+    writeln!(writer, "osu file format v14")?;
+    writeln!(writer)?;
+    writeln!(writer, "[General]")?;
+    writeln!(writer, "AudioFilename: {}", text(&simfile.music_path))?;
+    writeln!(writer, "AudioLeadIn: 0")?;
+    writeln!(
+        writer,
+        "PreviewTime: {}",
+        simfile
+            .sample_start
+            .map(|s| (s * 1000.0).round())
+            .unwrap_or(-1.0)
+    )?;
+    writeln!(writer, "Mode: {}", MANIA_MODE)?;
+    writeln!(writer)?;
+
+    writeln!(writer, "[Metadata]")?;
+    writeln!(
+        writer,
+        "Title:{}",
+        text(
+            &simfile
+                .title_translit
+                .clone()
+                .or_else(|| simfile.title.clone())
+        )
+    )?;
+    writeln!(writer, "TitleUnicode:{}", text(&simfile.title))?;
+    writeln!(
+        writer,
+        "Artist:{}",
+        text(
+            &simfile
+                .artist_translit
+                .clone()
+                .or_else(|| simfile.artist.clone())
+        )
+    )?;
+    writeln!(writer, "ArtistUnicode:{}", text(&simfile.artist))?;
+    writeln!(
+        writer,
+        "Creator:{}",
+        text(
+            &chart
+                .credit
+                .clone()
+                .or_else(|| chart.author.clone())
+                .or_else(|| simfile.credit.clone())
+        )
+    )?;
+    match chart.difficulty_name.as_ref() {
+        "" => writeln!(writer, "Version:{}", chart.difficulty)?,
+        name => writeln!(writer, "Version:{}", name)?,
+    }
+    writeln!(writer, "Source:{}", text(&simfile.origin))?;
+    writeln!(writer)?;
+
+    writeln!(writer, "[Difficulty]")?;
+    writeln!(writer, "HPDrainRate:8")?;
+    writeln!(writer, "CircleSize:{}", keys)?;
+    writeln!(writer, "OverallDifficulty:8")?;
+    writeln!(writer, "ApproachRate:5")?;
+    writeln!(writer, "SliderMultiplier:1.4")?;
+    writeln!(writer, "SliderTickRate:1")?;
+    writeln!(writer)?;
+
+    writeln!(writer, "[Events]")?;
+    if let Some(background) = &simfile.background_path {
+        writeln!(writer, "0,0,\"{}\",0,0", background)?;
+    }
+    writeln!(writer)?;
+
+    let timing_points: Vec<(f32, f32)> = timing
+        .timed_bpms()
+        .into_iter()
+        .filter(|(_, bpm)| *bpm > 0.0)
+        .collect();
+
+    writeln!(writer, "[TimingPoints]")?;
+    for (seconds, bpm) in timing_points {
+        let time = (seconds * 1000.0).round();
+        writeln!(writer, "{},{},4,0,0,100,1,0", time, 60000.0 / bpm)?;
+    }
+    writeln!(writer)?;
+
+    let notes = chart.notes();
+    let mut hit_objects = vec![];
+    for (i, note) in notes.iter().enumerate() {
+        let end_row = match note.note_type {
+            NoteType::Normal => None,
+            NoteType::HoldHead | NoteType::RollHead => notes[i + 1..]
+                .iter()
+                .find(|n| n.column == note.column)
+                .filter(|n| n.note_type == NoteType::HoldOrRollTail)
+                .map(|n| n.row),
+            _ => continue,
+        };
+
+        let x = (note.column as u32 * PLAYFIELD_WIDTH + PLAYFIELD_WIDTH / 2) / keys;
+        let time = milliseconds(note.beat());
+        match end_row {
+            Some(end_row) => {
+                let end_time = milliseconds(end_row as f32 / ROWS_PER_BEAT as f32);
+                hit_objects.push((time, x, HOLD_TYPE, format!("{}:0:0:0:0:", end_time)));
+            }
+            None => hit_objects.push((time, x, 1, "0:0:0:0:".to_string())),
+        }
+    }
+    hit_objects.sort_by(|a, b| (a.0, a.1).partial_cmp(&(b.0, b.1)).unwrap());
+
+    writeln!(writer, "[HitObjects]")?;
+    for (time, x, object_type, extras) in hit_objects {
+        writeln!(writer, "{},192,{},{},0,{}", x, time, object_type, extras)?;
+    }
+
+    Ok(())
+}
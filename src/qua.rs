@@ -0,0 +1,395 @@
+use crate::simfile::{
+    columns_for_chart_type, note_data_from_notes, Chart, ChartDifficulty, NoteType, Scroll,
+    Simfile, TimedNote, ROWS_PER_BEAT,
+};
+use crate::timing::TimingData;
+use crate::SimfileParseError;
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, Write};
+
+/// A list item of a .qua file, e.g. a timing point or a hit object.
+type Item = BTreeMap<String, String>;
+
+/// The parts of the YAML used by .qua files: top level values, and top level lists of items
+/// with single values. Anything nested deeper (e.g. the keysounds of a hit object) is skipped.
+struct QuaDocument {
+    values: BTreeMap<String, String>,
+    lists: BTreeMap<String, Vec<Item>>,
+}
+
+impl QuaDocument {
+    fn parse(data: &str) -> QuaDocument {
+        let mut values = BTreeMap::new();
+        let mut lists: BTreeMap<String, Vec<Item>> = BTreeMap::new();
+        let mut list: Option<String> = None;
+        let mut item_indent: Option<usize> = None;
+
+        for line in data.lines() {
+            let content = line.trim_start();
+            if content.trim().is_empty() || content.starts_with('#') {
+                continue;
+            }
+            let indent = line.len() - content.len();
+
+            if indent == 0 && !content.starts_with('-') {
+                let (key, value) = split_value(content);
+                if value.is_empty() {
+                    list = Some(key.to_string());
+                    lists.entry(key.to_string()).or_default();
+                } else {
+                    list = None;
+                    values.insert(key.to_string(), unquote(value));
+                }
+                item_indent = None;
+                continue;
+            }
+
+            let items = match &list {
+                Some(list) => lists.get_mut(list).unwrap(),
+                None => continue,
+            };
+            if content.starts_with("- ") && item_indent.map_or(true, |i| i == indent) {
+                item_indent = Some(indent);
+                items.push(Item::new());
+            }
+
+            // Values of the item are aligned with the first value after the "- "
+            let value_indent = item_indent.map(|i| i + 2);
+            let content = match content.strip_prefix("- ") {
+                Some(c) if Some(indent) == item_indent => c,
+                Some(_) => continue,
+                None if Some(indent) == value_indent => content,
+                None => continue,
+            };
+            if let Some(item) = items.last_mut() {
+                let (key, value) = split_value(content);
+                item.insert(key.to_string(), unquote(value));
+            }
+        }
+
+        QuaDocument { values, lists }
+    }
+
+    fn value(&self, key: &str) -> Option<String> {
+        self.values.get(key).filter(|v| !v.is_empty()).cloned()
+    }
+
+    fn list(&self, key: &str) -> &[Item] {
+        self.lists.get(key).map(|l| l.as_slice()).unwrap_or(&[])
+    }
+}
+
+fn split_value(content: &str) -> (&str, &str) {
+    match content.find(':') {
+        Some(i) => (content[..i].trim(), content[i + 1..].trim()),
+        None => (content.trim(), ""),
+    }
+}
+
+fn unquote(value: &str) -> String {
+    let quoted =
+        |quote: char| value.len() >= 2 && value.starts_with(quote) && value.ends_with(quote);
+
+    if quoted('\'') {
+        value[1..value.len() - 1].replace("''", "'")
+    } else if quoted('"') {
+        value[1..value.len() - 1]
+            .replace("\\\"", "\"")
+            .replace("\\\\", "\\")
+    } else {
+        value.to_string()
+    }
+}
+
+/// Returns a number from an item, values that are 0 are left out of .qua files.
+fn number(item: &Item, key: &str) -> Result<f32, SimfileParseError> {
+    match item.get(key).map(|v| v.parse()) {
+        Some(Ok(number)) => Ok(number),
+        Some(Err(_)) => Err(SimfileParseError::InvalidQuaData),
+        None => Ok(0.0),
+    }
+}
+
+/// Parses a Quaver map (.qua) into a simfile with a single chart.
+///
+/// The timing points and notes are converted from milliseconds to beats the same way as
+/// `osu::parse_osu`, with `TimingData::from_timed_bpms` and
+/// `TimingData::quantized_beat_at_seconds`. Slider velocities become scroll speed changes. 4 key
+/// maps become "dance-single" charts and 7 key maps "kb7-single" charts, or "beat-single7" charts
+/// with the scratch key as the first lane; other key modes return `UnsupportedKeyCount`. Quaver
+/// has no difficulty meter, so the meter is always 1, and the difficulty is found from the name
+/// of the map, or `Edit`.
+pub fn parse_qua<R: BufRead>(reader: &mut R) -> Result<Simfile, SimfileParseError> {
+    let mut data = String::new();
+    if reader.read_to_string(&mut data).is_err() {
+        return Err(SimfileParseError::BufReadError);
+    }
+    let document = QuaDocument::parse(&data);
+
+    let keys = match document
+        .value("Mode")
+        .map(|m| m.trim_start_matches("Keys").parse::<usize>())
+    {
+        Some(Ok(keys)) => keys,
+        _ => return Err(SimfileParseError::InvalidQuaData),
+    };
+    let scratch = document.value("HasScratchKey") == Some("true".to_string());
+    let chart_type = match (keys, scratch) {
+        (4, false) => "dance-single",
+        (7, false) => "kb7-single",
+        (7, true) => "beat-single7",
+        _ => return Err(SimfileParseError::UnsupportedKeyCount),
+    };
+    let keys = if scratch { keys + 1 } else { keys };
+
+    let mut timed_bpms = vec![];
+    for point in document.list("TimingPoints") {
+        timed_bpms.push((number(point, "StartTime")? / 1000.0, number(point, "Bpm")?));
+    }
+    let mut hit_objects = vec![];
+    for object in document.list("HitObjects") {
+        let lane = number(object, "Lane")? as usize;
+        if lane < 1 || lane > keys {
+            return Err(SimfileParseError::InvalidQuaData);
+        }
+        // The scratch is the last lane, but the first column of a beat chart
+        let column = match scratch {
+            true if lane == keys => 0,
+            true => lane,
+            false => lane - 1,
+        };
+        hit_objects.push((
+            number(object, "StartTime")? / 1000.0,
+            number(object, "EndTime")? / 1000.0,
+            column,
+        ));
+    }
+
+    let earliest = hit_objects
+        .iter()
+        .map(|o| o.0)
+        .fold(f32::INFINITY, f32::min);
+    let timing = match TimingData::from_timed_bpms(&timed_bpms, earliest) {
+        Some(timing) => timing,
+        None => return Err(SimfileParseError::InvalidQuaData),
+    };
+
+    let mut sim = Simfile::new();
+    sim.title = document.value("Title");
+    sim.artist = document.value("Artist");
+    sim.origin = document.value("Source");
+    sim.credit = document.value("Creator");
+    sim.music_path = document.value("AudioFile");
+    sim.background_path = document.value("BackgroundFile");
+    sim.banner_path = document.value("BannerFile");
+    sim.sample_start = document
+        .value("SongPreviewTime")
+        .and_then(|t| t.parse::<f32>().ok())
+        .map(|t| t / 1000.0);
+    sim.offset = Some(timing.offset);
+    sim.bpms = timing.bpms.clone();
+
+    if let Some(initial) = document.value("InitialScrollVelocity") {
+        match initial.parse() {
+            Ok(1.0) => {}
+            Ok(factor) => sim.scrolls.push(Scroll { beat: 0.0, factor }),
+            Err(_) => return Err(SimfileParseError::InvalidQuaData),
+        }
+    }
+    for velocity in document.list("SliderVelocities") {
+        let beat = timing.quantized_beat_at_seconds(number(velocity, "StartTime")? / 1000.0);
+        let factor = number(velocity, "Multiplier")?;
+        match sim.scrolls.last_mut() {
+            Some(last) if last.beat == beat => last.factor = factor,
+            _ => sim.scrolls.push(Scroll { beat, factor }),
+        }
+    }
+
+    let row_at = |seconds: f32| {
+        (timing.quantized_beat_at_seconds(seconds) * ROWS_PER_BEAT as f32).round() as u32
+    };
+    let mut notes = vec![];
+    for (start, end, column) in hit_objects {
+        let row = row_at(start);
+        let end_row = Some(end)
+            .filter(|e| *e > 0.0)
+            .map(row_at)
+            .filter(|e| *e > row);
+
+        notes.push(TimedNote {
+            row,
+            column,
+            note_type: match end_row {
+                Some(_) => NoteType::HoldHead,
+                None => NoteType::Normal,
+            },
+        });
+        if let Some(end_row) = end_row {
+            notes.push(TimedNote {
+                row: end_row,
+                column,
+                note_type: NoteType::HoldOrRollTail,
+            });
+        }
+    }
+    notes.sort_by_key(|n| (n.row, n.column));
+
+    let difficulty_name = document.value("DifficultyName").unwrap_or_default();
+    sim.charts.push(Chart {
+        chart_type: chart_type.to_string(),
+        author: document.value("Creator"),
+        difficulty: ChartDifficulty::from_name(&difficulty_name).unwrap_or(ChartDifficulty::Edit),
+        difficulty_name,
+        meter: 1,
+        radar_values: Vec::new(),
+        note_data: note_data_from_notes(keys, &notes),
+        lasers: Vec::new(),
+        timing: None,
+        credit: None,
+    });
+
+    Ok(sim)
+}
+
+/// Quotes a value if YAML wouldn't read it back as the same string.
+fn quote(value: &str) -> String {
+    let special = value.is_empty()
+        || value.starts_with(|c: char| c.is_whitespace() || "-?!&*|>'\"%@`#[]{},".contains(c))
+        || value.ends_with(char::is_whitespace)
+        || value.contains(": ")
+        || value.contains(" #")
+        || value.ends_with(':');
+
+    if special {
+        format!("'{}'", value.replace('\'', "''"))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Writes a chart of a simfile as a Quaver map (.qua).
+///
+/// The BPM changes are written as timing points with `TimingData::timed_bpms`, so stops are
+/// replaced by a timing point at their end, and scroll speed changes are written as slider
+/// velocities. Only normal notes, holds and rolls are written, rolls are written as holds.
+/// "beat-single7" charts are written with a scratch key. Returns an `InvalidInput` error if the
+/// chart type doesn't have 4 or 7 lanes.
+pub fn write_qua<W: Write>(simfile: &Simfile, chart: &Chart, writer: &mut W) -> io::Result<()> {
+    // Quaver only has 4 and 7 key modes, 7 keys can have a scratch key
+    let scratch = chart.chart_type == "beat-single7";
+    let keys = match columns_for_chart_type(&chart.chart_type) {
+        Some(8) if scratch => 7,
+        Some(keys) if keys == 4 || keys == 7 => keys,
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "chart type isn't supported by Quaver",
+            ))
+        }
+    };
+    let timing = TimingData::from_chart(simfile, chart);
+    let text = |value: &Option<String>| quote(value.as_deref().unwrap_or_default());
+    let milliseconds = |beat: f32| (timing.seconds_at_beat(beat) * 1000.0).round();
+
+    writeln!(writer, "AudioFile: {}", text(&simfile.music_path))?;
+    if let Some(sample_start) = simfile.sample_start {
+        writeln!(
+            writer,
+            "SongPreviewTime: {}",
+            (sample_start * 1000.0).round()
+        )?;
+    }
+    writeln!(writer, "BackgroundFile: {}", text(&simfile.background_path))?;
+    if simfile.banner_path.is_some() {
+        writeln!(writer, "BannerFile: {}", text(&simfile.banner_path))?;
+    }
+    writeln!(writer, "MapId: -1")?;
+    writeln!(writer, "MapSetId: -1")?;
+    writeln!(writer, "Mode: Keys{}", keys)?;
+    writeln!(writer, "Title: {}", text(&simfile.title))?;
+    writeln!(writer, "Artist: {}", text(&simfile.artist))?;
+    writeln!(writer, "Source: {}", text(&simfile.origin))?;
+    writeln!(writer, "Tags: ''")?;
+    writeln!(
+        writer,
+        "Creator: {}",
+        text(
+            &chart
+                .credit
+                .clone()
+                .or_else(|| chart.author.clone())
+                .or_else(|| simfile.credit.clone())
+        )
+    )?;
+    match chart.difficulty_name.as_ref() {
+        "" => writeln!(writer, "DifficultyName: {}", chart.difficulty)?,
+        name => writeln!(writer, "DifficultyName: {}", quote(name))?,
+    }
+    writeln!(writer, "Description: ''")?;
+    writeln!(writer, "BPMDoesNotAffectScrollVelocity: true")?;
+    // A scroll speed on the first beat is the initial scroll speed
+    let scrolls = chart
+        .timing
+        .as_ref()
+        .map_or(&simfile.scrolls, |t| &t.scrolls);
+    let (initial, scrolls) = match scrolls.split_first() {
+        Some((first, rest)) if first.beat <= 0.0 => (first.factor, rest),
+        _ => (1.0, scrolls.as_slice()),
+    };
+    writeln!(writer, "InitialScrollVelocity: {}", initial)?;
+    writeln!(writer, "HasScratchKey: {}", scratch)?;
+    writeln!(writer, "EditorLayers: []")?;
+    writeln!(writer, "CustomAudioSamples: []")?;
+    writeln!(writer, "SoundEffects: []")?;
+
+    writeln!(writer, "TimingPoints:")?;
+    for (seconds, bpm) in timing.timed_bpms() {
+        writeln!(writer, "- StartTime: {}", (seconds * 1000.0).round())?;
+        writeln!(writer, "  Bpm: {}", bpm)?;
+    }
+
+    match scrolls.len() {
+        0 => writeln!(writer, "SliderVelocities: []")?,
+        _ => writeln!(writer, "SliderVelocities:")?,
+    }
+    for scroll in scrolls {
+        writeln!(writer, "- StartTime: {}", milliseconds(scroll.beat))?;
+        writeln!(writer, "  Multiplier: {}", scroll.factor)?;
+    }
+
+    let notes = chart.notes();
+    let mut hit_objects = vec![];
+    for (i, note) in notes.iter().enumerate() {
+        let end_row = match note.note_type {
+            NoteType::Normal => None,
+            NoteType::HoldHead | NoteType::RollHead => notes[i + 1..]
+                .iter()
+                .find(|n| n.column == note.column)
+                .filter(|n| n.note_type == NoteType::HoldOrRollTail)
+                .map(|n| n.row),
+            _ => continue,
+        };
+        let end_time = end_row.map(|row| milliseconds(row as f32 / ROWS_PER_BEAT as f32));
+        let lane = match note.column {
+            0 if scratch => keys + 1,
+            column if scratch => column,
+            column => column + 1,
+        };
+        hit_objects.push((milliseconds(note.beat()), lane, end_time));
+    }
+
+    match hit_objects.len() {
+        0 => writeln!(writer, "HitObjects: []")?,
+        _ => writeln!(writer, "HitObjects:")?,
+    }
+    for (time, lane, end_time) in hit_objects {
+        writeln!(writer, "- StartTime: {}", time)?;
+        writeln!(writer, "  Lane: {}", lane)?;
+        if let Some(end_time) = end_time {
+            writeln!(writer, "  EndTime: {}", end_time)?;
+        }
+        writeln!(writer, "  KeySounds: []")?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,194 @@
+use crate::simfile::{Chart, NoteType, RadarValues, Simfile, TimedNote, ROWS_PER_BEAT};
+use crate::timing::TimingData;
+use std::collections::BTreeMap;
+
+/// The size of the window used when looking for the peak density of a chart.
+const VOLTAGE_WINDOW_BEATS: u32 = 8;
+
+/// Calculates the radar values of a chart from its note data, using the timing of the chart to
+/// find the length of the song.
+///
+/// The length of the song is `music_length` if it's set, otherwise the time of the last note.
+pub fn calculate_radar_values(simfile: &Simfile, chart: &Chart) -> RadarValues {
+    let song_seconds = match simfile.music_length {
+        Some(length) => length,
+        None => {
+            let timing = TimingData::from_chart(simfile, chart);
+            match chart.notes().last() {
+                Some(note) => timing.seconds_at_beat(note.beat()),
+                None => 0.0,
+            }
+        }
+    };
+
+    calculate_radar_values_for_length(chart, song_seconds)
+}
+
+/// Calculates the radar values of a chart from its note data, using the same formulas as
+/// Stepmania.
+///
+/// `song_seconds` is the length of the song in seconds, all values except the counts will be 0
+/// if the length is 0 or less.
+pub fn calculate_radar_values_for_length(chart: &Chart, song_seconds: f32) -> RadarValues {
+    let rows = group_by_row(&chart.notes());
+
+    let mut notes = 0;
+    let mut taps = 0;
+    let mut jumps = 0;
+    let mut holds = 0;
+    let mut mines = 0;
+    let mut hands = 0;
+    let mut rolls = 0;
+    let mut lifts = 0;
+    let mut fakes = 0;
+    let mut chaos_rows = 0;
+
+    // The row each column is held until, used to find hands made from holds and taps
+    let mut held_until: BTreeMap<usize, u32> = BTreeMap::new();
+    let hold_ends = find_hold_ends(&rows);
+
+    for (row, row_notes) in &rows {
+        let pressed: Vec<&TimedNote> = row_notes.iter().filter(|n| is_pressed(n)).collect();
+        notes += pressed.len();
+        mines += row_notes
+            .iter()
+            .filter(|n| n.note_type == NoteType::Mine)
+            .count();
+        fakes += row_notes
+            .iter()
+            .filter(|n| n.note_type == NoteType::FakeNote)
+            .count();
+        lifts += pressed
+            .iter()
+            .filter(|n| n.note_type == NoteType::LiftNote)
+            .count();
+        holds += pressed
+            .iter()
+            .filter(|n| n.note_type == NoteType::HoldHead)
+            .count();
+        rolls += pressed
+            .iter()
+            .filter(|n| n.note_type == NoteType::RollHead)
+            .count();
+
+        if pressed.is_empty() {
+            continue;
+        }
+
+        taps += 1;
+        if pressed.len() >= 2 {
+            jumps += 1;
+        }
+
+        // Rows that are not on a 4th or 8th note
+        if row % (ROWS_PER_BEAT / 2) != 0 {
+            chaos_rows += 1;
+        }
+
+        held_until.retain(|_, end| *end > *row);
+        let held = held_until
+            .keys()
+            .filter(|c| !pressed.iter().any(|n| n.column == **c))
+            .count();
+        if pressed.len() + held >= 3 {
+            hands += 1;
+        }
+
+        for note in &pressed {
+            if let Some(end) = hold_ends.get(&(*row, note.column)) {
+                held_until.insert(note.column, *end);
+            }
+        }
+    }
+
+    let last_beat = rows.keys().last().map(|r| *r as f32 / ROWS_PER_BEAT as f32);
+    let ratio = |value: f32| match song_seconds > 0.0 {
+        true => f32::min(value / song_seconds, 1.0),
+        false => 0.0,
+    };
+
+    RadarValues {
+        stream: ratio(notes as f32 / 7.0),
+        voltage: ratio(peak_density(&rows) * last_beat.unwrap_or(0.0) / 10.0),
+        air: ratio(jumps as f32),
+        freeze: ratio(holds as f32),
+        chaos: ratio(chaos_rows as f32 * 0.5),
+        notes: Some(notes as f32),
+        taps: Some(taps as f32),
+        jumps: Some(jumps as f32),
+        holds: Some(holds as f32),
+        mines: Some(mines as f32),
+        hands: Some(hands as f32),
+        rolls: Some(rolls as f32),
+        lifts: Some(lifts as f32),
+        fakes: Some(fakes as f32),
+    }
+}
+
+/// Calculates the radar values of every chart that is missing them, or only has zeroes saved.
+pub fn fill_radar_values(simfile: &mut Simfile) {
+    for i in 0..simfile.charts.len() {
+        let chart = &simfile.charts[i];
+        let missing = chart.radar_values.iter().all(|r| {
+            r.stream == 0.0 && r.voltage == 0.0 && r.air == 0.0 && r.freeze == 0.0 && r.chaos == 0.0
+        });
+
+        if missing {
+            let radar_values = calculate_radar_values(simfile, chart);
+            simfile.charts[i].radar_values = vec![radar_values];
+        }
+    }
+}
+
+/// Returns true if the note needs to be stepped on, i.e. it's not a mine, fake, tail or keysound.
+fn is_pressed(note: &TimedNote) -> bool {
+    matches!(
+        note.note_type,
+        NoteType::Normal | NoteType::HoldHead | NoteType::RollHead | NoteType::LiftNote
+    )
+}
+
+fn group_by_row(notes: &[TimedNote]) -> BTreeMap<u32, Vec<TimedNote>> {
+    let mut rows: BTreeMap<u32, Vec<TimedNote>> = BTreeMap::new();
+    for note in notes {
+        rows.entry(note.row).or_default().push(note.clone());
+    }
+    rows
+}
+
+/// Returns the end row of every hold and roll, keyed by the row and column of the head.
+fn find_hold_ends(rows: &BTreeMap<u32, Vec<TimedNote>>) -> BTreeMap<(u32, usize), u32> {
+    let mut heads: BTreeMap<usize, u32> = BTreeMap::new();
+    let mut ends = BTreeMap::new();
+
+    for (row, row_notes) in rows {
+        for note in row_notes {
+            match note.note_type {
+                NoteType::HoldHead | NoteType::RollHead => {
+                    heads.insert(note.column, *row);
+                }
+                NoteType::HoldOrRollTail => {
+                    if let Some(head) = heads.remove(&note.column) {
+                        ends.insert((head, note.column), *row);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    ends
+}
+
+/// Returns the highest amount of notes per beat in any window of `VOLTAGE_WINDOW_BEATS` beats.
+fn peak_density(rows: &BTreeMap<u32, Vec<TimedNote>>) -> f32 {
+    let window_rows = VOLTAGE_WINDOW_BEATS * ROWS_PER_BEAT;
+    let mut windows: BTreeMap<u32, usize> = BTreeMap::new();
+
+    for (row, row_notes) in rows {
+        *windows.entry(row / window_rows).or_default() +=
+            row_notes.iter().filter(|n| is_pressed(n)).count();
+    }
+
+    windows.values().cloned().max().unwrap_or(0) as f32 / VOLTAGE_WINDOW_BEATS as f32
+}
@@ -1,6 +1,12 @@
 use std::fmt;
 
-#[derive(Debug, Clone)]
+mod notes;
+pub use self::notes::{
+    chart_type_for_columns, columns_for_chart_type, note_data_from_notes, Laser, LaserPoint,
+    LaserSide, TimedNote, BEATS_PER_MEASURE, ROWS_PER_BEAT, ROWS_PER_MEASURE,
+};
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct BPM {
     /// The beat where the BPM gets set
     pub beat: f32,
@@ -8,7 +14,7 @@ pub struct BPM {
     pub bpm: f32,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Stop {
     /// The beat the stop occurs
     pub beat: f32,
@@ -17,26 +23,139 @@ pub struct Stop {
 }
 
 #[derive(Debug, Clone, PartialEq)]
-pub enum BPMDisplayType {
+pub struct Delay {
+    /// The beat the delay occurs, before the notes on that beat
+    pub beat: f32,
+    /// How long the delay lasts
+    pub time: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeSignature {
+    /// The beat where the time signature starts
+    pub beat: f32,
+    /// The amount of beats in a measure
+    pub numerator: u32,
+    /// The note value of a beat, e.g. 4 for quarter notes
+    pub denominator: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scroll {
+    /// The beat where the scroll speed gets set
+    pub beat: f32,
+    /// The scroll speed, as a multiple of the normal speed
+    pub factor: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TickCount {
+    /// The beat where the tick count gets set
+    pub beat: f32,
+    /// How many times per beat a held hold note adds to the combo
+    pub ticks: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SpeedUnit {
+    Beats,
+    Seconds,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Speed {
+    /// The beat where the speed starts changing
+    pub beat: f32,
+    /// The speed to change to, as a multiple of the normal speed
+    pub ratio: f32,
+    /// How long it takes to change from the previous speed
+    pub duration: f32,
+    /// Whether the duration is in beats or in seconds
+    pub unit: SpeedUnit,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Warp {
+    /// The beat the warp starts on
+    pub beat: f32,
+    /// How many beats are skipped
+    pub length: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fake {
+    /// The beat the fake section starts on
+    pub beat: f32,
+    /// How many beats the notes are fake for
+    pub length: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Combo {
+    /// The beat where the combo multipliers get set
+    pub beat: f32,
+    /// How much the combo goes up for every hit note
+    pub hit_multiplier: u32,
+    /// How much the miss combo goes up for every missed note
+    pub miss_multiplier: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Label {
+    /// The beat of the label
+    pub beat: f32,
+    /// The name of the section starting on the beat, shown in the editor
+    pub label: String,
+}
+
+/// Timing that a chart uses instead of the timing of the song. (.sma and .ssc only)
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ChartTiming {
+    pub offset: Option<f32>,
+    pub bpms: Vec<BPM>,
+    pub stops: Vec<Stop>,
+    pub delays: Vec<Delay>,
+    pub scrolls: Vec<Scroll>,
+    pub time_signatures: Vec<TimeSignature>,
+    pub tick_counts: Vec<TickCount>,
+    pub speeds: Vec<Speed>,
+    pub warps: Vec<Warp>,
+    pub fakes: Vec<Fake>,
+    pub combos: Vec<Combo>,
+    pub labels: Vec<Label>,
+}
+
+impl ChartTiming {
+    /// Creates chart timing that is a copy of the song timing of a simfile.
+    pub fn from_simfile(simfile: &Simfile) -> ChartTiming {
+        ChartTiming {
+            offset: simfile.offset,
+            bpms: simfile.bpms.clone(),
+            stops: simfile.stops.clone(),
+            delays: simfile.delays.clone(),
+            scrolls: simfile.scrolls.clone(),
+            time_signatures: simfile.time_signatures.clone(),
+            tick_counts: simfile.tick_counts.clone(),
+            speeds: simfile.speeds.clone(),
+            warps: simfile.warps.clone(),
+            fakes: simfile.fakes.clone(),
+            combos: simfile.combos.clone(),
+            labels: simfile.labels.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DisplayBPM {
     /// Only a single value will be displayed
-    Single,
-    /// A range will be displayed
-    Range,
+    Single(f32),
+    /// A range will be displayed, from the first value to the second value
+    Range(f32, f32),
     /// Will randomly change
     Random,
 }
 
-#[derive(Debug, Clone)]
-pub struct DisplayBPM {
-    /// Sets how the BPM should be displayed
-    pub display_type: BPMDisplayType,
-    /// The first value (Used if `display_type` is `SINGLE` or `RANGE`)
-    pub value: f32,
-    /// The second value (Used if `display_type` is `RANGE`)
-    pub value2: f32,
-}
-
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct BgChange {
     /// Start beat for bg change
     pub start_beat: f32,
@@ -62,13 +181,68 @@ pub struct BgChange {
     pub second_color_string: Option<String>
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct FgChange {
+    /// Start beat for fg change
     pub start_beat: f32,
     /// File or folder name
     pub path: String,
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum Selectable {
+    /// The song is always shown on the music wheel
+    Yes,
+    /// The song is never shown on the music wheel
+    No,
+    /// The song is only selectable through roulette (Stepmania 3.9)
+    Roulette,
+    /// The song is only selectable in extra stage (Stepmania 3.9+ builds)
+    Es,
+    /// The song is only selectable in one more extra stage (Stepmania 3.9+ builds)
+    Omes,
+    /// A numeric value, songs with a value above 0 are selectable
+    Numeric(i32),
+    /// A value Stepmania doesn't recognize, kept as it was written
+    Unknown(String),
+}
+
+impl Selectable {
+    /// Returns whether Stepmania shows the song on the music wheel.
+    ///
+    /// Stepmania 5 no longer has special handling for `ROULETTE`, `ES` and `OMES`, and treats
+    /// them as always selectable. Unknown values are ignored, which keeps the song selectable.
+    pub fn is_selectable(&self) -> bool {
+        match self {
+            Selectable::No => false,
+            Selectable::Numeric(i) => *i > 0,
+            _ => true,
+        }
+    }
+}
+
+impl fmt::Display for Selectable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Selectable::Yes => write!(f, "YES"),
+            Selectable::No => write!(f, "NO"),
+            Selectable::Roulette => write!(f, "ROULETTE"),
+            Selectable::Es => write!(f, "ES"),
+            Selectable::Omes => write!(f, "OMES"),
+            Selectable::Numeric(i) => write!(f, "{}", i),
+            Selectable::Unknown(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstrumentTrack {
+    /// The instrument the track belongs to (e.g. "guitar", "rhythm", "bass")
+    pub instrument: String,
+    /// Path to the audio file for the instrument. (Relative from the Song's directory.)
+    pub path: String,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum ChartDifficulty {
     Beginner,
@@ -79,6 +253,35 @@ pub enum ChartDifficulty {
     Edit,
 }
 
+impl ChartDifficulty {
+    /// Looks up a difficulty by name, accepting the legacy names (e.g. "Heavy", "Smaniac",
+    /// "Another") that Stepmania still recognizes. The lookup is case-insensitive.
+    pub fn from_name(name: &str) -> Option<ChartDifficulty> {
+        match name.trim().to_lowercase().as_ref() {
+            "beginner" => Some(ChartDifficulty::Beginner),
+            "easy" | "basic" | "light" => Some(ChartDifficulty::Easy),
+            "medium" | "another" | "trick" | "standard" | "difficult" => {
+                Some(ChartDifficulty::Medium)
+            }
+            "hard" | "ssr" | "maniac" | "heavy" => Some(ChartDifficulty::Hard),
+            "challenge" | "smaniac" | "expert" | "oni" => Some(ChartDifficulty::Challenge),
+            "edit" => Some(ChartDifficulty::Edit),
+            _ => None,
+        }
+    }
+
+    /// Guesses the difficulty from the meter, the same way Stepmania does for charts without a
+    /// valid difficulty.
+    pub fn from_meter(meter: u16) -> ChartDifficulty {
+        match meter {
+            1 => ChartDifficulty::Beginner,
+            0..=3 => ChartDifficulty::Easy,
+            4..=6 => ChartDifficulty::Medium,
+            _ => ChartDifficulty::Hard,
+        }
+    }
+}
+
 impl fmt::Display for ChartDifficulty {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{:?}", self)
@@ -99,24 +302,70 @@ pub enum NoteType {
     InvalidNote,
 }
 
+/// The groove radar values of a chart for one player.
+///
+/// Stepmania 3.9 and In The Groove only store the first five categories, which is why the
+/// counts are optional.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct RadarValues {
+    /// The density of the chart, based on the average notes per second
+    pub stream: f32,
+    /// The peak density of the chart
+    pub voltage: f32,
+    /// The amount of jumps in the chart
+    pub air: f32,
+    /// The amount of holds in the chart
+    pub freeze: f32,
+    /// The amount of notes that are not 4ths or 8ths
+    pub chaos: f32,
+    /// The total number of notes (Stepmania 5.1+)
+    pub notes: Option<f32>,
+    /// The number of rows with a tap note or hold head
+    pub taps: Option<f32>,
+    /// The number of rows with two or more notes
+    pub jumps: Option<f32>,
+    /// The number of holds
+    pub holds: Option<f32>,
+    /// The number of mines
+    pub mines: Option<f32>,
+    /// The number of rows where three or more panels needs to be pressed at the same time
+    pub hands: Option<f32>,
+    /// The number of rolls
+    pub rolls: Option<f32>,
+    /// The number of lift notes
+    pub lifts: Option<f32>,
+    /// The number of fake notes
+    pub fakes: Option<f32>,
+}
+
 #[derive(Debug, Clone)]
 pub struct Chart {
     pub chart_type: String,
     pub author: Option<String>,
     pub difficulty: ChartDifficulty,
+    /// The difficulty as it was written in the file, before resolving legacy names
+    pub difficulty_name: String,
     /// The numerical difficulty level of the chart
     pub meter: u16,
-    // TODO: Figure out what all these radar values actually are
-    pub radar_values: Vec<f32>,
+    /// The groove radar values, one entry for every player the values were saved for
+    pub radar_values: Vec<RadarValues>,
     /// Note data is defined in terms of "measures", every "measure" contains the notes for that
     /// measures, which is represented as a flat vector of notes, where there is one entry for
     /// every lane and line in the measure.
     pub note_data: Vec<Vec<NoteType>>,
+    /// The analog lasers, which are kept apart from the note data (K-Shoot Mania only)
+    pub lasers: Vec<Laser>,
+    /// The timing of the chart, when it doesn't use the timing of the song
+    pub timing: Option<ChartTiming>,
+    /// The author of the chart, when it's saved apart from the description (.ssc only)
+    pub credit: Option<String>,
 }
 
 /// Represents a parsed Stepmania stepfile (.sm)
 #[derive(Debug, Clone)]
 pub struct Simfile {
+    /// The version of the file format the simfile was saved with. (.ssc only)
+    pub version: Option<f32>,
     /// The primary title of the song.
     pub title: Option<String>,
     /// The subtitle of the song.
@@ -131,6 +380,8 @@ pub struct Simfile {
     pub artist_translit: Option<String>,
     /// The genre of the song.
     pub genre: Option<String>,
+    /// The origin of the song, e.g. the game it originally appeared in.
+    pub origin: Option<String>,
     /// The Creator/Credits.
     pub credit: Option<String>,
     /// Path to the Banner. (Relative from the Song's directory.)
@@ -142,11 +393,25 @@ pub struct Simfile {
     /// Path to the Jacket. (Relative from the Song's directory.)
     pub jacket_path: Option<String>,
     /// Path to the CD Image. (Relative from the Song's directory.)
+    pub cd_image_path: Option<String>,
+    /// Path to the Disc Image. (Relative from the Song's directory.)
+    pub disc_image_path: Option<String>,
+    /// Path to the Lyrics file. (Relative from the Song's directory.)
     pub lyrics_path: Option<String>,
     /// The Song's CD Title image, small image meant to show the origin of the song.
     pub cd_title_path: Option<String>,
     /// Path to the Audio file for the Song. (Relative from the Song's directory.)
     pub music_path: Option<String>,
+    /// Path to a separate Audio file used for the song preview. (Relative from the Song's directory.)
+    pub preview_path: Option<String>,
+    /// Separate Audio files for each instrument of the song.
+    pub instrument_tracks: Vec<InstrumentTrack>,
+    /// The length of the Song's music in seconds.
+    pub music_length: Option<f32>,
+    /// The beat of the last note in the Song, used when the notes can't be examined.
+    pub last_beat_hint: Option<f32>,
+    /// The time of the last note in the Song in seconds, used when the notes can't be examined.
+    pub last_second_hint: Option<f32>,
     /// The Song's Offset. (Effects the timing of the start of the Notes.)
     pub offset: Option<f32>,
     /// The Song's Sample Start Time
@@ -154,17 +419,40 @@ pub struct Simfile {
     /// The Song's Sample Length
     pub sample_length: Option<f32>,
     /// Is this song selectable?
-    pub selectable: Option<bool>,
+    pub selectable: Option<Selectable>,
     /// The Song's Beats Per Minute's at certain times. (Can have multiple changes.)
     pub bpms: Vec<BPM>,
     /// The displayed BPM shown in-game
     pub display_bpm: Option<DisplayBPM>,
+    /// Whether the displayed BPM is locked to the value of `display_bpm`, even when the song has
+    /// BPM changes. (`#BPMLOCK`, written by some older editors.)
+    pub bpm_lock: Option<bool>,
     /// The Song's Stops. (Can have multiple changes.)
     pub stops: Vec<Stop>,
+    /// The Song's Delays, which are like stops but happen before the notes on their beat.
+    pub delays: Vec<Delay>,
+    /// Changes to the scroll speed of the notes, which don't affect the timing.
+    pub scrolls: Vec<Scroll>,
+    /// The time signatures of the song, which are only used for the measure lines.
+    pub time_signatures: Vec<TimeSignature>,
+    /// How often held hold notes add to the combo.
+    pub tick_counts: Vec<TickCount>,
+    /// Gradual changes to the scroll speed of the notes, which don't affect the timing.
+    pub speeds: Vec<Speed>,
+    /// Sections of beats that are skipped over, along with the notes in them.
+    pub warps: Vec<Warp>,
+    /// Sections of beats where the notes can't be hit and don't count.
+    pub fakes: Vec<Fake>,
+    /// Changes to how much the combo goes up for every note.
+    pub combos: Vec<Combo>,
+    /// Names for sections of the song, shown in the editor.
+    pub labels: Vec<Label>,
     /// Is used to control what backgrounds are loaded by the simfile and when they appear.
     pub bg_changes: Vec<BgChange>,
     /// Is used to control what foregrounds are loaded by the simfile and when they appear.
     pub fg_changes: Vec<FgChange>,
+    /// The keysound files used by the charts, referenced by index from the note data.
+    pub keysounds: Vec<String>,
     /// The charts of the simfile
     pub charts: Vec<Chart>,
 }
@@ -172,6 +460,7 @@ pub struct Simfile {
 impl Simfile {
     pub fn new() -> Simfile {
         Simfile {
+            version: None,
             title: None,
             subtitle: None,
             artist: None,
@@ -179,36 +468,108 @@ impl Simfile {
             subtitle_translit: None,
             artist_translit: None,
             genre: None,
+            origin: None,
             credit: None,
             banner_path: None,
             background_path: None,
             preview_video_path: None,
             jacket_path: None,
+            cd_image_path: None,
+            disc_image_path: None,
             lyrics_path: None,
             cd_title_path: None,
             music_path: None,
+            preview_path: None,
+            instrument_tracks: Vec::new(),
+            music_length: None,
+            last_beat_hint: None,
+            last_second_hint: None,
             offset: None,
             sample_start: None,
             sample_length: None,
             selectable: None,
             bpms: Vec::new(),
             display_bpm: None,
+            bpm_lock: None,
             stops: Vec::new(),
+            delays: Vec::new(),
+            scrolls: Vec::new(),
+            time_signatures: Vec::new(),
+            tick_counts: Vec::new(),
+            speeds: Vec::new(),
+            warps: Vec::new(),
+            fakes: Vec::new(),
+            combos: Vec::new(),
+            labels: Vec::new(),
             bg_changes: Vec::new(),
             fg_changes: Vec::new(),
+            keysounds: Vec::new(),
             charts: Vec::new(),
         }
     }
 }
 
+impl Simfile {
+    /// Returns the BPM range (min, max) that is displayed in-game for the song.
+    ///
+    /// When `display_bpm` is not set the range is computed from
+    /// the BPM changes, ignoring zero and negative BPMs which are only used for effects.
+    /// Returns `None` if the displayed BPM is random or the song has no BPMs.
+    pub fn display_bpm_range(&self) -> Option<(f32, f32)> {
+        match self.display_bpm {
+            Some(DisplayBPM::Single(bpm)) => Some((bpm, bpm)),
+            Some(DisplayBPM::Range(min, max)) => Some((min, max)),
+            Some(DisplayBPM::Random) => None,
+            None => self.actual_bpm_range(),
+        }
+    }
+
+    /// Returns the actual BPM range (min, max) of the song, computed from the BPM changes.
+    pub fn actual_bpm_range(&self) -> Option<(f32, f32)> {
+        self.bpms
+            .iter()
+            .map(|b| b.bpm)
+            .filter(|bpm| *bpm > 0.0)
+            .fold(None, |range, bpm| match range {
+                Some((min, max)) => Some((f32::min(min, bpm), f32::max(max, bpm))),
+                None => Some((bpm, bpm)),
+            })
+    }
+
+    /// Replaces the song timing with the given timing, which is the opposite of
+    /// `ChartTiming::from_simfile`.
+    pub fn set_song_timing(&mut self, timing: ChartTiming) {
+        self.offset = timing.offset;
+        self.bpms = timing.bpms;
+        self.stops = timing.stops;
+        self.delays = timing.delays;
+        self.scrolls = timing.scrolls;
+        self.time_signatures = timing.time_signatures;
+        self.tick_counts = timing.tick_counts;
+        self.speeds = timing.speeds;
+        self.warps = timing.warps;
+        self.fakes = timing.fakes;
+        self.combos = timing.combos;
+        self.labels = timing.labels;
+    }
+}
+
+impl Default for Simfile {
+    fn default() -> Simfile {
+        Simfile::new()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::Simfile;
 
     #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn sm_simfile_new_initializes_empty() {
         let new_simfile = Simfile::new();
 
+        assert_eq!(new_simfile.version.is_none(), true);
         assert_eq!(new_simfile.title.is_none(), true);
         assert_eq!(new_simfile.subtitle.is_none(), true);
         assert_eq!(new_simfile.artist.is_none(), true);
@@ -216,23 +577,42 @@ mod tests {
         assert_eq!(new_simfile.subtitle_translit.is_none(), true);
         assert_eq!(new_simfile.artist_translit.is_none(), true);
         assert_eq!(new_simfile.genre.is_none(), true);
+        assert_eq!(new_simfile.origin.is_none(), true);
         assert_eq!(new_simfile.credit.is_none(), true);
         assert_eq!(new_simfile.banner_path.is_none(), true);
         assert_eq!(new_simfile.background_path.is_none(), true);
         assert_eq!(new_simfile.preview_video_path.is_none(), true);
         assert_eq!(new_simfile.jacket_path.is_none(), true);
+        assert_eq!(new_simfile.cd_image_path.is_none(), true);
+        assert_eq!(new_simfile.disc_image_path.is_none(), true);
         assert_eq!(new_simfile.lyrics_path.is_none(), true);
         assert_eq!(new_simfile.cd_title_path.is_none(), true);
         assert_eq!(new_simfile.music_path.is_none(), true);
+        assert_eq!(new_simfile.preview_path.is_none(), true);
+        assert_eq!(new_simfile.instrument_tracks.len(), 0);
+        assert_eq!(new_simfile.music_length.is_none(), true);
+        assert_eq!(new_simfile.last_beat_hint.is_none(), true);
+        assert_eq!(new_simfile.last_second_hint.is_none(), true);
         assert_eq!(new_simfile.offset.is_none(), true);
         assert_eq!(new_simfile.sample_start.is_none(), true);
         assert_eq!(new_simfile.sample_length.is_none(), true);
         assert_eq!(new_simfile.selectable.is_none(), true);
         assert_eq!(new_simfile.bpms.len(), 0);
         assert_eq!(new_simfile.display_bpm.is_none(), true);
+        assert_eq!(new_simfile.bpm_lock.is_none(), true);
         assert_eq!(new_simfile.stops.len(), 0);
+        assert_eq!(new_simfile.delays.len(), 0);
+        assert_eq!(new_simfile.scrolls.len(), 0);
+        assert_eq!(new_simfile.time_signatures.len(), 0);
+        assert_eq!(new_simfile.tick_counts.len(), 0);
+        assert_eq!(new_simfile.speeds.len(), 0);
+        assert_eq!(new_simfile.warps.len(), 0);
+        assert_eq!(new_simfile.fakes.len(), 0);
+        assert_eq!(new_simfile.combos.len(), 0);
+        assert_eq!(new_simfile.labels.len(), 0);
         assert_eq!(new_simfile.bg_changes.len(), 0);
         assert_eq!(new_simfile.fg_changes.len(), 0);
+        assert_eq!(new_simfile.keysounds.len(), 0);
         assert_eq!(new_simfile.charts.len(), 0);
     }
 }
@@ -0,0 +1,177 @@
+use super::{Chart, NoteType};
+
+/// The amount of rows in a beat, this is the smallest distance between two notes that can be
+/// represented in a measure.
+pub const ROWS_PER_BEAT: u32 = 48;
+/// The amount of beats in a measure, Stepmania always uses 4 beats per measure in the note data.
+pub const BEATS_PER_MEASURE: u32 = 4;
+/// The amount of rows in a measure.
+pub const ROWS_PER_MEASURE: u32 = ROWS_PER_BEAT * BEATS_PER_MEASURE;
+
+/// A single note in a chart, positioned by row instead of by measure.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimedNote {
+    /// The row the note is placed on, there are `ROWS_PER_BEAT` rows in a beat.
+    pub row: u32,
+    /// The lane of the note, starting from 0
+    pub column: usize,
+    /// The type of the note
+    pub note_type: NoteType,
+}
+
+impl TimedNote {
+    /// Returns the beat the note is placed on.
+    pub fn beat(&self) -> f32 {
+        self.row as f32 / ROWS_PER_BEAT as f32
+    }
+}
+
+/// The side of an analog laser in a K-Shoot Mania chart.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LaserSide {
+    Left,
+    Right,
+}
+
+/// A point of an analog laser, the laser moves in a straight line from one point to the next.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LaserPoint {
+    /// The row the point is placed on, there are `ROWS_PER_BEAT` rows in a beat.
+    pub row: u32,
+    /// The position of the laser, from 0.0 (left) to 1.0 (right)
+    pub position: f32,
+}
+
+/// An analog laser, which lasts from its first point to its last point.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Laser {
+    /// The knob used to follow the laser
+    pub side: LaserSide,
+    /// The points of the laser, ordered by row
+    pub points: Vec<LaserPoint>,
+}
+
+/// Returns the amount of lanes used by a chart type (e.g. 4 for "dance-single"), or `None` if the
+/// chart type is unknown.
+pub fn columns_for_chart_type(chart_type: &str) -> Option<usize> {
+    let columns = match chart_type.trim().to_lowercase().as_ref() {
+        "dance-threepanel" => 3,
+        "dance-single" | "maniax-single" | "techno-single4" => 4,
+        "pump-single" | "ez2-single" | "para-single" | "techno-single5" | "pnm-five" => 5,
+        "dance-solo" | "pump-halfdouble" | "beat-single5" | "sdvx-single" => 6,
+        "kb7-single" | "ez2-real" => 7,
+        "dance-double" | "dance-couple" | "dance-routine" | "beat-single7" | "maniax-double"
+        | "techno-single8" | "techno-double4" | "ds3ddx-single" => 8,
+        "pnm-nine" => 9,
+        "pump-double" | "pump-couple" | "pump-routine" | "ez2-double" | "techno-double5" => 10,
+        "beat-double5" => 12,
+        "beat-double7" | "techno-double8" => 16,
+        _ => return None,
+    };
+
+    Some(columns)
+}
+
+/// Returns the chart type usually used for a chart with the given amount of lanes, for formats
+/// that only store the amount of keys. Returns `None` if there's no chart type for that amount.
+pub fn chart_type_for_columns(columns: usize) -> Option<&'static str> {
+    let chart_type = match columns {
+        3 => "dance-threepanel",
+        4 => "dance-single",
+        5 => "pump-single",
+        6 => "dance-solo",
+        7 => "kb7-single",
+        8 => "dance-double",
+        9 => "pnm-nine",
+        10 => "pump-double",
+        12 => "beat-double5",
+        16 => "beat-double7",
+        _ => return None,
+    };
+
+    Some(chart_type)
+}
+
+impl Chart {
+    /// Returns the amount of lanes in the chart, or `None` if the chart type is unknown.
+    pub fn columns(&self) -> Option<usize> {
+        columns_for_chart_type(&self.chart_type)
+    }
+
+    /// Returns every note in the chart that isn't `NoteType::None`, ordered by row and column.
+    ///
+    /// Returns an empty list if the amount of lanes can't be determined from the chart type.
+    pub fn notes(&self) -> Vec<TimedNote> {
+        let columns = match self.columns() {
+            Some(c) => c,
+            None => return Vec::new(),
+        };
+
+        let mut notes = vec![];
+        for (measure_index, measure) in self.note_data.iter().enumerate() {
+            // A partial line at the end of a measure that was cut short is left out
+            let lines = measure.len() / columns;
+            if lines == 0 {
+                continue;
+            }
+
+            for (i, note_type) in measure[..lines * columns].iter().enumerate() {
+                if *note_type == NoteType::None {
+                    continue;
+                }
+
+                let line = (i / columns) as u32;
+                let row_in_measure = (line * ROWS_PER_MEASURE + lines as u32 / 2) / lines as u32;
+                notes.push(TimedNote {
+                    row: measure_index as u32 * ROWS_PER_MEASURE + row_in_measure,
+                    column: i % columns,
+                    note_type: note_type.clone(),
+                });
+            }
+        }
+
+        notes
+    }
+}
+
+/// The amount of lines a measure can be split into, from least to most precise.
+const MEASURE_LINES: [u32; 10] = [4, 8, 12, 16, 24, 32, 48, 64, 96, 192];
+
+/// Builds note data from a list of notes, using as few lines as possible for every measure.
+///
+/// Notes outside of the `columns` lanes are ignored. If two notes are placed on the same row and
+/// column the last one is kept.
+pub fn note_data_from_notes(columns: usize, notes: &[TimedNote]) -> Vec<Vec<NoteType>> {
+    let measure_count = notes
+        .iter()
+        .map(|n| n.row / ROWS_PER_MEASURE + 1)
+        .max()
+        .unwrap_or(1);
+
+    let mut note_data = vec![];
+    for measure in 0..measure_count {
+        let measure_notes: Vec<&TimedNote> = notes
+            .iter()
+            .filter(|n| n.row / ROWS_PER_MEASURE == measure && n.column < columns)
+            .collect();
+
+        let lines = MEASURE_LINES
+            .iter()
+            .cloned()
+            .find(|lines| {
+                measure_notes
+                    .iter()
+                    .all(|n| n.row % (ROWS_PER_MEASURE / lines) == 0)
+            })
+            .unwrap_or(ROWS_PER_MEASURE);
+
+        let mut data = vec![NoteType::None; lines as usize * columns];
+        for note in measure_notes {
+            let line = (note.row % ROWS_PER_MEASURE) / (ROWS_PER_MEASURE / lines);
+            data[line as usize * columns + note.column] = note.note_type.clone();
+        }
+        note_data.push(data);
+    }
+
+    note_data
+}
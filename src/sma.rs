@@ -0,0 +1,181 @@
+use crate::simfile::{
+    ChartTiming, Delay, Simfile, Speed, SpeedUnit, Stop, TickCount, TimeSignature, BPM,
+    ROWS_PER_BEAT,
+};
+use crate::{parse_section, read_sections, SimfileParseError};
+use std::io::BufRead;
+use std::ops::RangeInclusive;
+
+/// Parses a StepMania AMX simfile (.sma).
+///
+/// These are Stepmania simfiles with a few more timing tags: `#BEATSPERMEASURE` sets the time
+/// signatures, `#TICKCOUNT` the tick counts and `#SPEED` the speed changes. The beats in the
+/// timing tags can also be given in rows by ending them with "r", using the rows per beat set by
+/// `#ROWSPERBEAT` (48 by default).
+///
+/// Timing tags that come after a `#NOTES` section belong to that chart, which keeps the song
+/// timing for the tags it doesn't set.
+pub fn parse_sma<R: BufRead>(reader: &mut R) -> Result<Simfile, SimfileParseError> {
+    let mut sim = Simfile::new();
+    let mut rows_per_beat = ROWS_PER_BEAT as f32;
+
+    for (key, value) in read_sections(reader)? {
+        match key.as_ref() {
+            "ROWSPERBEAT" => {
+                if let Some(rows) = value.and_then(|v| v.parse::<u32>().ok()) {
+                    if rows > 0 {
+                        rows_per_beat = rows as f32;
+                    }
+                }
+            }
+            "OFFSET" | "BPMS" | "STOPS" | "DELAYS" | "BEATSPERMEASURE" | "TICKCOUNT" | "SPEED" => {
+                let mut timing = sim
+                    .charts
+                    .last()
+                    .and_then(|chart| chart.timing.clone())
+                    .unwrap_or_else(|| ChartTiming::from_simfile(&sim));
+                parse_timing_section(&mut timing, &key, value, rows_per_beat)?;
+                match sim.charts.last_mut() {
+                    Some(chart) => chart.timing = Some(timing),
+                    None => sim.set_song_timing(timing),
+                }
+            }
+            _ => parse_section(&mut sim, &key, value)?,
+        }
+    }
+
+    Ok(sim)
+}
+
+fn parse_timing_section(
+    timing: &mut ChartTiming,
+    key: &str,
+    value: Option<String>,
+    rows_per_beat: f32,
+) -> Result<(), SimfileParseError> {
+    let value = value.unwrap_or_default();
+
+    match key {
+        "OFFSET" => timing.offset = value.parse().ok(),
+        "BPMS" => {
+            let error = SimfileParseError::FailedToParseBPMs;
+            timing.bpms = parse_timing_list(&value, rows_per_beat, 1..=1, error)?
+                .into_iter()
+                .map(|(beat, values)| {
+                    Ok(BPM {
+                        beat,
+                        bpm: parse_number(values[0], error)?,
+                    })
+                })
+                .collect::<Result<_, _>>()?;
+        }
+        "STOPS" => {
+            let error = SimfileParseError::FailedToParseStops;
+            timing.stops = parse_timing_list(&value, rows_per_beat, 1..=1, error)?
+                .into_iter()
+                .map(|(beat, values)| {
+                    Ok(Stop {
+                        beat,
+                        time: parse_number(values[0], error)?,
+                    })
+                })
+                .collect::<Result<_, _>>()?;
+        }
+        "DELAYS" => {
+            let error = SimfileParseError::FailedToParseDelays;
+            timing.delays = parse_timing_list(&value, rows_per_beat, 1..=1, error)?
+                .into_iter()
+                .map(|(beat, values)| {
+                    Ok(Delay {
+                        beat,
+                        time: parse_number(values[0], error)?,
+                    })
+                })
+                .collect::<Result<_, _>>()?;
+        }
+        // Stepmania AMX only sets the amount of beats in a measure, a beat is always a quarter note
+        "BEATSPERMEASURE" => {
+            let error = SimfileParseError::FailedToParseTimeSignatures;
+            timing.time_signatures = parse_timing_list(&value, rows_per_beat, 1..=1, error)?
+                .into_iter()
+                .map(|(beat, values)| {
+                    Ok(TimeSignature {
+                        beat,
+                        numerator: parse_number(values[0], error)? as u32,
+                        denominator: 4,
+                    })
+                })
+                .collect::<Result<_, _>>()?;
+        }
+        "TICKCOUNT" => {
+            let error = SimfileParseError::FailedToParseTickCounts;
+            timing.tick_counts = parse_timing_list(&value, rows_per_beat, 1..=1, error)?
+                .into_iter()
+                .map(|(beat, values)| {
+                    Ok(TickCount {
+                        beat,
+                        ticks: parse_number(values[0], error)? as u32,
+                    })
+                })
+                .collect::<Result<_, _>>()?;
+        }
+        // A speed is "beat=ratio=duration", where the duration is in seconds if it ends with "s"
+        "SPEED" => {
+            let error = SimfileParseError::FailedToParseSpeeds;
+            timing.speeds = parse_timing_list(&value, rows_per_beat, 1..=2, error)?
+                .into_iter()
+                .map(|(beat, values)| {
+                    let duration = values.get(1).cloned().unwrap_or("0");
+                    let (duration, unit) = match duration.strip_suffix(&['s', 'S'][..]) {
+                        Some(seconds) => (seconds, SpeedUnit::Seconds),
+                        None => (duration, SpeedUnit::Beats),
+                    };
+                    Ok(Speed {
+                        beat,
+                        ratio: parse_number(values[0], error)?,
+                        duration: parse_number(duration, error)?,
+                        unit,
+                    })
+                })
+                .collect::<Result<_, _>>()?;
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Splits a list of timing values (`beat=value,beat=value`) into the beat and the other values of
+/// every entry, which needs to have an amount of values in `count`. A beat ending with "r" is a
+/// row instead.
+fn parse_timing_list(
+    value: &str,
+    rows_per_beat: f32,
+    count: RangeInclusive<usize>,
+    error: SimfileParseError,
+) -> Result<Vec<(f32, Vec<&str>)>, SimfileParseError> {
+    let mut list = vec![];
+
+    for entry in value.split(',').map(|e| e.trim()).filter(|e| !e.is_empty()) {
+        let mut values: Vec<&str> = entry.split('=').map(|v| v.trim()).collect();
+        let beat = values.remove(0);
+        if !count.contains(&values.len()) {
+            return Err(error);
+        }
+
+        let beat = match beat.strip_suffix(&['r', 'R'][..]) {
+            Some(row) => parse_number(row, error)? / rows_per_beat,
+            None => parse_number(beat, error)?,
+        };
+        list.push((beat, values));
+    }
+
+    Ok(list)
+}
+
+fn parse_number(value: &str, error: SimfileParseError) -> Result<f32, SimfileParseError> {
+    match value.trim().parse() {
+        Ok(number) => Ok(number),
+        Err(_) => Err(error),
+    }
+}
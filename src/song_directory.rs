@@ -0,0 +1,191 @@
+use crate::cache::SongCache;
+use crate::format::{load_simfile, SimfileFormat};
+use crate::simfile::Simfile;
+use crate::SimfileParseError;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The formats a simfile is loaded from when a song folder has several, from the most preferred
+/// to the least, like Stepmania does.
+const FORMAT_PRIORITY: [SimfileFormat; 9] = [
+    SimfileFormat::Ssc,
+    SimfileFormat::Sma,
+    SimfileFormat::Sm,
+    SimfileFormat::Dwi,
+    SimfileFormat::Bms,
+    SimfileFormat::Ucs,
+    SimfileFormat::Ksh,
+    SimfileFormat::Qua,
+    SimfileFormat::Osu,
+];
+
+const IMAGE_EXTENSIONS: [&str; 5] = ["png", "jpg", "jpeg", "gif", "bmp"];
+const AUDIO_EXTENSIONS: [&str; 6] = ["ogg", "oga", "mp3", "wav", "opus", "flac"];
+
+/// A song folder, with the simfile loaded from it and the paths of its assets.
+///
+/// Every asset is the file set in the simfile when it exists, matched ignoring case like
+/// Stepmania does on case-sensitive file systems. When it's missing or doesn't exist, it's guessed
+/// from the names of the files in the folder, e.g. an image ending in "bn" is the banner.
+#[derive(Debug, Clone)]
+pub struct SongDirectory {
+    /// The song folder
+    pub path: PathBuf,
+    /// The file the simfile was loaded from
+    pub simfile_path: PathBuf,
+    pub simfile: Simfile,
+    pub music: Option<PathBuf>,
+    pub banner: Option<PathBuf>,
+    pub background: Option<PathBuf>,
+    pub cd_title: Option<PathBuf>,
+    pub jacket: Option<PathBuf>,
+}
+
+impl SongDirectory {
+    /// Loads the simfile of a song folder and finds its assets.
+    ///
+    /// Returns `NoSimfileInDirectory` if there's no simfile in the folder.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<SongDirectory, SimfileParseError> {
+        SongDirectory::load_with(path.as_ref(), None)
+    }
+
+    /// Loads a song folder like `load`, but takes the simfile from a cache when it didn't change
+    /// since it was cached.
+    pub fn load_cached<P: AsRef<Path>>(
+        path: P,
+        cache: &SongCache,
+    ) -> Result<SongDirectory, SimfileParseError> {
+        SongDirectory::load_with(path.as_ref(), Some(cache))
+    }
+
+    pub(crate) fn load_with(
+        path: &Path,
+        cache: Option<&SongCache>,
+    ) -> Result<SongDirectory, SimfileParseError> {
+        let files = list_files(path)?;
+        let simfile_path = match find_simfile_in(&files) {
+            Some(simfile_path) => simfile_path.clone(),
+            None => return Err(SimfileParseError::NoSimfileInDirectory),
+        };
+        let simfile = match cache {
+            Some(cache) => cache.load_simfile(&simfile_path)?,
+            None => load_simfile(&simfile_path)?,
+        };
+
+        let asset = |set: &Option<String>, extensions: &[&str], names: &[&str]| {
+            set.as_ref()
+                .and_then(|set| resolve_path(path, set))
+                .or_else(|| guess_file(&files, extensions, names))
+        };
+        let music = asset(&simfile.music_path, &AUDIO_EXTENSIONS, &[]);
+        let banner = asset(&simfile.banner_path, &IMAGE_EXTENSIONS, &["bn", "banner"]);
+        let background = asset(
+            &simfile.background_path,
+            &IMAGE_EXTENSIONS,
+            &["bg", "background"],
+        );
+        let cd_title = asset(&simfile.cd_title_path, &IMAGE_EXTENSIONS, &["cdtitle"]);
+        let jacket = asset(&simfile.jacket_path, &IMAGE_EXTENSIONS, &["jk", "jacket"]);
+
+        Ok(SongDirectory {
+            path: path.to_path_buf(),
+            simfile_path,
+            simfile,
+            music,
+            banner,
+            background,
+            cd_title,
+            jacket,
+        })
+    }
+}
+
+/// Returns the simfile of a song folder, picking the most preferred format when there are
+/// several. Returns `None` if there's no simfile or the folder can't be read.
+pub fn find_simfile<P: AsRef<Path>>(path: P) -> Option<PathBuf> {
+    list_files(path.as_ref())
+        .ok()
+        .and_then(|files| find_simfile_in(&files).cloned())
+}
+
+fn find_simfile_in(files: &[PathBuf]) -> Option<&PathBuf> {
+    let format_of = |file: &PathBuf| {
+        file.extension()
+            .and_then(|e| e.to_str())
+            .and_then(SimfileFormat::from_extension)
+    };
+
+    FORMAT_PRIORITY
+        .iter()
+        .find_map(|format| files.iter().find(|f| format_of(f) == Some(*format)))
+}
+
+/// Returns the files in a folder, sorted by name.
+fn list_files(path: &Path) -> Result<Vec<PathBuf>, SimfileParseError> {
+    let entries = match fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(_) => return Err(SimfileParseError::BufReadError),
+    };
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_file())
+        .collect();
+    files.sort();
+
+    Ok(files)
+}
+
+/// Returns the existing file at `relative` from `dir`, matching every part of the path ignoring
+/// case when it doesn't exist as written. "\" is treated as a path separator too.
+pub fn resolve_path<P: AsRef<Path>>(dir: P, relative: &str) -> Option<PathBuf> {
+    let relative = relative.trim().replace('\\', "/");
+    if relative.is_empty() {
+        return None;
+    }
+
+    let mut path = dir.as_ref().to_path_buf();
+    for part in relative.split('/').filter(|p| !p.is_empty() && *p != ".") {
+        let exact = path.join(part);
+        if part == ".." || exact.exists() {
+            path = exact;
+            continue;
+        }
+
+        path = fs::read_dir(&path)
+            .ok()?
+            .filter_map(|e| e.ok())
+            .find(|e| e.file_name().to_string_lossy().to_lowercase() == part.to_lowercase())?
+            .path();
+    }
+
+    if path.is_file() {
+        Some(path)
+    } else {
+        None
+    }
+}
+
+/// Guesses an asset from the files of a song folder: the first file with one of the
+/// `extensions` whose name starts or ends with one of the `names`, ignoring case. Without any
+/// `names` it's the first file with one of the `extensions`.
+fn guess_file(files: &[PathBuf], extensions: &[&str], names: &[&str]) -> Option<PathBuf> {
+    files
+        .iter()
+        .filter(|f| {
+            f.extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(|e| extensions.contains(&e.to_lowercase().as_ref()))
+        })
+        .find(|f| {
+            let stem = f
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+            names.is_empty()
+                || names
+                    .iter()
+                    .any(|name| stem.starts_with(name) || stem.ends_with(name))
+        })
+        .cloned()
+}
@@ -0,0 +1,484 @@
+use crate::simfile::{
+    columns_for_chart_type, BgChange, Chart, ChartTiming, DisplayBPM, NoteType, RadarValues,
+    Simfile, SpeedUnit,
+};
+use crate::{
+    parse_chart_data, parse_chart_difficulty, parse_radar_values, parse_section, read_sections,
+    SimfileParseError,
+};
+use std::io::{self, BufRead, Write};
+
+/// The version of the .ssc format that is written.
+const SSC_VERSION: &str = "0.83";
+
+/// The tags of a chart that set its timing instead of the song timing.
+const CHART_TIMING_TAGS: [&str; 12] = [
+    "OFFSET",
+    "BPMS",
+    "STOPS",
+    "DELAYS",
+    "WARPS",
+    "SCROLLS",
+    "TIMESIGNATURES",
+    "TICKCOUNTS",
+    "COMBOS",
+    "SPEEDS",
+    "FAKES",
+    "LABELS",
+];
+
+/// The tags of a chart, collected until the chart ends.
+#[derive(Default)]
+struct ChartTags {
+    steps_type: Option<String>,
+    description: Option<String>,
+    difficulty: Option<String>,
+    meter: Option<String>,
+    radar_values: Option<String>,
+    credit: Option<String>,
+    notes: Option<String>,
+    timing: Option<ChartTiming>,
+}
+
+/// Parses a Stepmania 5 simfile (.ssc).
+///
+/// The song tags are the same as in a .sm file, but every chart starts with a `#NOTEDATA` tag and
+/// is made of its own tags instead of a single `#NOTES` section. Timing tags in a chart give it
+/// its own timing, which keeps the song timing for the tags the chart doesn't set.
+pub fn parse_ssc<R: BufRead>(reader: &mut R) -> Result<Simfile, SimfileParseError> {
+    let mut sim = Simfile::new();
+    let mut chart: Option<ChartTags> = None;
+
+    for (key, value) in read_sections(reader)? {
+        if key == "NOTEDATA" {
+            if let Some(tags) = chart.take() {
+                sim.charts.push(build_chart(tags)?);
+            }
+            chart = Some(ChartTags::default());
+            continue;
+        }
+
+        let tags = match chart.as_mut() {
+            Some(tags) => tags,
+            None => {
+                parse_section(&mut sim, &key, value)?;
+                continue;
+            }
+        };
+        match key.as_ref() {
+            "STEPSTYPE" => tags.steps_type = value,
+            "DESCRIPTION" => tags.description = value,
+            "DIFFICULTY" => tags.difficulty = value,
+            "METER" => tags.meter = value,
+            "RADARVALUES" => tags.radar_values = value,
+            "CREDIT" => tags.credit = value,
+            "NOTES" | "NOTES2" => tags.notes = value,
+            key if CHART_TIMING_TAGS.contains(&key) => {
+                // The song timing parser is used on a simfile that only holds the chart timing
+                let mut timing = Simfile::new();
+                timing.set_song_timing(
+                    tags.timing
+                        .take()
+                        .unwrap_or_else(|| ChartTiming::from_simfile(&sim)),
+                );
+                parse_section(&mut timing, key, value)?;
+                tags.timing = Some(ChartTiming::from_simfile(&timing));
+            }
+            _ => {}
+        }
+    }
+    if let Some(tags) = chart {
+        sim.charts.push(build_chart(tags)?);
+    }
+
+    Ok(sim)
+}
+
+fn build_chart(tags: ChartTags) -> Result<Chart, SimfileParseError> {
+    let chart_type = match tags.steps_type {
+        Some(steps_type) => steps_type,
+        None => return Err(SimfileParseError::InvalidChartFormat),
+    };
+    let description = tags.description.unwrap_or_default();
+    let difficulty_name = tags.difficulty.unwrap_or_default();
+
+    // Charts without a meter are given a meter of 1, like in .sm files
+    let meter = match tags.meter {
+        Some(meter) => match meter.parse() {
+            Ok(meter) => meter,
+            Err(_) => return Err(SimfileParseError::FailedToParseChartMeter),
+        },
+        None => 1,
+    };
+
+    Ok(Chart {
+        difficulty: parse_chart_difficulty(&difficulty_name, &description, meter),
+        author: Some(description).filter(|d| !d.is_empty()),
+        chart_type,
+        difficulty_name,
+        meter,
+        radar_values: parse_radar_values(&tags.radar_values.unwrap_or_default())?,
+        note_data: match tags.notes {
+            Some(notes) => parse_chart_data(&notes)?,
+            None => Vec::new(),
+        },
+        lasers: Vec::new(),
+        timing: tags.timing,
+        credit: tags.credit,
+    })
+}
+
+/// Writes a simfile as a Stepmania 5 simfile (.ssc), with all of its charts.
+///
+/// The version of the simfile is kept when it has one.
+/// Charts that have their own timing only write it when it differs from the song timing.
+/// Returns an `InvalidInput` error if a chart has an unknown chart type, since the amount of
+/// columns is needed to write the note data.
+pub fn write_ssc<W: Write>(simfile: &Simfile, writer: &mut W) -> io::Result<()> {
+    let text = |value: &Option<String>| value.clone().unwrap_or_default();
+    let number = |value: Option<f32>| value.map(|v| format!("{:.6}", v)).unwrap_or_default();
+
+    match simfile.version {
+        Some(version) => writeln!(writer, "#VERSION:{};", version)?,
+        None => writeln!(writer, "#VERSION:{};", SSC_VERSION)?,
+    }
+    writeln!(writer, "#TITLE:{};", text(&simfile.title))?;
+    writeln!(writer, "#SUBTITLE:{};", text(&simfile.subtitle))?;
+    writeln!(writer, "#ARTIST:{};", text(&simfile.artist))?;
+    writeln!(writer, "#TITLETRANSLIT:{};", text(&simfile.title_translit))?;
+    writeln!(
+        writer,
+        "#SUBTITLETRANSLIT:{};",
+        text(&simfile.subtitle_translit)
+    )?;
+    writeln!(
+        writer,
+        "#ARTISTTRANSLIT:{};",
+        text(&simfile.artist_translit)
+    )?;
+    writeln!(writer, "#GENRE:{};", text(&simfile.genre))?;
+    writeln!(writer, "#ORIGIN:{};", text(&simfile.origin))?;
+    writeln!(writer, "#CREDIT:{};", text(&simfile.credit))?;
+    writeln!(writer, "#BANNER:{};", text(&simfile.banner_path))?;
+    writeln!(writer, "#BACKGROUND:{};", text(&simfile.background_path))?;
+    writeln!(writer, "#PREVIEWVID:{};", text(&simfile.preview_video_path))?;
+    writeln!(writer, "#JACKET:{};", text(&simfile.jacket_path))?;
+    writeln!(writer, "#CDIMAGE:{};", text(&simfile.cd_image_path))?;
+    writeln!(writer, "#DISCIMAGE:{};", text(&simfile.disc_image_path))?;
+    writeln!(writer, "#LYRICSPATH:{};", text(&simfile.lyrics_path))?;
+    writeln!(writer, "#CDTITLE:{};", text(&simfile.cd_title_path))?;
+    writeln!(writer, "#MUSIC:{};", text(&simfile.music_path))?;
+    if simfile.preview_path.is_some() {
+        writeln!(writer, "#PREVIEW:{};", text(&simfile.preview_path))?;
+    }
+    let tracks: Vec<String> = simfile
+        .instrument_tracks
+        .iter()
+        .map(|t| format!("{}={}", t.instrument, t.path))
+        .collect();
+    writeln!(writer, "#INSTRUMENTTRACK:{};", tracks.join(","))?;
+    if simfile.music_length.is_some() {
+        writeln!(writer, "#MUSICLENGTH:{};", number(simfile.music_length))?;
+    }
+    writeln!(writer, "#SAMPLESTART:{};", number(simfile.sample_start))?;
+    writeln!(writer, "#SAMPLELENGTH:{};", number(simfile.sample_length))?;
+    match &simfile.selectable {
+        Some(selectable) => writeln!(writer, "#SELECTABLE:{};", selectable)?,
+        None => writeln!(writer, "#SELECTABLE:YES;")?,
+    }
+    match simfile.display_bpm {
+        Some(DisplayBPM::Single(bpm)) => writeln!(writer, "#DISPLAYBPM:{:.6};", bpm)?,
+        Some(DisplayBPM::Range(min, max)) => {
+            writeln!(writer, "#DISPLAYBPM:{:.6}:{:.6};", min, max)?
+        }
+        Some(DisplayBPM::Random) => writeln!(writer, "#DISPLAYBPM:*;")?,
+        None => {}
+    }
+    if let Some(bpm_lock) = simfile.bpm_lock {
+        writeln!(writer, "#BPMLOCK:{};", if bpm_lock { "YES" } else { "NO" })?;
+    }
+
+    let song_timing = ChartTiming::from_simfile(simfile);
+    write_timing(writer, &song_timing)?;
+
+    if simfile.last_beat_hint.is_some() {
+        writeln!(writer, "#LASTBEATHINT:{};", number(simfile.last_beat_hint))?;
+    }
+    if simfile.last_second_hint.is_some() {
+        writeln!(
+            writer,
+            "#LASTSECONDHINT:{};",
+            number(simfile.last_second_hint)
+        )?;
+    }
+    let bg_changes: Vec<String> = simfile.bg_changes.iter().map(bg_change).collect();
+    writeln!(writer, "#BGCHANGES:{};", bg_changes.join(","))?;
+    let fg_changes: Vec<String> = simfile
+        .fg_changes
+        .iter()
+        .map(|c| format!("{:.6}={}", c.start_beat, c.path))
+        .collect();
+    writeln!(writer, "#FGCHANGES:{};", fg_changes.join(","))?;
+    writeln!(writer, "#KEYSOUNDS:{};", simfile.keysounds.join(","))?;
+
+    for chart in simfile.charts.iter() {
+        writeln!(writer)?;
+        write_chart(writer, chart, &song_timing)?;
+    }
+
+    Ok(())
+}
+
+fn write_chart<W: Write>(
+    writer: &mut W,
+    chart: &Chart,
+    song_timing: &ChartTiming,
+) -> io::Result<()> {
+    let columns = match columns_for_chart_type(&chart.chart_type) {
+        Some(columns) => columns,
+        None => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unknown chart type: {}", chart.chart_type),
+            ))
+        }
+    };
+    let author = chart.author.clone().unwrap_or_default();
+    // The difficulty is written as it was parsed, unless the name doesn't match it anymore
+    let difficulty = match chart.difficulty_name.as_str() {
+        "" => chart.difficulty.to_string(),
+        name if parse_chart_difficulty(name, &author, chart.meter) == chart.difficulty => {
+            name.to_string()
+        }
+        _ => chart.difficulty.to_string(),
+    };
+
+    writeln!(
+        writer,
+        "//---------------{} - {}----------------",
+        chart.chart_type, author
+    )?;
+    writeln!(writer, "#NOTEDATA:;")?;
+    writeln!(writer, "#STEPSTYPE:{};", chart.chart_type)?;
+    writeln!(writer, "#DESCRIPTION:{};", author)?;
+    writeln!(writer, "#DIFFICULTY:{};", difficulty)?;
+    writeln!(writer, "#METER:{};", chart.meter)?;
+    let radar_values: Vec<String> = chart.radar_values.iter().map(radar_values).collect();
+    writeln!(writer, "#RADARVALUES:{};", radar_values.join(","))?;
+    writeln!(
+        writer,
+        "#CREDIT:{};",
+        chart.credit.clone().unwrap_or_default()
+    )?;
+    if let Some(timing) = chart.timing.as_ref().filter(|t| *t != song_timing) {
+        write_timing(writer, timing)?;
+    }
+
+    writeln!(writer, "#NOTES:")?;
+    for (i, measure) in chart.note_data.iter().enumerate() {
+        if i > 0 {
+            writeln!(writer, ",")?;
+        }
+        for line in measure.chunks(columns) {
+            let line: String = line.iter().map(note_char).collect();
+            writeln!(writer, "{}", line)?;
+        }
+    }
+    writeln!(writer, ";")?;
+
+    Ok(())
+}
+
+fn write_timing<W: Write>(writer: &mut W, timing: &ChartTiming) -> io::Result<()> {
+    let list = |values: Vec<String>| values.join(",");
+
+    writeln!(writer, "#OFFSET:{:.6};", timing.offset.unwrap_or(0.0))?;
+    writeln!(
+        writer,
+        "#BPMS:{};",
+        list(
+            timing
+                .bpms
+                .iter()
+                .map(|b| format!("{:.6}={:.6}", b.beat, b.bpm))
+                .collect()
+        )
+    )?;
+    writeln!(
+        writer,
+        "#STOPS:{};",
+        list(
+            timing
+                .stops
+                .iter()
+                .map(|s| format!("{:.6}={:.6}", s.beat, s.time))
+                .collect()
+        )
+    )?;
+    writeln!(
+        writer,
+        "#DELAYS:{};",
+        list(
+            timing
+                .delays
+                .iter()
+                .map(|d| format!("{:.6}={:.6}", d.beat, d.time))
+                .collect()
+        )
+    )?;
+    writeln!(
+        writer,
+        "#WARPS:{};",
+        list(
+            timing
+                .warps
+                .iter()
+                .map(|w| format!("{:.6}={:.6}", w.beat, w.length))
+                .collect()
+        )
+    )?;
+    writeln!(
+        writer,
+        "#TIMESIGNATURES:{};",
+        list(
+            timing
+                .time_signatures
+                .iter()
+                .map(|s| format!("{:.6}={}={}", s.beat, s.numerator, s.denominator))
+                .collect()
+        )
+    )?;
+    writeln!(
+        writer,
+        "#TICKCOUNTS:{};",
+        list(
+            timing
+                .tick_counts
+                .iter()
+                .map(|t| format!("{:.6}={}", t.beat, t.ticks))
+                .collect()
+        )
+    )?;
+    writeln!(
+        writer,
+        "#COMBOS:{};",
+        list(
+            timing
+                .combos
+                .iter()
+                .map(|c| format!("{:.6}={}={}", c.beat, c.hit_multiplier, c.miss_multiplier))
+                .collect()
+        )
+    )?;
+    writeln!(
+        writer,
+        "#SPEEDS:{};",
+        list(
+            timing
+                .speeds
+                .iter()
+                .map(|s| {
+                    let unit = match s.unit {
+                        SpeedUnit::Beats => 0,
+                        SpeedUnit::Seconds => 1,
+                    };
+                    format!("{:.6}={:.6}={:.6}={}", s.beat, s.ratio, s.duration, unit)
+                })
+                .collect()
+        )
+    )?;
+    writeln!(
+        writer,
+        "#SCROLLS:{};",
+        list(
+            timing
+                .scrolls
+                .iter()
+                .map(|s| format!("{:.6}={:.6}", s.beat, s.factor))
+                .collect()
+        )
+    )?;
+    writeln!(
+        writer,
+        "#FAKES:{};",
+        list(
+            timing
+                .fakes
+                .iter()
+                .map(|f| format!("{:.6}={:.6}", f.beat, f.length))
+                .collect()
+        )
+    )?;
+    writeln!(
+        writer,
+        "#LABELS:{};",
+        list(
+            timing
+                .labels
+                .iter()
+                .map(|l| format!("{:.6}={}", l.beat, l.label))
+                .collect()
+        )
+    )?;
+
+    Ok(())
+}
+
+fn bg_change(change: &BgChange) -> String {
+    let optional = |value: &Option<String>| value.clone().unwrap_or_default();
+    format!(
+        "{:.6}={}={:.6}={}={}={}={}={}={}={}={}",
+        change.start_beat,
+        change.file_name,
+        change.play_rate,
+        change.transition_type,
+        change.effect_flag,
+        change.second_effect_flag,
+        optional(&change.effect_file),
+        optional(&change.second_effect_file),
+        optional(&change.transition_file),
+        optional(&change.color_string),
+        optional(&change.second_color_string),
+    )
+}
+
+/// Formats the radar values of a player, leaving out the counts that aren't set.
+fn radar_values(values: &RadarValues) -> String {
+    let counts = [
+        values.notes,
+        values.taps,
+        values.jumps,
+        values.holds,
+        values.mines,
+        values.hands,
+        values.rolls,
+        values.lifts,
+        values.fakes,
+    ];
+    [
+        values.stream,
+        values.voltage,
+        values.air,
+        values.freeze,
+        values.chaos,
+    ]
+    .iter()
+    .chain(counts.iter().flatten())
+    .map(|v| format!("{:.6}", v))
+    .collect::<Vec<String>>()
+    .join(",")
+}
+
+pub(crate) fn note_char(note: &NoteType) -> char {
+    match note {
+        NoteType::None | NoteType::InvalidNote => '0',
+        NoteType::Normal => '1',
+        NoteType::HoldHead => '2',
+        NoteType::HoldOrRollTail => '3',
+        NoteType::RollHead => '4',
+        NoteType::Mine => 'M',
+        NoteType::AutomaticKeysound => 'K',
+        NoteType::LiftNote => 'L',
+        NoteType::FakeNote => 'F',
+    }
+}
@@ -0,0 +1,224 @@
+use crate::simfile::{Chart, Delay, Simfile, Stop, BEATS_PER_MEASURE, BPM, ROWS_PER_BEAT};
+
+/// The timing of a song, used to convert between beats and seconds.
+#[derive(Debug, Clone)]
+pub struct TimingData {
+    /// The offset in seconds, beat 0 happens at `-offset` seconds into the music
+    pub offset: f32,
+    /// The BPM changes, sorted by beat
+    pub bpms: Vec<BPM>,
+    /// The stops, sorted by beat
+    pub stops: Vec<Stop>,
+    /// The delays, sorted by beat
+    pub delays: Vec<Delay>,
+}
+
+impl TimingData {
+    /// Creates timing data from the song timing of a simfile.
+    pub fn from_simfile(simfile: &Simfile) -> TimingData {
+        TimingData::new(
+            simfile.offset.unwrap_or(0.0),
+            simfile.bpms.clone(),
+            simfile.stops.clone(),
+        )
+        .with_delays(simfile.delays.clone())
+    }
+
+    /// Creates timing data for a chart, which uses the timing of the chart when it has its own
+    /// timing and the song timing of the simfile otherwise.
+    pub fn from_chart(simfile: &Simfile, chart: &Chart) -> TimingData {
+        match &chart.timing {
+            Some(timing) => TimingData::new(
+                timing.offset.or(simfile.offset).unwrap_or(0.0),
+                timing.bpms.clone(),
+                timing.stops.clone(),
+            )
+            .with_delays(timing.delays.clone()),
+            None => TimingData::from_simfile(simfile),
+        }
+    }
+
+    pub fn new(offset: f32, mut bpms: Vec<BPM>, mut stops: Vec<Stop>) -> TimingData {
+        bpms.sort_by(|a, b| a.beat.total_cmp(&b.beat));
+        stops.sort_by(|a, b| a.beat.total_cmp(&b.beat));
+
+        TimingData {
+            offset,
+            bpms,
+            stops,
+            delays: Vec::new(),
+        }
+    }
+
+    /// Returns the timing data with the given delays.
+    pub fn with_delays(mut self, mut delays: Vec<Delay>) -> TimingData {
+        delays.sort_by(|a, b| a.beat.total_cmp(&b.beat));
+        self.delays = delays;
+        self
+    }
+
+    /// Creates timing data from BPM changes placed by time instead of by beat, as (seconds, BPM)
+    /// pairs, like the timing points of osu! and Quaver.
+    ///
+    /// Beat 0 is placed on the first BPM change, moved back by whole measures until it's before
+    /// `earliest` (usually the first note), since notes can't be placed before beat 0. The other
+    /// BPM changes are snapped with `quantized_beat_at_seconds`. Returns `None` if there are no
+    /// BPM changes.
+    pub fn from_timed_bpms(timed_bpms: &[(f32, f32)], earliest: f32) -> Option<TimingData> {
+        let mut timed_bpms = timed_bpms.to_vec();
+        timed_bpms.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let (mut start, first_bpm) = *timed_bpms.first()?;
+        while start > earliest && first_bpm > 0.0 {
+            start -= BEATS_PER_MEASURE as f32 * 60.0 / first_bpm;
+        }
+
+        let first = BPM {
+            beat: 0.0,
+            bpm: first_bpm,
+        };
+        let mut timing = TimingData::new(-start, vec![first], vec![]);
+        for (seconds, bpm) in timed_bpms.into_iter().skip(1) {
+            let beat = timing.quantized_beat_at_seconds(seconds);
+            match timing.bpms.last_mut() {
+                Some(last) if last.beat == beat => last.bpm = bpm,
+                _ => timing.bpms.push(BPM { beat, bpm }),
+            }
+        }
+
+        Some(timing)
+    }
+
+    /// Returns the BPM changes as (seconds, BPM) pairs sorted by time, for formats that place
+    /// timing by time instead of by beat.
+    ///
+    /// Those formats have no stops or delays, so the end of every stop and delay is added as a
+    /// change to the BPM at that beat, which keeps the following beats in place.
+    pub fn timed_bpms(&self) -> Vec<(f32, f32)> {
+        let mut timed_bpms: Vec<(f32, f32)> = self
+            .bpms
+            .iter()
+            .map(|b| (self.seconds_at_beat(b.beat), b.bpm))
+            .chain(self.stops.iter().map(|s| {
+                let end = self.seconds_at_beat(s.beat) + s.time;
+                (end, self.bpm_at_beat(s.beat))
+            }))
+            .chain(self.delays.iter().map(|d| {
+                let end = self.seconds_at_beat(d.beat);
+                (end, self.bpm_at_beat(d.beat))
+            }))
+            .collect();
+        timed_bpms.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        timed_bpms
+    }
+
+    /// Returns the BPM at the given beat. Songs without any BPMs are treated as 60 BPM.
+    pub fn bpm_at_beat(&self, beat: f32) -> f32 {
+        self.bpms
+            .iter()
+            .take_while(|b| b.beat <= beat)
+            .last()
+            .or_else(|| self.bpms.first())
+            .map(|b| b.bpm)
+            .unwrap_or(60.0)
+    }
+
+    /// Returns the time in seconds, relative to the start of the music, where the given beat is
+    /// hit.
+    ///
+    /// Stops on the beat itself are not included, since notes on that beat are hit before the
+    /// stop happens, but delays on the beat are.
+    pub fn seconds_at_beat(&self, beat: f32) -> f32 {
+        let mut seconds = -self.offset;
+        let mut current_beat = 0.0;
+        let mut current_bpm = self.bpm_at_beat(0.0);
+
+        for bpm in self.bpms.iter().filter(|b| b.beat > 0.0) {
+            if bpm.beat >= beat {
+                break;
+            }
+            seconds += (bpm.beat - current_beat) * 60.0 / current_bpm;
+            current_beat = bpm.beat;
+            current_bpm = bpm.bpm;
+        }
+        seconds += (beat - current_beat) * 60.0 / current_bpm;
+
+        seconds
+            + self
+                .stops
+                .iter()
+                .filter(|s| s.beat < beat)
+                .map(|s| s.time)
+                .sum::<f32>()
+            + self
+                .delays
+                .iter()
+                .filter(|d| d.beat <= beat)
+                .map(|d| d.time)
+                .sum::<f32>()
+    }
+
+    /// Returns the beat that is hit at the given time in seconds, relative to the start of the
+    /// music. This is the inverse of `seconds_at_beat`, a time during a stop or a delay returns
+    /// the beat of the stop or delay.
+    pub fn beat_at_seconds(&self, seconds: f32) -> f32 {
+        let mut seconds_left = seconds + self.offset;
+        let mut current_beat = 0.0;
+        let mut current_bpm = self.bpm_at_beat(0.0);
+
+        // The sort is stable, so BPM changes stay before pauses on the same beat
+        let mut events: Vec<(f32, Option<f32>, Option<f32>)> = self
+            .bpms
+            .iter()
+            .filter(|b| b.beat > 0.0)
+            .map(|b| (b.beat, Some(b.bpm), None))
+            .chain(self.stops.iter().map(|s| (s.beat, None, Some(s.time))))
+            .chain(self.delays.iter().map(|d| (d.beat, None, Some(d.time))))
+            .collect();
+        events.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        for (beat, bpm, pause) in events {
+            let segment = (beat - current_beat) * 60.0 / current_bpm;
+            if segment >= seconds_left {
+                break;
+            }
+            seconds_left -= segment;
+            current_beat = beat;
+
+            if let Some(bpm) = bpm {
+                current_bpm = bpm;
+            }
+            if let Some(pause) = pause {
+                if seconds_left <= pause {
+                    return current_beat;
+                }
+                seconds_left -= pause;
+            }
+        }
+
+        current_beat + seconds_left * current_bpm / 60.0
+    }
+
+    /// Returns the beat that is hit at the given time, snapped to the simplest subdivision of a
+    /// beat within `QUANTIZATION_TOLERANCE` seconds, or to the nearest row if there's none.
+    ///
+    /// This is used to import formats that place notes by time instead of by beat, which are
+    /// rarely exactly on a row because the times are rounded to milliseconds.
+    pub fn quantized_beat_at_seconds(&self, seconds: f32) -> f32 {
+        let beat = self.beat_at_seconds(seconds);
+        let tolerance = QUANTIZATION_TOLERANCE * self.bpm_at_beat(beat).abs() / 60.0;
+
+        BEAT_SUBDIVISIONS
+            .iter()
+            .map(|d| (beat * *d as f32).round() / *d as f32)
+            .find(|b| (b - beat).abs() <= tolerance)
+            .unwrap_or_else(|| (beat * ROWS_PER_BEAT as f32).round() / ROWS_PER_BEAT as f32)
+    }
+}
+
+/// How far away from a subdivision of a beat a time can be to be snapped to it, in seconds.
+pub const QUANTIZATION_TOLERANCE: f32 = 0.002;
+
+/// The subdivisions of a beat that a time can be snapped to, from simplest to most precise.
+const BEAT_SUBDIVISIONS: [u32; 10] = [1, 2, 3, 4, 6, 8, 12, 16, 24, 48];
@@ -0,0 +1,302 @@
+use crate::simfile::{
+    note_data_from_notes, Chart, ChartDifficulty, Delay, NoteType, Simfile, TimeSignature,
+    TimedNote, BPM, ROWS_PER_BEAT, ROWS_PER_MEASURE,
+};
+use crate::timing::TimingData;
+use crate::SimfileParseError;
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, Write};
+
+/// The amount of lines in a beat that a block can use, from least to most precise.
+const SPLITS: [u32; 10] = [1, 2, 3, 4, 6, 8, 12, 16, 24, 48];
+
+/// Parses a Pump It Up step file (.ucs) into a simfile with a single "pump-single" or
+/// "pump-double" chart.
+///
+/// Every block sets its BPM, delay, beats per measure and lines per beat (`:Split=`) before its
+/// lines. The delay of the first block is the offset of the song, the delays of the following
+/// blocks become delays on the first beat of the block. A hold is an `M` followed by `H` lines
+/// and ends on a `W`.
+pub fn parse_ucs<R: BufRead>(reader: &mut R) -> Result<Simfile, SimfileParseError> {
+    let mut data = String::new();
+    if reader.read_to_string(&mut data).is_err() {
+        return Err(SimfileParseError::BufReadError);
+    }
+
+    let mut sim = Simfile::new();
+    sim.offset = Some(0.0);
+    let mut columns: Option<usize> = None;
+    let mut notes = vec![];
+    let mut holds: Vec<Option<u32>> = vec![];
+
+    let mut block_start = 0.0;
+    let mut split = 1.0;
+    let mut line_index = 0;
+    let mut started = false;
+
+    for line in data.lines().map(|l| l.trim()).filter(|l| !l.is_empty()) {
+        if let Some(header) = line.strip_prefix(':') {
+            // A header after the lines of a block starts a new block
+            if line_index > 0 {
+                block_start += line_index as f32 / split;
+                line_index = 0;
+            }
+
+            let (key, value) = match header.find('=') {
+                Some(i) => (&header[..i], header[i + 1..].trim()),
+                None => (header, ""),
+            };
+            let number = || match value.parse::<f32>() {
+                Ok(number) => Ok(number),
+                Err(_) => Err(SimfileParseError::InvalidUcsData),
+            };
+
+            match key.trim().to_lowercase().as_ref() {
+                "mode" => {
+                    columns = match value.to_lowercase().as_ref() {
+                        "single" | "s-performance" => Some(5),
+                        "double" | "d-performance" => Some(10),
+                        _ => return Err(SimfileParseError::InvalidUcsData),
+                    };
+                    holds = vec![None; columns.unwrap()];
+                }
+                "bpm" => {
+                    let bpm = number()?;
+                    match sim.bpms.last_mut() {
+                        Some(last) if last.beat == block_start => last.bpm = bpm,
+                        _ => sim.bpms.push(BPM {
+                            beat: block_start,
+                            bpm,
+                        }),
+                    }
+                }
+                "delay" => {
+                    let time = number()? / 1000.0;
+                    if !started {
+                        sim.offset = Some(-time);
+                    } else if time != 0.0 {
+                        sim.delays.push(Delay {
+                            beat: block_start,
+                            time,
+                        });
+                    }
+                }
+                "beat" => {
+                    let numerator = number()? as u32;
+                    if sim.time_signatures.last().map(|s| s.numerator) != Some(numerator) {
+                        sim.time_signatures.push(TimeSignature {
+                            beat: block_start,
+                            numerator,
+                            denominator: 4,
+                        });
+                    }
+                }
+                "split" => {
+                    split = number()?;
+                    if split <= 0.0 {
+                        return Err(SimfileParseError::InvalidUcsData);
+                    }
+                }
+                _ => {}
+            }
+            continue;
+        }
+
+        if columns != Some(line.chars().count()) {
+            return Err(SimfileParseError::InvalidUcsData);
+        }
+        let beat = block_start + line_index as f32 / split;
+        let row = (beat * ROWS_PER_BEAT as f32).round() as u32;
+        line_index += 1;
+        started = true;
+
+        for (column, c) in line.chars().enumerate() {
+            let note_type = match (c.to_ascii_uppercase(), holds[column]) {
+                ('.', _) | ('H', _) => continue,
+                ('X', _) => NoteType::Normal,
+                ('M', _) => {
+                    holds[column] = Some(row);
+                    continue;
+                }
+                ('W', Some(start)) => {
+                    holds[column] = None;
+                    notes.push(TimedNote {
+                        row: start,
+                        column,
+                        note_type: NoteType::HoldHead,
+                    });
+                    NoteType::HoldOrRollTail
+                }
+                ('W', None) => NoteType::Normal,
+                _ => return Err(SimfileParseError::InvalidUcsData),
+            };
+            notes.push(TimedNote {
+                row,
+                column,
+                note_type,
+            });
+        }
+    }
+
+    // Holds that never end are turned into normal notes
+    for (column, start) in holds.iter().enumerate() {
+        if let Some(row) = start {
+            notes.push(TimedNote {
+                row: *row,
+                column,
+                note_type: NoteType::Normal,
+            });
+        }
+    }
+    notes.sort_by_key(|n| (n.row, n.column));
+
+    let columns = match columns {
+        Some(columns) => columns,
+        None => return Err(SimfileParseError::InvalidUcsData),
+    };
+    sim.charts.push(Chart {
+        chart_type: match columns {
+            5 => "pump-single".to_string(),
+            _ => "pump-double".to_string(),
+        },
+        author: None,
+        difficulty: ChartDifficulty::Edit,
+        difficulty_name: String::new(),
+        meter: 1,
+        radar_values: Vec::new(),
+        note_data: note_data_from_notes(columns, &notes),
+        lasers: Vec::new(),
+        timing: None,
+        credit: None,
+    });
+
+    Ok(sim)
+}
+
+/// Writes a "pump-single" or "pump-double" chart of a simfile as a Pump It Up step file (.ucs).
+///
+/// A new block is started on every BPM change and delay, using as few lines per beat as
+/// possible. UCS has no stops, so a stop becomes a delay on the next note or block after it,
+/// which keeps every note on the same time. Only normal notes, holds and rolls are written, rolls
+/// are written as holds; UCS has no mines, fakes or lifts, so they are left out. Returns an
+/// `InvalidInput` error for other chart types.
+pub fn write_ucs<W: Write>(simfile: &Simfile, chart: &Chart, writer: &mut W) -> io::Result<()> {
+    let (mode, columns) = match chart.chart_type.as_ref() {
+        "pump-single" => ("Single", 5),
+        "pump-double" => ("Double", 10),
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "only pump-single and pump-double charts can be written",
+            ))
+        }
+    };
+    let timing = TimingData::from_chart(simfile, chart);
+    let row_of = |beat: f32| (beat * ROWS_PER_BEAT as f32).round() as u32;
+
+    let notes: BTreeMap<(u32, usize), NoteType> = chart
+        .notes()
+        .into_iter()
+        .filter(|n| {
+            matches!(
+                n.note_type,
+                NoteType::Normal
+                    | NoteType::HoldHead
+                    | NoteType::RollHead
+                    | NoteType::HoldOrRollTail
+            )
+        })
+        .map(|n| ((n.row, n.column), n.note_type))
+        .collect();
+    let last_row = notes.keys().map(|(row, _)| *row).max().unwrap_or(0);
+    let end_row = (last_row / ROWS_PER_MEASURE + 1) * ROWS_PER_MEASURE;
+
+    // The start row and delay in seconds of every block
+    let mut blocks: BTreeMap<u32, f32> = BTreeMap::new();
+    blocks.insert(0, -timing.offset);
+    for bpm in timing.bpms.iter().map(|b| row_of(b.beat)) {
+        if bpm < end_row {
+            blocks.entry(bpm).or_insert(0.0);
+        }
+    }
+    for delay in timing.delays.iter() {
+        let row = row_of(delay.beat);
+        if row < end_row {
+            *blocks.entry(row).or_insert(0.0) += delay.time;
+        }
+    }
+    for stop in timing.stops.iter() {
+        let row = row_of(stop.beat);
+        let next_note = notes.keys().map(|(r, _)| *r).find(|r| *r > row);
+        let next_block = blocks.keys().cloned().find(|r| *r > row);
+        match (next_note, next_block) {
+            (Some(note), Some(block)) => *blocks.entry(note.min(block)).or_insert(0.0) += stop.time,
+            (Some(r), None) | (None, Some(r)) => *blocks.entry(r).or_insert(0.0) += stop.time,
+            (None, None) => {}
+        }
+    }
+
+    writeln!(writer, ":Format=1")?;
+    writeln!(writer, ":Mode={}", mode)?;
+
+    let time_signatures = chart
+        .timing
+        .as_ref()
+        .map_or(&simfile.time_signatures, |t| &t.time_signatures);
+    let starts: Vec<u32> = blocks.keys().cloned().collect();
+    let mut holding = vec![false; columns];
+    for (i, (start, delay)) in blocks.iter().enumerate() {
+        let end = starts.get(i + 1).cloned().unwrap_or(end_row);
+        let rows_in_block = notes
+            .keys()
+            .map(|(row, _)| *row)
+            .filter(|row| *row >= *start && *row < end)
+            .chain(std::iter::once(end))
+            .map(|row| row - start)
+            .collect::<Vec<u32>>();
+        let split = SPLITS
+            .iter()
+            .cloned()
+            .find(|s| {
+                let step = ROWS_PER_BEAT / s;
+                rows_in_block.iter().all(|row| row % step == 0)
+            })
+            .unwrap_or(ROWS_PER_BEAT);
+        let beat = *start as f32 / ROWS_PER_BEAT as f32;
+        let beats_per_measure = time_signatures
+            .iter()
+            .take_while(|s| s.beat <= beat)
+            .last()
+            .map(|s| s.numerator)
+            .unwrap_or(4);
+
+        writeln!(writer, ":BPM={}", timing.bpm_at_beat(beat))?;
+        writeln!(writer, ":Delay={}", (delay * 1000.0).round() as i64)?;
+        writeln!(writer, ":Beat={}", beats_per_measure)?;
+        writeln!(writer, ":Split={}", split)?;
+
+        let step = (ROWS_PER_BEAT / split) as usize;
+        for row in (*start..end).step_by(step) {
+            let mut line = String::with_capacity(columns);
+            for (column, held) in holding.iter_mut().enumerate() {
+                let c = match notes.get(&(row, column)) {
+                    Some(NoteType::Normal) => 'X',
+                    Some(NoteType::HoldHead) | Some(NoteType::RollHead) => {
+                        *held = true;
+                        'M'
+                    }
+                    Some(NoteType::HoldOrRollTail) if *held => {
+                        *held = false;
+                        'W'
+                    }
+                    _ if *held => 'H',
+                    _ => '.',
+                };
+                line.push(c);
+            }
+            writeln!(writer, "{}", line)?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,244 @@
+extern crate sm_parser;
+
+mod common;
+
+use common::test_dir;
+use sm_parser::cache::SongCache;
+use sm_parser::catalogue::scan_songs_dir_cached;
+use sm_parser::format::load_simfile;
+use sm_parser::song_directory::SongDirectory;
+use sm_parser::ssc::write_ssc;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+/// Replaces the contents of a file without changing its modification time.
+fn overwrite_keeping_time(path: &Path, data: &str) {
+    let modified = fs::metadata(path).unwrap().modified().unwrap();
+    fs::write(path, data).unwrap();
+    File::options()
+        .write(true)
+        .open(path)
+        .unwrap()
+        .set_modified(modified)
+        .unwrap();
+}
+
+const TEST_SM: &str = "#TITLE:Cached;#BPMS:0=120;#NOTES:dance-single::Hard:9::1000;";
+
+#[test]
+fn reuses_unchanged_files() {
+    let dir = test_dir("unchanged", &[]);
+    let path = dir.join("song.sm");
+    fs::write(&path, TEST_SM).unwrap();
+    let cache = SongCache::new(true);
+
+    let sim = cache.load_simfile(&path).unwrap();
+    assert_eq!(sim.title, Some("Cached".to_string()));
+    assert_eq!(sim.charts.len(), 1);
+    assert_eq!(cache.len(), 1);
+
+    // Same size and time, so the file isn't read again
+    overwrite_keeping_time(&path, &TEST_SM.replace("Cached", "Edited"));
+    let sim = cache.load_simfile(&path).unwrap();
+    assert_eq!(sim.title, Some("Cached".to_string()));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn parses_changed_files_again() {
+    let dir = test_dir("changed", &[]);
+    let path = dir.join("song.sm");
+    fs::write(&path, TEST_SM).unwrap();
+    let cache = SongCache::new(true);
+    cache.load_simfile(&path).unwrap();
+
+    overwrite_keeping_time(&path, &TEST_SM.replace("Cached", "Changed title"));
+    let sim = cache.load_simfile(&path).unwrap();
+    assert_eq!(sim.title, Some("Changed title".to_string()));
+    assert_eq!(cache.len(), 1);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn saves_and_loads_cache() {
+    let dir = test_dir("save", &[]);
+    let path = dir.join("song.sm");
+    let cache_path = dir.join("songs.cache");
+    fs::write(&path, TEST_SM).unwrap();
+    let cache = SongCache::new(true);
+    cache.load_simfile(&path).unwrap();
+    cache.save(&cache_path).unwrap();
+
+    let cache = SongCache::load(&cache_path, true);
+    assert_eq!(cache.len(), 1);
+    overwrite_keeping_time(&path, &TEST_SM.replace("Cached", "Edited"));
+    let sim = cache.load_simfile(&path).unwrap();
+    assert_eq!(sim.title, Some("Cached".to_string()));
+    assert_eq!(sim.charts[0].meter, 9);
+
+    // A cache saved with charts can't be used for one without them
+    assert!(SongCache::load(&cache_path, false).is_empty());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn loads_saved_simfiles_without_parsing() {
+    let dir = test_dir("no_parse", &[]);
+    let path = dir.join("song.sm");
+    let cache_path = dir.join("songs.cache");
+    fs::write(&path, TEST_SM).unwrap();
+    let cache = SongCache::new(true);
+    cache.load_simfile(&path).unwrap();
+    cache.save(&cache_path).unwrap();
+
+    // The cache keeps the parsed simfile instead of the file
+    let data = fs::read_to_string(&cache_path).unwrap();
+    assert!(!data.contains(TEST_SM));
+    assert!(data.contains("\nCached\n"));
+
+    // The file can't be parsed anymore, but the simfile is still taken from the cache
+    let unparsable = format!("#BPMS:x;{}", " ".repeat(TEST_SM.len() - 8));
+    overwrite_keeping_time(&path, &unparsable);
+    assert!(load_simfile(&path).is_err());
+    let sim = SongCache::load(&cache_path, true)
+        .load_simfile(&path)
+        .unwrap();
+    assert_eq!(sim.title, Some("Cached".to_string()));
+    assert_eq!(sim.bpms[0].bpm, 120.0);
+    assert_eq!(sim.charts[0].meter, 9);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn saves_only_headers() {
+    let dir = test_dir("saved_headers", &[]);
+    let path = dir.join("song.sm");
+    let cache_path = dir.join("songs.cache");
+    fs::write(&path, TEST_SM).unwrap();
+    let cache = SongCache::new(false);
+    cache.load_simfile(&path).unwrap();
+    cache.save(&cache_path).unwrap();
+
+    let data = fs::read_to_string(&cache_path).unwrap();
+    assert!(data.contains("\nCached\n"));
+    assert!(!data.contains("dance-single"));
+    assert!(!data.contains("1000"));
+
+    let sim = SongCache::load(&cache_path, false)
+        .load_simfile(&path)
+        .unwrap();
+    assert_eq!(sim.title, Some("Cached".to_string()));
+    assert!(sim.charts.is_empty());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+const TEST_KSH: &str = "title=Lasers
+artist=Someone
+effect=mapper
+difficulty=extended
+level=12
+t=120
+--
+1000|00|0-
+0100|02|:-
+0010|00|o-
+0001|10|-0
+--
+0000|00|-o
+--
+";
+
+#[test]
+fn cached_simfiles_are_the_same_as_loaded_ones() {
+    let dir = test_dir("same", &[]);
+    let cache_path = dir.join("songs.cache");
+    let example = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("example_files/goin_under.sm");
+    let mut ssc = vec![];
+    write_ssc(&load_simfile(&example).unwrap(), &mut ssc).unwrap();
+    fs::copy(&example, dir.join("song.sm")).unwrap();
+    fs::write(dir.join("song.ssc"), ssc).unwrap();
+    fs::write(dir.join("song.ksh"), TEST_KSH).unwrap();
+
+    let cache = SongCache::new(true);
+    for name in &["song.sm", "song.ssc", "song.ksh"] {
+        let path = dir.join(name);
+        let loaded = format!("{:?}", load_simfile(&path).unwrap());
+
+        // Parsed when it's added, taken from memory and parsed again from a saved cache
+        assert_eq!(format!("{:?}", cache.load_simfile(&path).unwrap()), loaded);
+        assert_eq!(format!("{:?}", cache.load_simfile(&path).unwrap()), loaded);
+        cache.save(&cache_path).unwrap();
+        let saved = SongCache::load(&cache_path, true);
+        assert_eq!(format!("{:?}", saved.load_simfile(&path).unwrap()), loaded);
+    }
+    assert!(!cache.load_simfile(dir.join("song.ksh")).unwrap().charts[0]
+        .lasers
+        .is_empty());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn ignores_damaged_cache_files() {
+    let dir = test_dir("damaged", &[]);
+    let cache_path = dir.join("songs.cache");
+
+    assert!(SongCache::load(&cache_path, true).is_empty());
+    fs::write(
+        &cache_path,
+        "sm_parser song cache 3 charts\n0 1 2 500 song.sm\n#TITLE",
+    )
+    .unwrap();
+    assert!(SongCache::load(&cache_path, true).is_empty());
+    fs::write(
+        &cache_path,
+        "sm_parser song cache 3 charts\n0 1 2 6 song.sm\n+\n1.5\n\n",
+    )
+    .unwrap();
+    assert!(SongCache::load(&cache_path, true).is_empty());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn keeps_only_headers() {
+    let dir = test_dir("headers", &[]);
+    let path = dir.join("song.sm");
+    fs::write(&path, TEST_SM).unwrap();
+    let cache = SongCache::new(false);
+
+    let sim = cache.load_simfile(&path).unwrap();
+    assert_eq!(sim.title, Some("Cached".to_string()));
+    assert!(sim.charts.is_empty());
+    assert_eq!(sim.bpms.len(), 1);
+
+    fs::remove_file(&path).unwrap();
+    cache.prune();
+    assert!(cache.is_empty());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn scans_songs_with_cache() {
+    let dir = test_dir("scan", &[]);
+    for song in &["Song 1", "Song 2"] {
+        fs::create_dir_all(dir.join("Pack").join(song)).unwrap();
+        fs::write(dir.join("Pack").join(song).join("song.sm"), TEST_SM).unwrap();
+    }
+    let cache = SongCache::new(false);
+
+    let catalogue = scan_songs_dir_cached(&dir, 2, &cache).unwrap();
+    assert_eq!(catalogue.songs().count(), 2);
+    assert_eq!(cache.len(), 2);
+
+    let song = SongDirectory::load_cached(dir.join("Pack/Song 1"), &cache).unwrap();
+    assert_eq!(song.simfile.title, Some("Cached".to_string()));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,174 @@
+extern crate sm_parser;
+
+use sm_parser::radar::{
+    calculate_radar_values, calculate_radar_values_for_length, fill_radar_values,
+};
+use sm_parser::simfile::Simfile;
+use sm_parser::{parse_simfile, SimfileParseError};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::PathBuf;
+
+fn load_and_parse_simfile(filename: &str) -> Result<Simfile, SimfileParseError> {
+    let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    d.push(format!("example_files/{}", filename));
+    let file = File::open(d).unwrap();
+
+    parse_simfile(&mut BufReader::new(file))
+}
+
+fn parse_string_as_simfile(data: &str) -> Result<Simfile, SimfileParseError> {
+    parse_simfile(&mut BufReader::new(data.as_bytes()))
+}
+
+// 2 measures at 120 BPM, every beat is half a second
+const TEST_CHART: &str = "
+#BPMS:0.000=120.000;
+#NOTES:dance-single::Hard:5::
+1000
+0100
+1001
+0010
+,
+2000
+0M00
+3000
+0000
+0001
+0000
+1100
+0000
+;
+";
+
+#[test]
+fn counts_notes() {
+    let sim = parse_string_as_simfile(TEST_CHART).unwrap();
+    let radar_values = calculate_radar_values_for_length(&sim.charts[0], 4.0);
+
+    assert_eq!(radar_values.notes, Some(9.0));
+    assert_eq!(radar_values.taps, Some(7.0));
+    assert_eq!(radar_values.jumps, Some(2.0));
+    assert_eq!(radar_values.holds, Some(1.0));
+    assert_eq!(radar_values.mines, Some(1.0));
+    assert_eq!(radar_values.hands, Some(0.0));
+    assert_eq!(radar_values.rolls, Some(0.0));
+    assert_eq!(radar_values.lifts, Some(0.0));
+    assert_eq!(radar_values.fakes, Some(0.0));
+}
+
+#[test]
+fn ignores_partial_lines() {
+    let sim = parse_string_as_simfile("#NOTES:dance-single::Hard:9::10,1000 0100 01;").unwrap();
+    let radar_values = calculate_radar_values_for_length(&sim.charts[0], 4.0);
+
+    assert_eq!(sim.charts[0].notes().len(), 2);
+    assert_eq!(radar_values.notes, Some(2.0));
+}
+
+#[test]
+fn calculates_radar_values_from_song_length() {
+    let sim = parse_string_as_simfile(TEST_CHART).unwrap();
+    let radar_values = calculate_radar_values_for_length(&sim.charts[0], 4.0);
+
+    assert_eq!(radar_values.stream, 9.0 / 4.0 / 7.0);
+    // 9 notes in the first 8 beat window, with the last note on beat 7
+    assert_eq!(radar_values.voltage, (9.0 / 8.0) * 7.0 / 4.0 / 10.0);
+    assert_eq!(radar_values.air, 2.0 / 4.0);
+    assert_eq!(radar_values.freeze, 1.0 / 4.0);
+    // Every note is on a 4th or 8th, so there's no chaos
+    assert_eq!(radar_values.chaos, 0.0);
+}
+
+#[test]
+fn radar_values_are_zero_without_song_length() {
+    let sim = parse_string_as_simfile(TEST_CHART).unwrap();
+    let radar_values = calculate_radar_values_for_length(&sim.charts[0], 0.0);
+
+    assert_eq!(radar_values.stream, 0.0);
+    assert_eq!(radar_values.voltage, 0.0);
+    assert_eq!(radar_values.notes, Some(9.0));
+}
+
+#[test]
+fn uses_last_note_as_song_length() {
+    let sim = parse_string_as_simfile(TEST_CHART).unwrap();
+    let radar_values = calculate_radar_values(&sim, &sim.charts[0]);
+
+    // The last note is on beat 7, which is 3.5 seconds in
+    assert_eq!(radar_values.stream, 9.0 / 3.5 / 7.0);
+}
+
+#[test]
+fn uses_music_length_as_song_length() {
+    let mut sim = parse_string_as_simfile(TEST_CHART).unwrap();
+    sim.music_length = Some(8.0);
+    let radar_values = calculate_radar_values(&sim, &sim.charts[0]);
+
+    assert_eq!(radar_values.stream, 9.0 / 8.0 / 7.0);
+}
+
+#[test]
+fn counts_chaos_and_hands() {
+    let sim = parse_string_as_simfile(
+        "#NOTES:dance-single::Hard:5::
+        2000
+        0000
+        0110
+        0000
+        3000
+        0000
+        0000
+        1011
+        ;",
+    )
+    .unwrap();
+    let radar_values = calculate_radar_values_for_length(&sim.charts[0], 1.0);
+
+    // The jump on beat 1 is pressed while the hold is held
+    assert_eq!(radar_values.hands, Some(2.0));
+    assert_eq!(radar_values.chaos, 0.0);
+
+    let sim = parse_string_as_simfile(
+        "#NOTES:dance-single::Hard:5::
+        1000
+        0100
+        0010
+        ;",
+    )
+    .unwrap();
+    let radar_values = calculate_radar_values_for_length(&sim.charts[0], 2.0);
+    // The last two notes are 12th notes
+    assert_eq!(radar_values.chaos, 2.0 * 0.5 / 2.0);
+}
+
+#[test]
+fn calculated_counts_match_saved_counts() {
+    let sim = load_and_parse_simfile("goin_under.sm").unwrap();
+
+    for chart in &sim.charts {
+        let saved = &chart.radar_values[0];
+        let calculated = calculate_radar_values(&sim, chart);
+        assert_eq!(calculated.notes, saved.notes);
+        assert_eq!(calculated.taps, saved.taps);
+        assert_eq!(calculated.jumps, saved.jumps);
+        assert_eq!(calculated.holds, saved.holds);
+        assert_eq!(calculated.mines, saved.mines);
+        assert_eq!(calculated.hands, saved.hands);
+    }
+}
+
+#[test]
+fn fill_radar_values_only_replaces_missing_values() {
+    let mut sim = parse_string_as_simfile(&format!(
+        "{}#NOTES:dance-single::Easy:1:0.1,0.2,0.3,0.4,0.5:1000;",
+        TEST_CHART
+    ))
+    .unwrap();
+    fill_radar_values(&mut sim);
+
+    assert_eq!(sim.charts[0].radar_values.len(), 1);
+    assert_eq!(sim.charts[0].radar_values[0].notes, Some(9.0));
+    assert_eq!(sim.charts[1].radar_values[0].stream, 0.1);
+    assert_eq!(sim.charts[1].radar_values[0].notes, None);
+}
@@ -0,0 +1,154 @@
+extern crate sm_parser;
+
+mod common;
+
+use common::test_dir;
+use sm_parser::catalogue::{parse_group_ini, scan_songs_dir, Catalogue, GroupInfo};
+use sm_parser::SimfileParseError;
+use std::fs;
+use std::io::BufReader;
+
+fn parse_string_as_group_ini(data: &str) -> Result<GroupInfo, SimfileParseError> {
+    parse_group_ini(&mut BufReader::new(data.as_bytes()))
+}
+
+const TEST_SONGS: [(&str, &str); 8] = [
+    (
+        "Pack A/Group.ini",
+        "#DISPLAYTITLE:The First Pack;#SYNCOFFSET:ITG;",
+    ),
+    ("Pack A/banner.png", ""),
+    ("Pack A/Song 1/song.sm", "#TITLE:Song 1;"),
+    ("Pack A/Song 2/song.ssc", "#TITLE:Song 2;"),
+    ("Pack A/Broken/song.sm", "#BPMS:0=abc;"),
+    ("Pack A/Empty/readme.txt", ""),
+    ("Pack B/group.ini", "#SYNCOFFSET:later;"),
+    ("Pack B/Song 3/song.sm", "#TITLE:Song 3;"),
+];
+
+#[test]
+fn parses_group_ini() {
+    let info = parse_string_as_group_ini(
+        "#DISPLAYTITLE:Pack;#SORTTITLE:pack;#TRANSLITTITLE:Pack;#SERIES:Series;#SYNCOFFSET:NULL;",
+    )
+    .unwrap();
+
+    assert_eq!(info.display_title, Some("Pack".to_string()));
+    assert_eq!(info.sort_title, Some("pack".to_string()));
+    assert_eq!(info.series, Some("Series".to_string()));
+    assert_eq!(info.sync_offset, Some(0.0));
+    assert_eq!(
+        parse_string_as_group_ini("#SYNCOFFSET:itg;")
+            .unwrap()
+            .sync_offset,
+        Some(0.009)
+    );
+    assert_eq!(
+        parse_string_as_group_ini("#SYNCOFFSET:later;").unwrap_err(),
+        SimfileParseError::InvalidGroupData
+    );
+}
+
+#[test]
+fn scans_groups_and_songs() {
+    let dir = test_dir("scan", &TEST_SONGS);
+    let catalogue = scan_songs_dir(&dir, 1).unwrap();
+
+    assert_eq!(catalogue.groups.len(), 2);
+    let group = &catalogue.groups[0];
+    assert_eq!(group.name, "Pack A");
+    assert_eq!(group.info.display_title, Some("The First Pack".to_string()));
+    assert_eq!(group.info.sync_offset, Some(0.009));
+    assert_eq!(group.banner, Some(dir.join("Pack A/banner.png")));
+    let titles: Vec<_> = group
+        .songs
+        .iter()
+        .map(|s| s.simfile.title.clone())
+        .collect();
+    assert_eq!(
+        titles,
+        vec![Some("Song 1".to_string()), Some("Song 2".to_string())]
+    );
+
+    let group = &catalogue.groups[1];
+    assert_eq!(group.info, GroupInfo::default());
+    assert_eq!(group.songs.len(), 1);
+    assert_eq!(catalogue.songs().count(), 3);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn reports_files_that_fail_to_load() {
+    let dir = test_dir("diagnostics", &TEST_SONGS);
+    let catalogue = scan_songs_dir(&dir, 1).unwrap();
+    let diagnostics: Vec<_> = catalogue
+        .diagnostics
+        .iter()
+        .map(|d| (d.path.clone(), d.error))
+        .collect();
+
+    assert_eq!(
+        diagnostics,
+        vec![
+            (
+                dir.join("Pack B/group.ini"),
+                SimfileParseError::InvalidGroupData
+            ),
+            (
+                dir.join("Pack A/Broken"),
+                SimfileParseError::FailedToParseBPMs
+            ),
+            (
+                dir.join("Pack A/Empty"),
+                SimfileParseError::NoSimfileInDirectory
+            ),
+        ]
+    );
+    assert_eq!(
+        scan_songs_dir(dir.join("missing"), 1).unwrap_err(),
+        SimfileParseError::BufReadError
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn loads_songs_in_parallel() {
+    let mut files = vec![];
+    for i in 0..20 {
+        files.push((
+            format!("Pack/Song {:02}/song.sm", i),
+            format!("#TITLE:Song {:02};", i),
+        ));
+    }
+    let files: Vec<(&str, &str)> = files.iter().map(|(f, d)| (&f[..], &d[..])).collect();
+    let dir = test_dir("parallel", &files);
+
+    let titles = |catalogue: &Catalogue| -> Vec<Option<String>> {
+        catalogue.songs().map(|s| s.simfile.title.clone()).collect()
+    };
+
+    let sequential = scan_songs_dir(&dir, 1).unwrap();
+    for threads in &[0, 4] {
+        let parallel = scan_songs_dir(&dir, *threads).unwrap();
+        assert_eq!(titles(&parallel), titles(&sequential));
+    }
+    assert_eq!(sequential.songs().count(), 20);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn lists_songs_for_courses() {
+    let dir = test_dir("courses", &TEST_SONGS);
+    let catalogue = scan_songs_dir(&dir, 1).unwrap();
+    let songs = catalogue.loaded_songs();
+
+    assert_eq!(songs.len(), 3);
+    assert_eq!(songs[0].group, "Pack A");
+    assert_eq!(songs[0].folder, "Song 1");
+    assert_eq!(songs[2].group, "Pack B");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,42 @@
+//! Helpers shared by the integration tests. Not every test uses every helper.
+#![allow(dead_code)]
+
+use sm_parser::simfile::{NoteType, TimedNote};
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+pub fn note(row: u32, column: usize, note_type: NoteType) -> TimedNote {
+    TimedNote {
+        row,
+        column,
+        note_type,
+    }
+}
+
+/// Creates an empty folder for a test, with the given files in it.
+///
+/// Every call gets its own folder, so tests running at the same time (or in another test run)
+/// never share one.
+pub fn test_dir(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    let dir = std::env::temp_dir().join(format!(
+        "sm_parser_test_{}_{}_{}",
+        process::id(),
+        COUNT.fetch_add(1, Ordering::SeqCst),
+        name
+    ));
+    if dir.exists() {
+        fs::remove_dir_all(&dir).unwrap();
+    }
+    fs::create_dir_all(&dir).unwrap();
+    for (file, data) in files {
+        let path = dir.join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, data).unwrap();
+    }
+
+    dir
+}
@@ -0,0 +1,155 @@
+extern crate sm_parser;
+
+use sm_parser::diff::{
+    diff_simfiles, ChartStatus, FieldChange, NoteEdit, TimingChange, TimingKind,
+};
+use sm_parser::parse_simfile;
+use sm_parser::simfile::{ChartDifficulty, NoteType, Simfile};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::PathBuf;
+
+fn parse_string_as_simfile(data: &str) -> Simfile {
+    parse_simfile(&mut BufReader::new(data.as_bytes())).unwrap()
+}
+
+const OLD_SM: &str = "#TITLE:Song;#ARTIST:Someone;#OFFSET:-0.1;
+#BPMS:0=120,8=150;#STOPS:4=0.5;
+#NOTES:dance-single::Hard:9::1000 0100 0010 0001,0000 0000 0000 0000;
+#NOTES:dance-single:mapper:Edit:5::1000 0000 0000 0000;
+#NOTES:dance-double::Easy:3::10000000;";
+
+const NEW_SM: &str = "#TITLE:Song (Remix);#ARTIST:Someone;#OFFSET:-0.12;
+#BPMS:0=120,8=160;#STOPS:2=0.5;
+#NOTES:dance-single::Hard:10::
+1000 0000 0100 0000 0010 0000 0002 0000,0000 0000 0003 0000 0000 0000 0000 0000;
+#NOTES:dance-single:mapper:Edit:5::1000 0000 0000 0000;
+#NOTES:dance-single:someone else:Edit:6::0001;";
+
+#[test]
+fn finds_no_changes_in_same_simfile() {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    path.push("example_files/goin_under.sm");
+    let sim = parse_simfile(&mut BufReader::new(File::open(path).unwrap())).unwrap();
+
+    let diff = diff_simfiles(&sim, &sim.clone());
+    assert!(diff.is_empty());
+    assert_eq!(diff.to_string(), "");
+}
+
+#[test]
+fn diffs_headers() {
+    let diff = diff_simfiles(
+        &parse_string_as_simfile(OLD_SM),
+        &parse_string_as_simfile(NEW_SM),
+    );
+
+    assert_eq!(
+        diff.headers,
+        vec![
+            FieldChange {
+                field: "TITLE",
+                old: Some("Song".to_string()),
+                new: Some("Song (Remix)".to_string()),
+            },
+            FieldChange {
+                field: "OFFSET",
+                old: Some("-0.1".to_string()),
+                new: Some("-0.12".to_string()),
+            },
+        ]
+    );
+}
+
+#[test]
+fn diffs_timing() {
+    let diff = diff_simfiles(
+        &parse_string_as_simfile(OLD_SM),
+        &parse_string_as_simfile(NEW_SM),
+    );
+
+    assert_eq!(
+        diff.timing,
+        vec![
+            TimingChange {
+                kind: TimingKind::Bpm,
+                beat: 8.0,
+                old: Some(150.0),
+                new: Some(160.0),
+            },
+            TimingChange {
+                kind: TimingKind::Stop,
+                beat: 2.0,
+                old: None,
+                new: Some(0.5),
+            },
+            TimingChange {
+                kind: TimingKind::Stop,
+                beat: 4.0,
+                old: Some(0.5),
+                new: None,
+            },
+        ]
+    );
+}
+
+#[test]
+fn diffs_charts() {
+    let diff = diff_simfiles(
+        &parse_string_as_simfile(OLD_SM),
+        &parse_string_as_simfile(NEW_SM),
+    );
+    assert_eq!(diff.charts.len(), 3);
+
+    // The notes were written with more rows, which isn't a change, only the hold is
+    let chart = &diff.charts[0];
+    assert_eq!(chart.status, ChartStatus::Changed);
+    assert_eq!(chart.difficulty, ChartDifficulty::Hard);
+    assert_eq!(chart.fields[0].field, "METER");
+    assert_eq!(
+        chart.notes,
+        vec![
+            NoteEdit {
+                beat: 3.0,
+                column: 3,
+                old: NoteType::Normal,
+                new: NoteType::HoldHead,
+            },
+            NoteEdit {
+                beat: 5.0,
+                column: 3,
+                old: NoteType::None,
+                new: NoteType::HoldOrRollTail,
+            },
+        ]
+    );
+
+    assert_eq!(diff.charts[1].chart_type, "dance-double");
+    assert_eq!(diff.charts[1].status, ChartStatus::Removed);
+    assert_eq!(diff.charts[2].author, Some("someone else".to_string()));
+    assert_eq!(diff.charts[2].status, ChartStatus::Added);
+}
+
+#[test]
+fn formats_diff() {
+    let diff = diff_simfiles(
+        &parse_string_as_simfile(OLD_SM),
+        &parse_string_as_simfile(NEW_SM),
+    );
+
+    assert_eq!(
+        diff.to_string(),
+        "TITLE: \"Song\" -> \"Song (Remix)\"
+OFFSET: \"-0.1\" -> \"-0.12\"
+BPM at beat 8: 150 -> 160
+Stop at beat 2: none -> 0.5
+Stop at beat 4: 0.5 -> none
+dance-single Hard: changed
+  METER: \"9\" -> \"10\"
+  beat 3, column 3: tap -> hold head
+  beat 5, column 3: empty -> tail
+dance-double Easy: removed
+dance-single Edit (someone else): added
+"
+    );
+}
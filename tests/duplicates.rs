@@ -0,0 +1,130 @@
+extern crate sm_parser;
+
+use sm_parser::duplicates::{compare_charts, find_duplicates, ChartId, DuplicateKind};
+use sm_parser::parse_simfile;
+use sm_parser::simfile::Simfile;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::PathBuf;
+
+fn parse_string_as_simfile(data: &str) -> Simfile {
+    parse_simfile(&mut BufReader::new(data.as_bytes())).unwrap()
+}
+
+fn parse_example_file(name: &str) -> Simfile {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    path.push("example_files");
+    path.push(name);
+    parse_simfile(&mut BufReader::new(File::open(path).unwrap())).unwrap()
+}
+
+const TEST_SM: &str = "#OFFSET:0;#BPMS:0=120;
+#NOTES:dance-single::Hard:9::
+1000
+0100
+0010
+0001
+,
+2000
+0000
+3000
+M000
+;";
+
+#[test]
+fn finds_exact_duplicates() {
+    let first = parse_string_as_simfile(TEST_SM);
+    let second = parse_string_as_simfile(TEST_SM);
+
+    let comparison = compare_charts(&first, &first.charts[0], &second, &second.charts[0]).unwrap();
+    assert_eq!(comparison.kind, DuplicateKind::Exact);
+    assert_eq!(comparison.similarity, 1.0);
+    assert_eq!(comparison.offset_difference, 0.0);
+}
+
+#[test]
+fn matches_notes_by_time() {
+    // Twice the BPM with twice as many beats between the notes puts them at the same times
+    let first = parse_string_as_simfile(TEST_SM);
+    let second = parse_string_as_simfile(
+        "#OFFSET:0;#BPMS:0=240;
+        #NOTES:dance-single::Hard:9::
+        1000 0000 0100 0000,0010 0000 0001 0000,
+        2000 0000 0000 0000,3000 0000 M000 0000;",
+    );
+
+    let comparison = compare_charts(&first, &first.charts[0], &second, &second.charts[0]).unwrap();
+    assert_eq!(comparison.kind, DuplicateKind::Exact);
+}
+
+#[test]
+fn finds_offset_shifted_duplicates() {
+    let first = parse_string_as_simfile(TEST_SM);
+    let second = parse_string_as_simfile(&TEST_SM.replace("#OFFSET:0;", "#OFFSET:0.015;"));
+
+    let comparison = compare_charts(&first, &first.charts[0], &second, &second.charts[0]).unwrap();
+    assert_eq!(comparison.kind, DuplicateKind::OffsetShifted);
+    assert_eq!(comparison.similarity, 1.0);
+    assert!((comparison.offset_difference - 0.015).abs() < 1e-6);
+}
+
+#[test]
+fn finds_mirrored_and_similar_charts() {
+    let first = parse_string_as_simfile(TEST_SM);
+    let mirrored = parse_string_as_simfile(
+        "#BPMS:0=120;#NOTES:dance-single::Hard:9::0001 0010 0100 1000,0002 0000 0003 000M;",
+    );
+    let similar = parse_string_as_simfile(
+        "#BPMS:0=120;#NOTES:dance-single::Hard:9::1000 0100 0010 1000,2000 0000 3000 M000;",
+    );
+
+    let comparison =
+        compare_charts(&first, &first.charts[0], &mirrored, &mirrored.charts[0]).unwrap();
+    assert_eq!(comparison.kind, DuplicateKind::Mirrored);
+    assert_eq!(comparison.similarity, 1.0);
+
+    let comparison =
+        compare_charts(&first, &first.charts[0], &similar, &similar.charts[0]).unwrap();
+    assert_eq!(comparison.kind, DuplicateKind::Similar);
+    assert!((comparison.similarity - 5.0 / 6.0).abs() < 1e-6);
+}
+
+#[test]
+fn only_compares_same_chart_types() {
+    let first = parse_string_as_simfile(TEST_SM);
+    let double = parse_string_as_simfile(
+        "#BPMS:0=120;#NOTES:dance-double::Hard:9::10000000 01000000 00100000 00010000;",
+    );
+
+    assert!(compare_charts(&first, &first.charts[0], &double, &double.charts[0]).is_none());
+}
+
+#[test]
+fn finds_duplicates_across_library() {
+    let original = parse_example_file("goin_under.sm");
+    let mut reupload = original.clone();
+    reupload.offset = Some(original.offset.unwrap() - 0.02);
+    let other = parse_example_file("news_39.sm");
+
+    let duplicates = find_duplicates(vec![&original, &other, &reupload], 0.95);
+
+    assert_eq!(duplicates.len(), original.charts.len());
+    for (i, duplicate) in duplicates.iter().enumerate() {
+        assert_eq!(
+            duplicate.first,
+            ChartId {
+                simfile: 0,
+                chart: i
+            }
+        );
+        assert_eq!(
+            duplicate.second,
+            ChartId {
+                simfile: 2,
+                chart: i
+            }
+        );
+        assert_eq!(duplicate.comparison.kind, DuplicateKind::OffsetShifted);
+        assert!((duplicate.comparison.offset_difference + 0.02).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,158 @@
+extern crate sm_parser;
+
+use sm_parser::groovestats::{chart_hash, normalized_bpms, normalized_note_data};
+use sm_parser::parse_simfile;
+use sm_parser::simfile::{Simfile, BPM};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::PathBuf;
+
+fn parse_example_file(name: &str) -> Simfile {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    path.push("example_files");
+    path.push(name);
+    parse_simfile(&mut BufReader::new(File::open(path).unwrap())).unwrap()
+}
+
+fn parse_string_as_simfile(data: &str) -> Simfile {
+    parse_simfile(&mut BufReader::new(data.as_bytes())).unwrap()
+}
+
+fn hashes(sim: &Simfile) -> Vec<String> {
+    sim.charts
+        .iter()
+        .map(|chart| chart_hash(sim, chart).unwrap())
+        .collect()
+}
+
+#[test]
+fn hashes_normalized_chart() {
+    let sim = parse_string_as_simfile(
+        "#BPMS:0=150,32=75.5;
+        #NOTES:dance-single::Hard:9::
+        1000 0000 0100 0000 0010 0000 0001 0000,
+        0000 0000 0000 0001;",
+    );
+
+    // The SHA-1 of "1000\n0100\n0010\n0001\n,\n0000\n0000\n0000\n00010.000=150.000,32.000=75.500",
+    // hashed apart from this crate
+    assert_eq!(hashes(&sim), vec!["59f14bad4fc58a16"]);
+}
+
+#[test]
+fn hashes_goin_under() {
+    let sim = parse_example_file("goin_under.sm");
+
+    assert_eq!(
+        hashes(&sim),
+        vec![
+            "f267ba60615803d2",
+            "5dd5923ed8be785c",
+            "6abada15569e53c1",
+            "571c6cc8be5a269a",
+            "1329d92275410bbc",
+            "02a14fee2cf7b5dd",
+            "08bc3f31aed7349d",
+            "528341e6306abf57",
+            "2c0ca280b4a4920b",
+        ]
+    );
+}
+
+#[test]
+fn hashes_news_39() {
+    let sim = parse_example_file("news_39.sm");
+
+    assert_eq!(
+        hashes(&sim),
+        vec![
+            "a1bf565ce7eca7bb",
+            "01d33003985ad807",
+            "b17ee6b7a3d2c2b8",
+            "584f8236bbb31bba",
+            "51b4590cd20de8ba",
+        ]
+    );
+}
+
+#[test]
+fn minimizes_measures() {
+    let sim = parse_string_as_simfile(
+        "#NOTES:dance-single::Hard:9::
+        1000
+        0000
+        0100
+        0000
+        0010
+        0000
+        0001
+        0000
+        ,
+        0000
+        0000
+        0000
+        0000
+        ,
+        1000
+        0000
+        0000
+        0000
+        0000
+        0000
+        0100
+        0000
+        0000
+        0000
+        0000
+        0000;",
+    );
+
+    assert_eq!(
+        normalized_note_data(&sim.charts[0]).unwrap(),
+        "1000\n0100\n0010\n0001\n,\n0000\n,\n1000\n0000\n0000\n0100\n0000\n0000"
+    );
+}
+
+#[test]
+fn normalizes_bpms() {
+    let bpms = vec![
+        BPM {
+            beat: 0.0,
+            bpm: 132.0,
+        },
+        BPM {
+            beat: 291.6875,
+            bpm: -132.0005,
+        },
+    ];
+
+    assert_eq!(normalized_bpms(&bpms), "0.000=132.000,291.688=-132.000");
+}
+
+#[test]
+fn same_chart_written_differently_has_same_hash() {
+    let sim = parse_string_as_simfile(
+        "#BPMS:0=150;
+        #NOTES:dance-single::Hard:9::1000\n0100\n0010\n0001;
+        #NOTES:dance-single::Hard:9:: 1000 // first beat
+        0000 0100 0000 0010 0000 0001 0000;
+        #NOTES:dance-single::Hard:9::1000\n0100\n0010\n0002;",
+    );
+    let hashes = hashes(&sim);
+
+    assert_eq!(hashes[0], hashes[1]);
+    assert_ne!(hashes[0], hashes[2]);
+    assert!(chart_hash(
+        &sim,
+        &parse_string_as_simfile("#NOTES:guitar-five::Hard:9::00000;").charts[0]
+    )
+    .is_none());
+}
+
+#[test]
+fn does_not_hash_charts_with_unknown_notes() {
+    let sim = parse_string_as_simfile("#BPMS:0=150;#NOTES:dance-single::Hard:9::1000\n0X00;");
+
+    assert!(normalized_note_data(&sim.charts[0]).is_none());
+    assert!(chart_hash(&sim, &sim.charts[0]).is_none());
+}
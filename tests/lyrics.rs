@@ -0,0 +1,129 @@
+extern crate sm_parser;
+
+mod common;
+
+use common::test_dir;
+use sm_parser::lyrics::{load_lyrics, parse_lrc, LyricColor, LyricLine};
+use sm_parser::parse_simfile;
+use sm_parser::simfile::Simfile;
+use sm_parser::timing::TimingData;
+use sm_parser::SimfileParseError;
+use std::fs;
+use std::io::BufReader;
+
+fn parse_string_as_lrc(data: &str) -> Result<Vec<LyricLine>, SimfileParseError> {
+    parse_lrc(&mut BufReader::new(data.as_bytes()))
+}
+
+fn parse_string_as_simfile(data: &str) -> Simfile {
+    parse_simfile(&mut BufReader::new(data.as_bytes())).unwrap()
+}
+
+const TEST_LRC: &str = "[ti:Test Song]
+[ar:Someone]
+[00:01.50]First line
+[COLOUR]0xFF8000
+[00:03.00]Second|line
+[00:02.00][01:00.00]Chorus
+";
+
+#[test]
+fn parses_timed_lines() {
+    let lines = parse_string_as_lrc(TEST_LRC).unwrap();
+
+    let times: Vec<f32> = lines.iter().map(|l| l.time).collect();
+    assert_eq!(times, vec![1.5, 2.0, 3.0, 60.0]);
+    assert_eq!(lines[0].text, "First line");
+    assert_eq!(lines[1].text, "Chorus");
+    assert_eq!(lines[2].text, "Second\nline");
+    assert_eq!(lines[3].text, "Chorus");
+}
+
+#[test]
+fn parses_colors() {
+    let lines = parse_string_as_lrc(TEST_LRC).unwrap();
+    let orange = LyricColor {
+        red: 1.0,
+        green: 128.0 / 255.0,
+        blue: 0.0,
+        alpha: 1.0,
+    };
+
+    assert_eq!(lines[0].color, None);
+    assert_eq!(lines[1].color, Some(orange));
+    assert_eq!(lines[2].color, Some(orange));
+
+    let lines = parse_string_as_lrc("[color]0.5,0.25,1,0.5\n[00:01.00]a").unwrap();
+    assert_eq!(
+        lines[0].color,
+        Some(LyricColor {
+            red: 0.5,
+            green: 0.25,
+            blue: 1.0,
+            alpha: 0.5,
+        })
+    );
+}
+
+#[test]
+fn applies_offset() {
+    let lines = parse_string_as_lrc("[offset:+500]\n[00:02.00]a\n[00:04.00]b").unwrap();
+
+    assert_eq!(lines[0].time, 1.5);
+    assert_eq!(lines[1].time, 3.5);
+}
+
+#[test]
+fn parsing_invalid_lyrics_returns_error() {
+    assert_eq!(
+        parse_string_as_lrc("[00:xx]a").unwrap_err(),
+        SimfileParseError::InvalidLyricsData
+    );
+    assert_eq!(
+        parse_string_as_lrc("[COLOUR]0xFF80").unwrap_err(),
+        SimfileParseError::InvalidLyricsData
+    );
+    assert_eq!(
+        parse_string_as_lrc("[offset:soon]").unwrap_err(),
+        SimfileParseError::InvalidLyricsData
+    );
+}
+
+#[test]
+fn parsing_non_finite_times_returns_error() {
+    assert_eq!(
+        parse_string_as_lrc("[00:01.00]a\n[00:NaN]b").unwrap_err(),
+        SimfileParseError::InvalidLyricsData
+    );
+    assert_eq!(
+        parse_string_as_lrc("[offset:inf]\n[00:01.00]a").unwrap_err(),
+        SimfileParseError::InvalidLyricsData
+    );
+}
+
+#[test]
+fn converts_times_to_beats() {
+    let lines = parse_string_as_lrc(TEST_LRC).unwrap();
+    let sim = parse_string_as_simfile("#OFFSET:-0.5;#BPMS:0=120;");
+    let timing = TimingData::from_simfile(&sim);
+
+    assert_eq!(lines[0].beat(&timing), 2.0);
+    assert_eq!(lines[3].beat(&timing), 119.0);
+}
+
+#[test]
+fn loads_lyrics_relative_to_song() {
+    let dir = test_dir("lyrics", &[("song.lrc", TEST_LRC)]);
+
+    let sim = parse_string_as_simfile("#LYRICSPATH:song.lrc;");
+    assert_eq!(load_lyrics(&sim, &dir).unwrap().unwrap().len(), 4);
+
+    let sim = parse_string_as_simfile("#LYRICSPATH:missing.lrc;");
+    assert_eq!(
+        load_lyrics(&sim, &dir).unwrap().unwrap_err(),
+        SimfileParseError::BufReadError
+    );
+    assert!(load_lyrics(&parse_string_as_simfile("#TITLE:a;"), &dir).is_none());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
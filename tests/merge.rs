@@ -0,0 +1,271 @@
+extern crate sm_parser;
+
+use sm_parser::merge::{merge_simfiles, ChartRegion, Conflict};
+use sm_parser::parse_simfile;
+use sm_parser::simfile::{ChartDifficulty, ChartTiming, Simfile};
+use std::io::BufReader;
+
+fn parse_string_as_simfile(data: &str) -> Simfile {
+    parse_simfile(&mut BufReader::new(data.as_bytes())).unwrap()
+}
+
+const BASE_SM: &str = "#TITLE:Song;#ARTIST:Someone;#OFFSET:0;#BPMS:0=120;
+#NOTES:dance-single::Easy:3::1000 0000 0000 0000,0100 0000 0000 0000;
+#NOTES:dance-single::Hard:9::1000 0100 0010 0001,0001 0010 0100 1000;";
+
+fn edit(replacements: &[(&str, &str)]) -> Simfile {
+    let mut data = BASE_SM.to_string();
+    for (from, to) in replacements {
+        assert!(data.contains(from));
+        data = data.replace(from, to);
+    }
+    parse_string_as_simfile(&data)
+}
+
+fn hard_conflict(region: ChartRegion) -> Conflict {
+    Conflict::Chart {
+        chart_type: "dance-single".to_string(),
+        difficulty: ChartDifficulty::Hard,
+        author: None,
+        region,
+    }
+}
+
+#[test]
+fn merges_changes_from_both_sides() {
+    let base = parse_string_as_simfile(BASE_SM);
+    let ours = edit(&[
+        ("#TITLE:Song;", "#TITLE:Song (Remix);"),
+        (
+            "Hard:9::1000 0100 0010 0001,",
+            "Hard:9::1000 0000 0100 0000 0000 0000 0000 0000,",
+        ),
+    ]);
+    let theirs = edit(&[
+        ("#ARTIST:Someone;", "#ARTIST:Someone else;"),
+        ("Easy:3::", "Easy:4::"),
+        (
+            "0001 0010 0100 1000",
+            "0001 0010 0100 2000,0000 3000 0000 0000",
+        ),
+    ]);
+
+    let result = merge_simfiles(&base, &ours, &theirs);
+    assert!(result.is_clean());
+
+    let merged = result.simfile;
+    let expected = parse_string_as_simfile(
+        "#NOTES:dance-single::Hard:9::1000 0000 0100 0000 0000 0000 0000 0000,
+        0001 0010 0100 2000,0000 3000 0000 0000;",
+    );
+    assert_eq!(merged.title, Some("Song (Remix)".to_string()));
+    assert_eq!(merged.artist, Some("Someone else".to_string()));
+    assert_eq!(merged.charts.len(), 2);
+    assert_eq!(merged.charts[0].meter, 4);
+    assert_eq!(merged.charts[1].notes(), expected.charts[0].notes());
+}
+
+#[test]
+fn same_change_on_both_sides_is_not_a_conflict() {
+    let base = parse_string_as_simfile(BASE_SM);
+    let ours = edit(&[("#OFFSET:0;", "#OFFSET:-0.05;"), ("Hard:9::", "Hard:10::")]);
+    let theirs = edit(&[("#OFFSET:0;", "#OFFSET:-0.05;"), ("Hard:9::", "Hard:10::")]);
+
+    let result = merge_simfiles(&base, &ours, &theirs);
+    assert!(result.is_clean());
+    assert_eq!(result.simfile.offset, Some(-0.05));
+    assert_eq!(result.simfile.charts[1].meter, 10);
+}
+
+#[test]
+fn reports_conflicting_tags() {
+    let base = parse_string_as_simfile(BASE_SM);
+    let ours = edit(&[
+        ("#TITLE:Song;", "#TITLE:Ours;"),
+        ("#BPMS:0=120;", "#BPMS:0=121;"),
+    ]);
+    let theirs = edit(&[
+        ("#TITLE:Song;", "#TITLE:Theirs;"),
+        ("#BPMS:0=120;", "#BPMS:0=119;"),
+    ]);
+
+    let result = merge_simfiles(&base, &ours, &theirs);
+    assert_eq!(
+        result.conflicts,
+        vec![Conflict::Tag("TITLE"), Conflict::Tag("BPMS")]
+    );
+    assert_eq!(result.simfile.title, Some("Ours".to_string()));
+    assert_eq!(result.simfile.bpms[0].bpm, 121.0);
+}
+
+#[test]
+fn reports_conflicting_chart_regions() {
+    let base = parse_string_as_simfile(BASE_SM);
+    let ours = edit(&[
+        ("Hard:9::", "Hard:10::"),
+        ("1000 0100 0010 0001", "1000 0100 0010 0010"),
+        ("0001 0010 0100 1000", "0001 0010 0100 0100"),
+    ]);
+    let theirs = edit(&[
+        ("Hard:9::", "Hard:11::"),
+        ("0001 0010 0100 1000", "0001 0010 0100 0010"),
+    ]);
+
+    let result = merge_simfiles(&base, &ours, &theirs);
+    assert_eq!(
+        result.conflicts,
+        vec![
+            hard_conflict(ChartRegion::Tag("METER")),
+            hard_conflict(ChartRegion::Measure(1)),
+        ]
+    );
+    assert_eq!(
+        result.conflicts[1].to_string(),
+        "dance-single Hard: measure 1"
+    );
+
+    // Our version of the conflicting measure is kept
+    let chart = &result.simfile.charts[1];
+    assert_eq!(chart.meter, 10);
+    assert_eq!(chart.notes(), ours.charts[1].notes());
+}
+
+#[test]
+fn merges_added_and_removed_charts() {
+    let base = parse_string_as_simfile(BASE_SM);
+    let ours = parse_string_as_simfile(&format!(
+        "{}#NOTES:dance-single:me:Edit:5::1000 0000 0000 0000;",
+        BASE_SM.replace(
+            "#NOTES:dance-single::Easy:3::",
+            "#NOTES:dance-single::Beginner:1::"
+        )
+    ));
+    let theirs = parse_string_as_simfile(&format!(
+        "{}#NOTES:dance-double::Hard:9::10000000;",
+        BASE_SM
+    ));
+
+    let result = merge_simfiles(&base, &ours, &theirs);
+    assert!(result.is_clean());
+
+    let charts: Vec<(&str, ChartDifficulty)> = result
+        .simfile
+        .charts
+        .iter()
+        .map(|c| (c.chart_type.as_ref(), c.difficulty.clone()))
+        .collect();
+    assert_eq!(
+        charts,
+        vec![
+            ("dance-single", ChartDifficulty::Beginner),
+            ("dance-single", ChartDifficulty::Hard),
+            ("dance-single", ChartDifficulty::Edit),
+            ("dance-double", ChartDifficulty::Hard),
+        ]
+    );
+}
+
+#[test]
+fn keeps_chart_that_was_changed_and_removed() {
+    let base = parse_string_as_simfile(BASE_SM);
+    let ours = edit(&[("Easy:3::", "Easy:4::")]);
+    let theirs = edit(&[(
+        "#NOTES:dance-single::Easy:3::1000 0000 0000 0000,0100 0000 0000 0000;",
+        "",
+    )]);
+
+    let result = merge_simfiles(&base, &ours, &theirs);
+    assert_eq!(
+        result.conflicts,
+        vec![Conflict::Chart {
+            chart_type: "dance-single".to_string(),
+            difficulty: ChartDifficulty::Easy,
+            author: None,
+            region: ChartRegion::Chart,
+        }]
+    );
+    assert_eq!(result.simfile.charts.len(), 2);
+    assert_eq!(result.simfile.charts[0].meter, 4);
+
+    // Removing a chart the other side didn't touch is fine
+    let result = merge_simfiles(&base, &base.clone(), &theirs);
+    assert!(result.is_clean());
+    assert_eq!(result.simfile.charts.len(), 1);
+}
+
+#[test]
+fn merges_removed_chart_timing() {
+    let mut base = parse_string_as_simfile(BASE_SM);
+    let mut timing = ChartTiming::from_simfile(&base);
+    timing.bpms[0].bpm = 150.0;
+    base.charts[1].timing = Some(timing);
+
+    // Removing the timing on one side isn't undone by the other side leaving it alone
+    let mut ours = base.clone();
+    ours.charts[1].timing = None;
+    let result = merge_simfiles(&base, &ours, &base.clone());
+    assert!(result.is_clean());
+    assert!(result.simfile.charts[1].timing.is_none());
+
+    // But it conflicts with changing it
+    let mut theirs = base.clone();
+    theirs.charts[1].timing.as_mut().unwrap().bpms[0].bpm = 160.0;
+    let result = merge_simfiles(&base, &ours, &theirs);
+    assert_eq!(
+        result.conflicts,
+        vec![hard_conflict(ChartRegion::Tag("TIMING"))]
+    );
+    assert!(result.simfile.charts[1].timing.is_none());
+
+    let result = merge_simfiles(&base, &theirs, &ours);
+    assert_eq!(
+        result.conflicts,
+        vec![hard_conflict(ChartRegion::Tag("TIMING"))]
+    );
+    assert_eq!(
+        result.simfile.charts[1].timing.as_ref().unwrap().bpms[0].bpm,
+        160.0
+    );
+}
+
+#[test]
+fn reports_holds_split_between_sides() {
+    let base = parse_string_as_simfile(
+        "#BPMS:0=120;#NOTES:dance-single::Hard:9::1000 0000 0000 0000,0000 0000 0000 0000,
+        0000 0000 0000 0000;",
+    );
+    // A hold from the first measure to the third
+    let theirs = parse_string_as_simfile(
+        "#BPMS:0=120;#NOTES:dance-single::Hard:9::2000 0000 0000 0000,0000 0000 0000 0000,
+        3000 0000 0000 0000;",
+    );
+
+    // A note in another lane in the middle of the hold doesn't split it
+    let ours = parse_string_as_simfile(
+        "#BPMS:0=120;#NOTES:dance-single::Hard:9::1000 0000 0000 0000,0001 0000 0000 0000,
+        0000 0000 0000 0000;",
+    );
+    let expected = parse_string_as_simfile(
+        "#NOTES:dance-single::Hard:9::2000 0000 0000 0000,0001 0000 0000 0000,
+        3000 0000 0000 0000;",
+    );
+    let result = merge_simfiles(&base, &ours, &theirs);
+    assert!(result.is_clean());
+    assert_eq!(result.simfile.charts[0].notes(), expected.charts[0].notes());
+
+    // A note in the lane of the hold does, so every measure of the hold is a conflict
+    let ours = parse_string_as_simfile(
+        "#BPMS:0=120;#NOTES:dance-single::Hard:9::1000 0000 0000 0000,1000 0000 0000 0000,
+        0000 0000 0000 0000;",
+    );
+    let result = merge_simfiles(&base, &ours, &theirs);
+    assert_eq!(
+        result.conflicts,
+        vec![
+            hard_conflict(ChartRegion::Measure(0)),
+            hard_conflict(ChartRegion::Measure(1)),
+            hard_conflict(ChartRegion::Measure(2)),
+        ]
+    );
+    assert_eq!(result.simfile.charts[0].notes(), ours.charts[0].notes());
+}
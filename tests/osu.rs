@@ -0,0 +1,199 @@
+extern crate sm_parser;
+
+mod common;
+
+use common::note;
+use sm_parser::osu::{parse_osu, write_osu};
+use sm_parser::parse_simfile;
+use sm_parser::simfile::{ChartDifficulty, NoteType, Simfile};
+use sm_parser::SimfileParseError;
+use std::io::BufReader;
+
+fn parse_string_as_osu(data: &str) -> Result<Simfile, SimfileParseError> {
+    parse_osu(&mut BufReader::new(data.as_bytes()))
+}
+
+fn write_to_string(sim: &Simfile) -> String {
+    let mut data = vec![];
+    write_osu(sim, &sim.charts[0], &mut data).unwrap();
+    String::from_utf8(data).unwrap()
+}
+
+// 120 BPM from 1 second in, then 240 BPM from 5 seconds in (beat 8)
+const TEST_OSU: &str = "osu file format v14
+
+[General]
+AudioFilename: audio.mp3
+AudioLeadIn: 0
+PreviewTime: 12500
+Mode: 3
+
+[Metadata]
+Title:Kimi no Uta
+TitleUnicode:君の歌
+Artist:Someone
+ArtistUnicode:Someone
+Creator:mapper
+Version:Hard
+Source:Some Game
+
+[Difficulty]
+HPDrainRate:8
+CircleSize:4
+OverallDifficulty:8
+
+[Events]
+//Background and Video events
+0,0,\"bg.jpg\",0,0
+
+[TimingPoints]
+1000,500,4,2,0,100,1,0
+3000,-50,4,2,0,100,0,0
+5000,250,4,2,0,100,1,0
+
+[HitObjects]
+64,192,1000,1,0,0:0:0:0:
+192,192,1250,1,0,0:0:0:0:
+320,192,1667,1,0,0:0:0:0:
+448,192,2000,128,0,3000:0:0:0:0:
+64,192,5125,1,0,0:0:0:0:
+";
+
+#[test]
+fn parses_metadata() {
+    let sim = parse_string_as_osu(TEST_OSU).unwrap();
+
+    assert_eq!(sim.title, Some("君の歌".to_string()));
+    assert_eq!(sim.title_translit, Some("Kimi no Uta".to_string()));
+    assert_eq!(sim.artist, Some("Someone".to_string()));
+    assert_eq!(sim.artist_translit, None);
+    assert_eq!(sim.credit, Some("mapper".to_string()));
+    assert_eq!(sim.origin, Some("Some Game".to_string()));
+    assert_eq!(sim.music_path, Some("audio.mp3".to_string()));
+    assert_eq!(sim.background_path, Some("bg.jpg".to_string()));
+    assert_eq!(sim.sample_start, Some(12.5));
+}
+
+#[test]
+fn parses_timing_points() {
+    let sim = parse_string_as_osu(TEST_OSU).unwrap();
+
+    assert_eq!(sim.offset, Some(-1.0));
+    assert_eq!(sim.bpms.len(), 2);
+    assert_eq!(sim.bpms[0].beat, 0.0);
+    assert_eq!(sim.bpms[0].bpm, 120.0);
+    assert_eq!(sim.bpms[1].beat, 8.0);
+    assert_eq!(sim.bpms[1].bpm, 240.0);
+}
+
+#[test]
+fn parses_chart() {
+    let sim = parse_string_as_osu(TEST_OSU).unwrap();
+    let chart = &sim.charts[0];
+
+    assert_eq!(chart.chart_type, "dance-single");
+    assert_eq!(chart.author, Some("mapper".to_string()));
+    assert_eq!(chart.difficulty, ChartDifficulty::Hard);
+    assert_eq!(chart.difficulty_name, "Hard");
+    assert_eq!(chart.meter, 1);
+}
+
+#[test]
+fn quantizes_hit_objects() {
+    let sim = parse_string_as_osu(TEST_OSU).unwrap();
+
+    // 1667ms is rounded from a 12th note after beat 1
+    assert_eq!(
+        sim.charts[0].notes(),
+        vec![
+            note(0, 0, NoteType::Normal),
+            note(24, 1, NoteType::Normal),
+            note(64, 2, NoteType::Normal),
+            note(96, 3, NoteType::HoldHead),
+            note(192, 3, NoteType::HoldOrRollTail),
+            note(408, 0, NoteType::Normal),
+        ]
+    );
+}
+
+#[test]
+fn moves_first_beat_before_notes() {
+    let sim = parse_string_as_osu(
+        "[General]\nMode: 3\n[Difficulty]\nCircleSize:7\n
+        [TimingPoints]\n3000,500,4,2,0,100,1,0\n
+        [HitObjects]\n36,192,500,1,0,0:0:0:0:",
+    )
+    .unwrap();
+
+    // Beat 0 is moved back two measures, from 3 seconds to -1 second
+    assert_eq!(sim.offset, Some(1.0));
+    assert_eq!(sim.charts[0].chart_type, "kb7-single");
+    assert_eq!(sim.charts[0].notes(), vec![note(144, 0, NoteType::Normal)]);
+}
+
+#[test]
+fn rejects_other_modes() {
+    assert_eq!(
+        parse_string_as_osu("[General]\nMode: 0\n[Difficulty]\nCircleSize:4").unwrap_err(),
+        SimfileParseError::NotAnOsuManiaBeatmap
+    );
+    assert_eq!(
+        parse_string_as_osu("[General]\nMode: 3\n[Difficulty]\nCircleSize:11").unwrap_err(),
+        SimfileParseError::UnsupportedKeyCount
+    );
+    assert_eq!(
+        parse_string_as_osu("[General]\nMode: 3\n[Difficulty]\nCircleSize:4").unwrap_err(),
+        SimfileParseError::InvalidOsuData
+    );
+}
+
+#[test]
+fn writes_beatmap() {
+    let sim = parse_string_as_osu(TEST_OSU).unwrap();
+    let data = write_to_string(&sim);
+
+    assert!(data.starts_with("osu file format v14\n"));
+    assert!(data.contains("TitleUnicode:君の歌\n"));
+    assert!(data.contains("Title:Kimi no Uta\n"));
+    assert!(data.contains("PreviewTime: 12500\n"));
+    assert!(data.contains("CircleSize:4\n"));
+    assert!(data.contains("Version:Hard\n"));
+    assert!(data.contains("0,0,\"bg.jpg\",0,0\n"));
+    assert!(data.contains("[TimingPoints]\n1000,500,4,0,0,100,1,0\n5000,250,4,0,0,100,1,0\n"));
+    assert!(data.contains("448,192,2000,128,0,3000:0:0:0:0:\n"));
+}
+
+#[test]
+fn round_trips_notes() {
+    let sim = parse_string_as_osu(TEST_OSU).unwrap();
+    let written = parse_string_as_osu(&write_to_string(&sim)).unwrap();
+
+    assert_eq!(written.offset, sim.offset);
+    assert_eq!(written.bpms.len(), sim.bpms.len());
+    assert_eq!(written.charts[0].notes(), sim.charts[0].notes());
+}
+
+#[test]
+fn writes_stops_as_timing_points() {
+    let sim = parse_simfile(&mut BufReader::new(
+        "#OFFSET:0;#BPMS:0=120;#STOPS:4=0.5;
+        #NOTES:dance-single::Hard:5::
+        1000
+        0100
+        0010
+        0001
+        ,
+        4000
+        0000
+        3000
+        0000
+        ;"
+        .as_bytes(),
+    ))
+    .unwrap();
+    let data = write_to_string(&sim);
+
+    assert!(data.contains("[TimingPoints]\n0,500,4,0,0,100,1,0\n2500,500,4,0,0,100,1,0\n"));
+    // The roll is written as a hold, which ends half a second later because of the stop
+    assert!(data.contains("64,192,2000,128,0,3500:0:0:0:0:\n"));
+}
@@ -0,0 +1,107 @@
+extern crate sm_parser;
+
+use sm_parser::{load_simfile, parse_any, SimfileFormat, SimfileParseError};
+use std::io::BufReader;
+use std::path::PathBuf;
+
+fn detect(data: &str) -> Option<SimfileFormat> {
+    SimfileFormat::detect(data.as_bytes())
+}
+
+#[test]
+fn picks_format_from_extension() {
+    assert_eq!(SimfileFormat::from_extension("sm"), Some(SimfileFormat::Sm));
+    assert_eq!(
+        SimfileFormat::from_extension("SSC"),
+        Some(SimfileFormat::Ssc)
+    );
+    assert_eq!(
+        SimfileFormat::from_extension("bme"),
+        Some(SimfileFormat::Bms)
+    );
+    assert_eq!(
+        SimfileFormat::from_extension("ucs"),
+        Some(SimfileFormat::Ucs)
+    );
+    assert_eq!(SimfileFormat::from_extension("txt"), None);
+}
+
+#[test]
+fn detects_format_from_contents() {
+    assert_eq!(detect("#TITLE:a;\n#NOTES:::::;"), Some(SimfileFormat::Sm));
+    assert_eq!(
+        detect("#TITLE:a;\n#NOTEDATA:;\n#NOTES:;"),
+        Some(SimfileFormat::Ssc)
+    );
+    assert_eq!(
+        detect("#ROWSPERBEAT:48;\n#NOTES:::::;"),
+        Some(SimfileFormat::Sma)
+    );
+    assert_eq!(
+        detect("#BPM:120;\n#SINGLE:BASIC:3:2468;"),
+        Some(SimfileFormat::Dwi)
+    );
+    assert_eq!(detect("#PLAYER 1\n#00111:01"), Some(SimfileFormat::Bms));
+    assert_eq!(detect("#00111:0101"), Some(SimfileFormat::Bms));
+    assert_eq!(
+        detect("\u{feff}osu file format v14\n"),
+        Some(SimfileFormat::Osu)
+    );
+    assert_eq!(
+        detect("AudioFile: a.mp3\nMode: Keys4\n"),
+        Some(SimfileFormat::Qua)
+    );
+    assert_eq!(
+        detect("title=a\n--\n1000|00|--\n--"),
+        Some(SimfileFormat::Ksh)
+    );
+    assert_eq!(
+        detect(":Format=1\n:Mode=Single\nX...."),
+        Some(SimfileFormat::Ucs)
+    );
+    assert_eq!(detect("Hello there"), None);
+}
+
+#[test]
+fn parses_detected_format() {
+    let sim = parse_any(
+        &mut BufReader::new(":Mode=Single\n:BPM=120\n:Delay=0\n:Split=1\nX....".as_bytes()),
+        None,
+    )
+    .unwrap();
+
+    assert_eq!(sim.charts[0].chart_type, "pump-single");
+}
+
+#[test]
+fn prefers_hint_over_contents() {
+    // A .sma file without any of its own tags looks like a .sm file
+    let sim = parse_any(
+        &mut BufReader::new("#BPMS:0=120;#TICKCOUNT:0=2;".as_bytes()),
+        Some(SimfileFormat::Sma),
+    )
+    .unwrap();
+
+    assert_eq!(sim.tick_counts.len(), 1);
+}
+
+#[test]
+fn fails_on_unknown_format() {
+    assert_eq!(
+        parse_any(&mut BufReader::new("Hello there".as_bytes()), None).unwrap_err(),
+        SimfileParseError::UnknownSimfileFormat
+    );
+}
+
+#[test]
+fn loads_simfile_from_path() {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    path.push("example_files/goin_under.sm");
+    let sim = load_simfile(&path).unwrap();
+
+    assert_eq!(sim.title, Some("Goin' Under".to_string()));
+    assert_eq!(
+        load_simfile(path.with_file_name("missing.sm")).unwrap_err(),
+        SimfileParseError::BufReadError
+    );
+}
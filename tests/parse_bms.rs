@@ -0,0 +1,295 @@
+extern crate sm_parser;
+
+mod common;
+
+use common::note;
+use sm_parser::bms::{parse_bms, parse_bms_with_random};
+use sm_parser::simfile::{ChartDifficulty, NoteType, Simfile};
+use sm_parser::SimfileParseError;
+use std::io::BufReader;
+
+fn parse_string_as_bms(data: &str) -> Result<Simfile, SimfileParseError> {
+    parse_bms(&mut BufReader::new(data.as_bytes()))
+}
+
+const TEST_BMS: &str = "
+*---------------------- HEADER FIELD
+#PLAYER 1
+#TITLE Test Song
+#SUBTITLE [Another]
+#ARTIST Someone
+#GENRE Techno
+#BPM 150
+#PLAYLEVEL 7
+#DIFFICULTY 4
+#STAGEFILE title.bmp
+#WAV01 kick.wav
+#WAV0A snare.wav
+#WAV02 hat.wav
+#BPM01 300.5
+#STOP01 96
+
+*---------------------- MAIN DATA FIELD
+#00001:0102
+#00011:01000000
+#00016:00000100
+#00112:0A0A
+#00208:0001
+#00209:01
+#00302:0.5
+#00418:01
+#00519:01
+";
+
+#[test]
+fn parses_header() {
+    let sim = parse_string_as_bms(TEST_BMS).unwrap();
+
+    assert_eq!(sim.title, Some("Test Song".to_string()));
+    assert_eq!(sim.subtitle, Some("[Another]".to_string()));
+    assert_eq!(sim.artist, Some("Someone".to_string()));
+    assert_eq!(sim.genre, Some("Techno".to_string()));
+    assert_eq!(sim.background_path, Some("title.bmp".to_string()));
+    assert_eq!(sim.keysounds.len(), 11);
+    assert_eq!(sim.keysounds[0], "");
+    assert_eq!(sim.keysounds[1], "kick.wav");
+    assert_eq!(sim.keysounds[2], "hat.wav");
+    assert_eq!(sim.keysounds[10], "snare.wav");
+}
+
+#[test]
+fn parses_chart() {
+    let sim = parse_string_as_bms(TEST_BMS).unwrap();
+    let chart = &sim.charts[0];
+
+    assert_eq!(chart.chart_type, "beat-single7");
+    assert_eq!(chart.meter, 7);
+    assert_eq!(chart.difficulty, ChartDifficulty::Hard);
+    assert_eq!(chart.difficulty_name, "4");
+}
+
+#[test]
+fn maps_channels_to_columns() {
+    let sim = parse_string_as_bms(TEST_BMS).unwrap();
+    let notes = sim.charts[0].notes();
+
+    // Measure 3 is half as long, so measure 4 starts on beat 14
+    assert_eq!(
+        notes,
+        vec![
+            note(0, 1, NoteType::Normal),
+            note(96, 0, NoteType::Normal),
+            note(192, 2, NoteType::Normal),
+            note(288, 2, NoteType::Normal),
+            note(672, 6, NoteType::Normal),
+            note(864, 7, NoteType::Normal),
+        ]
+    );
+}
+
+#[test]
+fn parses_bpms_and_stops() {
+    let sim = parse_string_as_bms(TEST_BMS).unwrap();
+
+    assert_eq!(sim.bpms.len(), 2);
+    assert_eq!(sim.bpms[0].beat, 0.0);
+    assert_eq!(sim.bpms[0].bpm, 150.0);
+    assert_eq!(sim.bpms[1].beat, 10.0);
+    assert_eq!(sim.bpms[1].bpm, 300.5);
+
+    // 96 192nds of a measure are 2 beats, at 150 BPM
+    assert_eq!(sim.stops.len(), 1);
+    assert_eq!(sim.stops[0].beat, 8.0);
+    assert_eq!(sim.stops[0].time, 2.0 * 60.0 / 150.0);
+}
+
+#[test]
+fn parses_hex_bpm_changes() {
+    let sim = parse_string_as_bms("#BPM 120\n#00103:00B4").unwrap();
+
+    assert_eq!(sim.bpms[1].beat, 6.0);
+    assert_eq!(sim.bpms[1].bpm, 180.0);
+}
+
+#[test]
+fn uses_default_bpm() {
+    let sim = parse_string_as_bms("#00111:01").unwrap();
+
+    assert_eq!(sim.bpms[0].bpm, 130.0);
+}
+
+#[test]
+fn detects_chart_type() {
+    let chart_type = |data: &str| {
+        parse_string_as_bms(data).unwrap().charts[0]
+            .chart_type
+            .clone()
+    };
+
+    assert_eq!(chart_type("#00111:01\n#00115:01"), "beat-single5");
+    assert_eq!(chart_type("#00111:01\n#00116:01"), "beat-single5");
+    assert_eq!(chart_type("#00111:01\n#00119:01"), "kb7-single");
+    assert_eq!(chart_type("#00116:01\n#00119:01"), "beat-single7");
+    assert_eq!(chart_type("#00111:01\n#00121:01"), "beat-double5");
+    assert_eq!(chart_type("#00111:01\n#00228:01"), "beat-double7");
+}
+
+#[test]
+fn maps_double_columns() {
+    let sim = parse_string_as_bms("#00016:01\n#00111:01\n#00221:01\n#00326:01").unwrap();
+    let notes = sim.charts[0].notes();
+
+    assert_eq!(notes[0], note(0, 0, NoteType::Normal));
+    assert_eq!(notes[1], note(192, 1, NoteType::Normal));
+    assert_eq!(notes[2], note(384, 6, NoteType::Normal));
+    assert_eq!(notes[3], note(576, 11, NoteType::Normal));
+}
+
+#[test]
+fn parses_kb7_columns() {
+    let sim = parse_string_as_bms("#00111:01\n#00119:01").unwrap();
+    let notes = sim.charts[0].notes();
+
+    assert_eq!(notes[0], note(192, 0, NoteType::Normal));
+    assert_eq!(notes[1], note(192, 6, NoteType::Normal));
+}
+
+#[test]
+fn parses_long_note_channels() {
+    let sim = parse_string_as_bms("#00151:0101\n#00152:01\n#00111:00000001").unwrap();
+    let notes = sim.charts[0].notes();
+
+    assert_eq!(
+        notes,
+        vec![
+            note(192, 1, NoteType::HoldHead),
+            note(192, 2, NoteType::Normal),
+            note(288, 1, NoteType::HoldOrRollTail),
+            note(336, 1, NoteType::Normal),
+        ]
+    );
+}
+
+#[test]
+fn parses_ln_object() {
+    let sim = parse_string_as_bms("#LNOBJ ZZ\n#00111:01ZZ").unwrap();
+    let notes = sim.charts[0].notes();
+
+    assert_eq!(
+        notes,
+        vec![
+            note(192, 1, NoteType::HoldHead),
+            note(288, 1, NoteType::HoldOrRollTail),
+        ]
+    );
+}
+
+#[test]
+fn only_turns_normal_notes_into_ln_object_holds() {
+    let sim =
+        parse_string_as_bms("#LNOBJ ZZ\n#00151:0101\n#00111:000000ZZ\n#001D2:01\n#00112:00ZZ")
+            .unwrap();
+    let notes = sim.charts[0].notes();
+
+    assert_eq!(
+        notes,
+        vec![
+            note(192, 1, NoteType::HoldHead),
+            note(192, 2, NoteType::Mine),
+            note(288, 1, NoteType::HoldOrRollTail),
+        ]
+    );
+}
+
+#[test]
+fn parses_mines() {
+    let sim = parse_string_as_bms("#00111:01\n#001D2:01").unwrap();
+    let notes = sim.charts[0].notes();
+
+    assert_eq!(notes[1], note(192, 2, NoteType::Mine));
+}
+
+const RANDOM_BMS: &str = "
+#00111:01
+#RANDOM 2
+#IF 1
+#00112:01
+#ENDIF
+#IF 2
+#00113:01
+#ENDIF
+#ENDRANDOM
+";
+
+#[test]
+fn uses_first_random_branch() {
+    let sim = parse_string_as_bms(RANDOM_BMS).unwrap();
+    let notes = sim.charts[0].notes();
+
+    assert_eq!(notes.len(), 2);
+    assert_eq!(notes[1].column, 2);
+}
+
+#[test]
+fn uses_random_branch() {
+    let mut max_values = vec![];
+    let sim = parse_bms_with_random(&mut BufReader::new(RANDOM_BMS.as_bytes()), |max| {
+        max_values.push(max);
+        2
+    })
+    .unwrap();
+    let notes = sim.charts[0].notes();
+
+    assert_eq!(max_values, vec![2]);
+    assert_eq!(notes.len(), 2);
+    assert_eq!(notes[1].column, 3);
+}
+
+#[test]
+fn only_ends_random_branches_with_endif() {
+    let sim =
+        parse_string_as_bms("#RANDOM 2\n#IF 2\n#00112:01\n#END\n#00113:01\n#ENDIF\n#00111:01")
+            .unwrap();
+    let notes = sim.charts[0].notes();
+
+    assert_eq!(notes.len(), 1);
+    assert_eq!(notes[0].column, 1);
+}
+
+#[test]
+fn handles_nan_measure_lengths() {
+    let sim = parse_string_as_bms("#00102:nan\n#00111:0101\n#00211:01").unwrap();
+
+    assert_eq!(sim.charts.len(), 1);
+}
+
+#[test]
+fn has_no_chart_without_notes() {
+    let sim = parse_string_as_bms("#TITLE Nothing\n#BPM 120").unwrap();
+
+    assert!(sim.charts.is_empty());
+}
+
+#[test]
+fn ignores_non_ascii_header_keys() {
+    let sim = parse_string_as_bms("#TITLE Song\n#00\u{c9}1:00\n#00111:01").unwrap();
+
+    assert_eq!(sim.title, Some("Song".to_string()));
+    assert_eq!(sim.charts[0].notes().len(), 1);
+}
+
+#[test]
+fn fails_on_invalid_data() {
+    assert_eq!(
+        parse_string_as_bms("#00111:010").unwrap_err(),
+        SimfileParseError::InvalidBmsData
+    );
+    assert_eq!(
+        parse_string_as_bms("#BPM fast").unwrap_err(),
+        SimfileParseError::FailedToParseBPMs
+    );
+    assert_eq!(
+        parse_string_as_bms("#00108:01").unwrap_err(),
+        SimfileParseError::FailedToParseBPMs
+    );
+}
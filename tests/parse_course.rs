@@ -0,0 +1,185 @@
+extern crate sm_parser;
+
+use sm_parser::course::{parse_course, Attack, Course, CourseChart, CourseSong, LoadedSong};
+use sm_parser::parse_simfile;
+use sm_parser::simfile::{ChartDifficulty, Simfile};
+use sm_parser::SimfileParseError;
+use std::io::BufReader;
+
+fn parse_string_as_course(data: &str) -> Result<Course, SimfileParseError> {
+    parse_course(&mut BufReader::new(data.as_bytes()))
+}
+
+fn parse_string_as_simfile(data: &str) -> Simfile {
+    parse_simfile(&mut BufReader::new(data.as_bytes())).unwrap()
+}
+
+const TEST_CRS: &str = "#COURSE:Event Night;
+#SCRIPTER:organizer;
+#BANNER:banner.png;
+#REPEAT:NO;
+#LIVES:4;
+#METER:Medium:8;
+#METER:Hard:12;
+#SONG:Pack A/First Song:Hard:1.5x,noshowcourse;
+#GAINSECONDS:10.5;
+#MODS:TIME=10:LEN=5:MODS=drunk,2x:TIME=20:END=30:MODS=dark;
+#SONG:Pack B/*:5..7;
+#SONG:*;
+";
+
+#[test]
+fn parses_course_header() {
+    let course = parse_string_as_course(TEST_CRS).unwrap();
+
+    assert_eq!(course.name, Some("Event Night".to_string()));
+    assert_eq!(course.scripter, Some("organizer".to_string()));
+    assert_eq!(course.banner_path, Some("banner.png".to_string()));
+    assert!(!course.repeat);
+    assert_eq!(course.lives, Some(4));
+    assert_eq!(course.meter(ChartDifficulty::Medium), Some(8));
+    assert_eq!(course.meter(ChartDifficulty::Hard), Some(12));
+    assert_eq!(course.meter(ChartDifficulty::Easy), None);
+}
+
+#[test]
+fn parses_song_entries() {
+    let course = parse_string_as_course(TEST_CRS).unwrap();
+    assert_eq!(course.entries.len(), 3);
+
+    let entry = &course.entries[0];
+    assert_eq!(
+        entry.song,
+        CourseSong::Fixed("Pack A/First Song".to_string())
+    );
+    assert_eq!(
+        entry.chart,
+        Some(CourseChart::Difficulty(ChartDifficulty::Hard))
+    );
+    assert_eq!(entry.modifiers, vec!["1.5x", "noshowcourse"]);
+    assert_eq!(entry.gain_seconds, 0.0);
+
+    let entry = &course.entries[1];
+    assert_eq!(entry.song, CourseSong::RandomInGroup("Pack B".to_string()));
+    assert_eq!(entry.chart, Some(CourseChart::MeterRange(5, 7)));
+    assert_eq!(entry.gain_seconds, 10.5);
+
+    let entry = &course.entries[2];
+    assert_eq!(entry.song, CourseSong::Random);
+    assert_eq!(entry.chart, None);
+    assert!(entry.modifiers.is_empty());
+    assert_eq!(entry.gain_seconds, 10.5);
+}
+
+#[test]
+fn parses_attacks() {
+    let course = parse_string_as_course(TEST_CRS).unwrap();
+
+    // The attacks belong to the song after them
+    assert!(course.entries[0].attacks.is_empty());
+    assert_eq!(
+        course.entries[1].attacks,
+        vec![
+            Attack {
+                start: 10.0,
+                length: 5.0,
+                modifiers: vec!["drunk".to_string(), "2x".to_string()],
+            },
+            Attack {
+                start: 20.0,
+                length: 10.0,
+                modifiers: vec!["dark".to_string()],
+            },
+        ]
+    );
+    assert!(course.entries[2].attacks.is_empty());
+}
+
+#[test]
+fn parses_single_meter_as_medium() {
+    let course = parse_string_as_course("#METER:6;").unwrap();
+
+    assert_eq!(course.meters, vec![(ChartDifficulty::Medium, 6)]);
+}
+
+#[test]
+fn parsing_invalid_course_returns_error() {
+    assert_eq!(
+        parse_string_as_course("#LIVES:many;").unwrap_err(),
+        SimfileParseError::InvalidCourseData
+    );
+    assert_eq!(
+        parse_string_as_course("#METER:Insane:5;").unwrap_err(),
+        SimfileParseError::InvalidCourseData
+    );
+    assert_eq!(
+        parse_string_as_course("#SONG::Hard;").unwrap_err(),
+        SimfileParseError::InvalidCourseData
+    );
+    assert_eq!(
+        parse_string_as_course("#SONG:*:Insane;").unwrap_err(),
+        SimfileParseError::InvalidCourseData
+    );
+    assert_eq!(
+        parse_string_as_course("#MODS:TIME=1:SPEED=2;").unwrap_err(),
+        SimfileParseError::InvalidCourseData
+    );
+}
+
+#[test]
+fn finds_songs_for_entries() {
+    let course = parse_string_as_course(TEST_CRS).unwrap();
+    let first = parse_string_as_simfile("#TITLE:First Song;");
+    let second = parse_string_as_simfile("#TITLE:Second Song;");
+    let third = parse_string_as_simfile("#TITLE:Third Song;");
+    let songs = vec![
+        LoadedSong {
+            group: "pack a",
+            folder: "first song",
+            simfile: &first,
+        },
+        LoadedSong {
+            group: "Pack B",
+            folder: "Second",
+            simfile: &second,
+        },
+        LoadedSong {
+            group: "Pack B",
+            folder: "Third",
+            simfile: &third,
+        },
+    ];
+
+    let found = course.entries[0].find_songs(&songs);
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].folder, "first song");
+    assert_eq!(course.entries[1].find_songs(&songs).len(), 2);
+    assert_eq!(course.entries[2].find_songs(&songs).len(), 3);
+    assert!(course.missing_songs(&songs).is_empty());
+    assert_eq!(course.missing_songs(&songs[1..]).len(), 1);
+}
+
+#[test]
+fn finds_charts_for_entries() {
+    let course = parse_string_as_course(TEST_CRS).unwrap();
+    let sim = parse_string_as_simfile(
+        "#NOTES:dance-single::Medium:6::0000;
+        #NOTES:dance-single::Hard:9::0000;
+        #NOTES:dance-double::Hard:9::00000000;",
+    );
+
+    let charts = course.entries[0].find_charts(&sim, "dance-single", ChartDifficulty::Easy);
+    assert_eq!(charts.len(), 1);
+    assert_eq!(charts[0].meter, 9);
+
+    let charts = course.entries[1].find_charts(&sim, "dance-single", ChartDifficulty::Easy);
+    assert_eq!(charts.len(), 1);
+    assert_eq!(charts[0].meter, 6);
+
+    // Entries without a chart use the difficulty the course is played on
+    let charts = course.entries[2].find_charts(&sim, "dance-double", ChartDifficulty::Hard);
+    assert_eq!(charts.len(), 1);
+    assert!(course.entries[2]
+        .find_charts(&sim, "dance-double", ChartDifficulty::Medium)
+        .is_empty());
+}
@@ -0,0 +1,228 @@
+extern crate sm_parser;
+
+mod common;
+
+use common::note;
+use sm_parser::dwi::parse_dwi;
+use sm_parser::simfile::{ChartDifficulty, DisplayBPM, NoteType, Simfile};
+use sm_parser::SimfileParseError;
+use std::io::BufReader;
+
+fn parse_string_as_dwi(data: &str) -> Result<Simfile, SimfileParseError> {
+    parse_dwi(&mut BufReader::new(data.as_bytes()))
+}
+
+const TEST_DWI: &str = "
+#TITLE:Butterfly (Upswing Mix);
+#ARTIST:smile.dk;
+#GENRE:Eurobeat;
+#CDTITLE:cdtitle.png;
+#FILE:butterfly.mp3;
+#BPM:135.00;
+#GAP:-150;
+#CHANGEBPM:64=270.000,128=135.000;
+#FREEZE:32=500;
+#DISPLAYBPM:135..270;
+#SAMPLESTART:1:02.5;
+#SAMPLELENGTH:10;
+#SINGLE:BASIC:3:
+2468
+(2468)
+00008!8000
+<28>000000;
+#DOUBLE:ANOTHER:7:24680000:00002468;
+#COUPLE:MANIAC:8:2000:0008;
+#SOLO:SMANIAC:10:CD7!8000
+0007;
+";
+
+#[test]
+fn parses_dwi_header() {
+    let sim = parse_string_as_dwi(TEST_DWI).unwrap();
+    assert_eq!(sim.title, Some("Butterfly".to_string()));
+    assert_eq!(sim.subtitle, Some("(Upswing Mix)".to_string()));
+    assert_eq!(sim.artist, Some("smile.dk".to_string()));
+    assert_eq!(sim.genre, Some("Eurobeat".to_string()));
+    assert_eq!(sim.cd_title_path, Some("cdtitle.png".to_string()));
+    assert_eq!(sim.music_path, Some("butterfly.mp3".to_string()));
+    assert_eq!(sim.display_bpm, Some(DisplayBPM::Range(135.0, 270.0)));
+    assert_eq!(sim.sample_start, Some(62.5));
+    assert_eq!(sim.sample_length, Some(10.0));
+}
+
+#[test]
+fn parses_dwi_gap_as_offset() {
+    let sim = parse_string_as_dwi(TEST_DWI).unwrap();
+    assert_eq!(sim.offset, Some(0.15));
+}
+
+#[test]
+fn parses_dwi_bpm_changes() {
+    let sim = parse_string_as_dwi(TEST_DWI).unwrap();
+    assert_eq!(sim.bpms.len(), 3);
+    assert_eq!(sim.bpms[0].beat, 0.0);
+    assert_eq!(sim.bpms[0].bpm, 135.0);
+    assert_eq!(sim.bpms[1].beat, 16.0);
+    assert_eq!(sim.bpms[1].bpm, 270.0);
+    assert_eq!(sim.bpms[2].beat, 32.0);
+    assert_eq!(sim.bpms[2].bpm, 135.0);
+}
+
+#[test]
+fn parses_dwi_freezes() {
+    let sim = parse_string_as_dwi(TEST_DWI).unwrap();
+    assert_eq!(sim.stops.len(), 1);
+    assert_eq!(sim.stops[0].beat, 8.0);
+    assert_eq!(sim.stops[0].time, 0.5);
+}
+
+#[test]
+fn parses_dwi_chart_header() {
+    let sim = parse_string_as_dwi(TEST_DWI).unwrap();
+    assert_eq!(sim.charts.len(), 4);
+
+    assert_eq!(sim.charts[0].chart_type, "dance-single");
+    assert_eq!(sim.charts[0].difficulty, ChartDifficulty::Easy);
+    assert_eq!(sim.charts[0].difficulty_name, "BASIC");
+    assert_eq!(sim.charts[0].meter, 3);
+
+    assert_eq!(sim.charts[1].chart_type, "dance-double");
+    assert_eq!(sim.charts[1].difficulty, ChartDifficulty::Medium);
+    assert_eq!(sim.charts[2].chart_type, "dance-couple");
+    assert_eq!(sim.charts[2].difficulty, ChartDifficulty::Hard);
+    assert_eq!(sim.charts[3].chart_type, "dance-solo");
+    assert_eq!(sim.charts[3].difficulty, ChartDifficulty::Challenge);
+}
+
+#[test]
+fn parses_dwi_8th_and_16th_notes() {
+    let sim = parse_string_as_dwi(TEST_DWI).unwrap();
+    let notes = sim.charts[0].notes();
+
+    assert_eq!(
+        notes[..8].to_vec(),
+        vec![
+            note(0, 1, NoteType::Normal),
+            note(24, 0, NoteType::Normal),
+            note(48, 3, NoteType::Normal),
+            note(72, 2, NoteType::Normal),
+            note(96, 1, NoteType::Normal),
+            note(108, 0, NoteType::Normal),
+            note(120, 3, NoteType::Normal),
+            note(132, 2, NoteType::Normal),
+        ]
+    );
+}
+
+#[test]
+fn parses_dwi_holds_and_jumps() {
+    let sim = parse_string_as_dwi(TEST_DWI).unwrap();
+    let notes = sim.charts[0].notes();
+
+    // The hold ends at the jump, which replaces the up arrow of the jump with the tail
+    assert_eq!(
+        notes[8..].to_vec(),
+        vec![
+            note(240, 2, NoteType::HoldHead),
+            note(336, 1, NoteType::Normal),
+            note(336, 2, NoteType::HoldOrRollTail),
+        ]
+    );
+}
+
+#[test]
+fn parses_other_dwi_brackets() {
+    let sim = parse_string_as_dwi("#SINGLE:BASIC:1:[28]{46}`88'2;").unwrap();
+    let notes = sim.charts[0].notes();
+
+    assert_eq!(
+        notes,
+        vec![
+            note(0, 1, NoteType::Normal),
+            note(8, 2, NoteType::Normal),
+            note(16, 0, NoteType::Normal),
+            note(19, 3, NoteType::Normal),
+            note(22, 2, NoteType::Normal),
+            note(23, 2, NoteType::Normal),
+            note(24, 1, NoteType::Normal),
+        ]
+    );
+}
+
+#[test]
+fn parses_dwi_doubles_on_both_pads() {
+    let sim = parse_string_as_dwi(TEST_DWI).unwrap();
+
+    assert_eq!(sim.charts[1].note_data.len(), 1);
+    let notes = sim.charts[1].notes();
+    assert_eq!(notes.len(), 8);
+    assert_eq!(notes[0], note(0, 1, NoteType::Normal));
+    assert_eq!(notes[3], note(72, 2, NoteType::Normal));
+    assert_eq!(notes[4], note(96, 5, NoteType::Normal));
+    assert_eq!(notes[7], note(168, 6, NoteType::Normal));
+
+    let notes = sim.charts[2].notes();
+    assert_eq!(
+        notes,
+        vec![note(0, 1, NoteType::Normal), note(72, 6, NoteType::Normal)]
+    );
+}
+
+#[test]
+fn parses_dwi_solo_panels() {
+    let sim = parse_string_as_dwi(TEST_DWI).unwrap();
+    let notes = sim.charts[3].notes();
+
+    assert_eq!(
+        notes,
+        vec![
+            note(0, 1, NoteType::Normal),
+            note(24, 4, NoteType::Normal),
+            note(48, 0, NoteType::Normal),
+            note(48, 3, NoteType::HoldHead),
+            note(216, 0, NoteType::Normal),
+            note(216, 3, NoteType::HoldOrRollTail),
+        ]
+    );
+}
+
+#[test]
+fn turns_unended_dwi_holds_into_notes() {
+    let sim = parse_string_as_dwi("#SINGLE:BASIC:1:8!80000000;").unwrap();
+    assert_eq!(sim.charts[0].notes(), vec![note(0, 2, NoteType::Normal)]);
+}
+
+#[test]
+fn parses_single_and_random_dwi_display_bpm() {
+    let sim = parse_string_as_dwi("#DISPLAYBPM:150;").unwrap();
+    assert_eq!(sim.display_bpm, Some(DisplayBPM::Single(150.0)));
+    let sim = parse_string_as_dwi("#DISPLAYBPM:*;").unwrap();
+    assert_eq!(sim.display_bpm, Some(DisplayBPM::Random));
+}
+
+#[test]
+fn parsing_dwi_with_invalid_step_returns_error() {
+    let sim = parse_string_as_dwi("#SINGLE:BASIC:1:2468Z;");
+    assert_eq!(sim.err().unwrap(), SimfileParseError::InvalidDwiNoteData);
+}
+
+#[test]
+fn parsing_dwi_double_with_one_pad_returns_error() {
+    let sim = parse_string_as_dwi("#DOUBLE:BASIC:1:2468;");
+    assert_eq!(sim.err().unwrap(), SimfileParseError::InvalidChartFormat);
+}
+
+#[test]
+fn parsing_dwi_with_unknown_difficulty_returns_error() {
+    let sim = parse_string_as_dwi("#SINGLE:EXTREME:1:2468;");
+    assert_eq!(
+        sim.err().unwrap(),
+        SimfileParseError::UnknownChartDifficulty
+    );
+}
+
+#[test]
+fn parsing_dwi_with_invalid_bpm_returns_error() {
+    let sim = parse_string_as_dwi("#BPM:fast;");
+    assert_eq!(sim.err().unwrap(), SimfileParseError::FailedToParseBPMs);
+}
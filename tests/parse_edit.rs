@@ -0,0 +1,101 @@
+extern crate sm_parser;
+
+use sm_parser::edit::{merge_edits, parse_edit, EditFile};
+use sm_parser::parse_simfile;
+use sm_parser::simfile::{ChartDifficulty, NoteType, Simfile};
+use sm_parser::SimfileParseError;
+use std::io::BufReader;
+
+fn parse_string_as_edit(data: &str) -> Result<EditFile, SimfileParseError> {
+    parse_edit(&mut BufReader::new(data.as_bytes()))
+}
+
+fn parse_string_as_simfile(data: &str) -> Simfile {
+    parse_simfile(&mut BufReader::new(data.as_bytes())).unwrap()
+}
+
+const TEST_EDIT: &str = "#SONG:Pack A/First Song;
+#NOTES:
+     dance-single:
+     My Edit:
+     Challenge:
+     10:
+     0,0,0,0,0:
+1000
+0100
+0010
+0001
+;
+#NOTES:dance-double:Other Edit:Edit:4::00000001;
+";
+
+#[test]
+fn parses_edit() {
+    let edit = parse_string_as_edit(TEST_EDIT).unwrap();
+
+    assert_eq!(edit.song, "Pack A/First Song");
+    assert_eq!(edit.charts.len(), 2);
+
+    let chart = &edit.charts[0];
+    assert_eq!(chart.chart_type, "dance-single");
+    assert_eq!(chart.meter, 10);
+    assert_eq!(chart.note_data[0][0], NoteType::Normal);
+    assert_eq!(edit.charts[1].chart_type, "dance-double");
+}
+
+#[test]
+fn forces_edit_difficulty() {
+    let edit = parse_string_as_edit(TEST_EDIT).unwrap();
+
+    assert_eq!(edit.charts[0].difficulty, ChartDifficulty::Edit);
+    assert_eq!(edit.charts[1].difficulty, ChartDifficulty::Edit);
+}
+
+#[test]
+fn parsing_edit_without_song_returns_error() {
+    assert_eq!(
+        parse_string_as_edit("#NOTES:dance-single::Edit:4::0000;").unwrap_err(),
+        SimfileParseError::MissingEditSong
+    );
+    assert_eq!(
+        parse_string_as_edit("#SONG:a;#NOTES:dance-single;").unwrap_err(),
+        SimfileParseError::InvalidChartFormat
+    );
+}
+
+#[test]
+fn matches_songs_by_folder_or_title() {
+    let edit = parse_string_as_edit(TEST_EDIT).unwrap();
+    let sim = parse_string_as_simfile("#TITLE:First Song;");
+
+    assert!(edit.is_for("pack a", "first song", &sim));
+    assert!(edit.is_for("Pack A", "001", &sim));
+    assert!(!edit.is_for("Pack B", "First Song", &sim));
+
+    let edit = parse_string_as_edit("#SONG:first song;").unwrap();
+    assert!(edit.is_for("Pack B", "001", &sim));
+}
+
+#[test]
+fn merges_edits_into_songs() {
+    let edits = vec![
+        parse_string_as_edit(TEST_EDIT).unwrap(),
+        parse_string_as_edit("#SONG:Pack A/Missing Song;").unwrap(),
+    ];
+    let mut first = parse_string_as_simfile("#TITLE:First Song;#NOTES:dance-single::Hard:9::0000;");
+    let mut second = parse_string_as_simfile("#TITLE:Second Song;");
+
+    let missing = merge_edits(
+        &edits,
+        vec![
+            ("Pack A", "Second Song", &mut second),
+            ("Pack A", "First Song", &mut first),
+        ],
+    );
+
+    assert_eq!(missing.len(), 1);
+    assert_eq!(missing[0].song, "Pack A/Missing Song");
+    assert_eq!(first.charts.len(), 3);
+    assert_eq!(first.charts[2].difficulty, ChartDifficulty::Edit);
+    assert!(second.charts.is_empty());
+}
@@ -0,0 +1,172 @@
+extern crate sm_parser;
+
+mod common;
+
+use common::note;
+use sm_parser::ksh::parse_ksh;
+use sm_parser::simfile::{ChartDifficulty, DisplayBPM, LaserPoint, LaserSide, NoteType, Simfile};
+use sm_parser::SimfileParseError;
+use std::io::BufReader;
+
+fn parse_string_as_ksh(data: &str) -> Result<Simfile, SimfileParseError> {
+    parse_ksh(&mut BufReader::new(data.as_bytes()))
+}
+
+fn point(row: u32, position: f32) -> LaserPoint {
+    LaserPoint { row, position }
+}
+
+const TEST_KSH: &str = "\u{feff}title=Test Song
+artist=Someone
+effect=mapper
+jacket=jacket.png
+illustrator=artist
+difficulty=infinite
+level=18
+t=120-240
+m=song.ogg;song_f.ogg
+mvol=75
+o=500
+po=30000
+plength=15000
+ver=167
+--
+beat=4/4
+t=120
+1000|00|0-
+0100|02|:-
+0020|00|o-
+0020|10|--
+--
+beat=3/4
+0020|10|-0
+0000|10|-o
+t=240
+2000|00|--
+--
+// The last measure
+1000|00|--
+0000|00|--
+stop=96
+0001|00|--
+0000|00|--
+--
+#define_fx echo type=Echo
+";
+
+#[test]
+fn parses_header() {
+    let sim = parse_string_as_ksh(TEST_KSH).unwrap();
+
+    assert_eq!(sim.title, Some("Test Song".to_string()));
+    assert_eq!(sim.artist, Some("Someone".to_string()));
+    assert_eq!(sim.credit, Some("mapper".to_string()));
+    assert_eq!(sim.jacket_path, Some("jacket.png".to_string()));
+    assert_eq!(sim.music_path, Some("song.ogg".to_string()));
+    assert_eq!(sim.offset, Some(-0.5));
+    assert_eq!(sim.sample_start, Some(30.0));
+    assert_eq!(sim.sample_length, Some(15.0));
+    assert_eq!(sim.display_bpm, Some(DisplayBPM::Range(120.0, 240.0)));
+}
+
+#[test]
+fn parses_chart() {
+    let sim = parse_string_as_ksh(TEST_KSH).unwrap();
+    let chart = &sim.charts[0];
+
+    assert_eq!(chart.chart_type, "sdvx-single");
+    assert_eq!(chart.author, Some("mapper".to_string()));
+    assert_eq!(chart.difficulty, ChartDifficulty::Challenge);
+    assert_eq!(chart.difficulty_name, "infinite");
+    assert_eq!(chart.meter, 18);
+}
+
+#[test]
+fn parses_time_signatures_and_bpms() {
+    let sim = parse_string_as_ksh(TEST_KSH).unwrap();
+
+    assert_eq!(sim.time_signatures.len(), 2);
+    assert_eq!(sim.time_signatures[1].beat, 4.0);
+    assert_eq!(sim.time_signatures[1].numerator, 3);
+    assert_eq!(sim.time_signatures[1].denominator, 4);
+
+    // The BPM in the first measure replaces the one from the header
+    assert_eq!(sim.bpms.len(), 2);
+    assert_eq!(sim.bpms[0].beat, 0.0);
+    assert_eq!(sim.bpms[0].bpm, 120.0);
+    assert_eq!(sim.bpms[1].beat, 6.0);
+    assert_eq!(sim.bpms[1].bpm, 240.0);
+
+    // 96 192nds of a measure are 2 beats, at 240 BPM
+    assert_eq!(sim.stops.len(), 1);
+    assert_eq!(sim.stops[0].beat, 8.5);
+    assert_eq!(sim.stops[0].time, 0.5);
+}
+
+#[test]
+fn parses_bt_and_fx_notes() {
+    let sim = parse_string_as_ksh(TEST_KSH).unwrap();
+
+    // The last measure is still in 3/4, so it starts on beat 7 with a line every 3/4 of a beat.
+    // The chip on row 336 is left out, since it would replace the end of the long note.
+    assert_eq!(
+        sim.charts[0].notes(),
+        vec![
+            note(0, 0, NoteType::Normal),
+            note(48, 1, NoteType::Normal),
+            note(48, 5, NoteType::Normal),
+            note(96, 2, NoteType::HoldHead),
+            note(144, 4, NoteType::HoldHead),
+            note(240, 2, NoteType::HoldOrRollTail),
+            note(288, 0, NoteType::HoldHead),
+            note(288, 4, NoteType::HoldOrRollTail),
+            note(336, 0, NoteType::HoldOrRollTail),
+            note(408, 3, NoteType::Normal),
+        ]
+    );
+}
+
+#[test]
+fn parses_lasers() {
+    let sim = parse_string_as_ksh(TEST_KSH).unwrap();
+    let lasers = &sim.charts[0].lasers;
+
+    assert_eq!(lasers.len(), 2);
+    assert_eq!(lasers[0].side, LaserSide::Left);
+    assert_eq!(lasers[0].points, vec![point(0, 0.0), point(96, 1.0)]);
+    assert_eq!(lasers[1].side, LaserSide::Right);
+    assert_eq!(lasers[1].points, vec![point(192, 0.0), point(240, 1.0)]);
+}
+
+#[test]
+fn ends_long_notes_at_the_end_of_the_chart() {
+    let sim = parse_string_as_ksh("t=150\n--\n2000|00|--\n2000|00|--\n--").unwrap();
+
+    assert_eq!(
+        sim.charts[0].notes(),
+        vec![
+            note(0, 0, NoteType::HoldHead),
+            note(192, 0, NoteType::HoldOrRollTail),
+        ]
+    );
+}
+
+#[test]
+fn fails_on_invalid_lines() {
+    assert_eq!(
+        parse_string_as_ksh("--\n100|00|--\n--").unwrap_err(),
+        SimfileParseError::InvalidKshData
+    );
+    assert_eq!(
+        parse_string_as_ksh("--\n3000|00|--\n--").unwrap_err(),
+        SimfileParseError::InvalidKshData
+    );
+    assert_eq!(
+        parse_string_as_ksh("--\n0000|00|!-\n--").unwrap_err(),
+        SimfileParseError::InvalidKshData
+    );
+    assert_eq!(
+        parse_string_as_ksh("t=fast\n--").unwrap_err(),
+        SimfileParseError::InvalidKshData
+    );
+}
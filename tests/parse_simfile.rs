@@ -1,14 +1,14 @@
 extern crate sm_parser;
 
-use sm_parser::simfile::{BPMDisplayType, ChartDifficulty, NoteType, Simfile, Stop, BPM};
+use sm_parser::simfile::{
+    ChartDifficulty, DisplayBPM, NoteType, RadarValues, Selectable, Simfile, Stop, BPM,
+};
 #[cfg(test)]
 use sm_parser::{parse_simfile, SimfileParseError};
 use std::fs::File;
 use std::io::BufReader;
 use std::path::PathBuf;
 
-// TODO: Test BGCHANGES/FGCHANGES parsing
-
 fn load_and_parse_simfile(filename: &str) -> Result<Simfile, SimfileParseError> {
     // Load example file
     let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
@@ -244,13 +244,13 @@ fn parses_empty_sample_length() {
 #[test]
 fn parses_true_selectable() {
     let sim = parse_string_as_simfile("#SELECTABLE:YES;").unwrap();
-    assert_eq!(sim.selectable, Some(true));
+    assert_eq!(sim.selectable, Some(Selectable::Yes));
 }
 
 #[test]
 fn parses_false_selectable() {
     let sim = parse_string_as_simfile("#SELECTABLE:NO;").unwrap();
-    assert_eq!(sim.selectable, Some(false));
+    assert_eq!(sim.selectable, Some(Selectable::No));
 }
 
 #[test]
@@ -259,6 +259,188 @@ fn parses_empty_selectable() {
     assert_eq!(sim.selectable, None);
 }
 
+#[test]
+fn parses_roulette_selectable() {
+    let sim = parse_string_as_simfile("#SELECTABLE:ROULETTE;").unwrap();
+    assert_eq!(sim.selectable, Some(Selectable::Roulette));
+    assert!(sim.selectable.unwrap().is_selectable());
+}
+
+#[test]
+fn parses_es_and_omes_selectable() {
+    let sim = parse_string_as_simfile("#SELECTABLE:ES;").unwrap();
+    assert_eq!(sim.selectable, Some(Selectable::Es));
+    let sim = parse_string_as_simfile("#SELECTABLE:OMES;").unwrap();
+    assert_eq!(sim.selectable, Some(Selectable::Omes));
+}
+
+#[test]
+fn parses_numeric_and_lowercase_selectable() {
+    let sim = parse_string_as_simfile("#SELECTABLE:1;").unwrap();
+    assert_eq!(sim.selectable, Some(Selectable::Numeric(1)));
+    let sim = parse_string_as_simfile("#SELECTABLE:0;").unwrap();
+    assert!(!sim.selectable.unwrap().is_selectable());
+    let sim = parse_string_as_simfile("#SELECTABLE:no;").unwrap();
+    assert_eq!(sim.selectable, Some(Selectable::No));
+}
+
+#[test]
+fn parses_unknown_selectable_verbatim() {
+    let sim = parse_string_as_simfile("#SELECTABLE:Sometimes;").unwrap();
+    assert_eq!(
+        sim.selectable,
+        Some(Selectable::Unknown("Sometimes".to_string()))
+    );
+}
+
+#[test]
+fn selectable_is_written_back_as_parsed() {
+    for value in &["YES", "NO", "ROULETTE", "ES", "OMES", "-1", "Sometimes"] {
+        let sim = parse_string_as_simfile(&format!("#SELECTABLE:{};", value)).unwrap();
+        assert_eq!(sim.selectable.unwrap().to_string(), *value);
+    }
+}
+
+#[test]
+fn parses_version() {
+    let sim = parse_string_as_simfile("#VERSION:0.83;").unwrap();
+    assert_eq!(sim.version, Some(0.83));
+}
+
+#[test]
+fn parses_empty_version() {
+    let sim = parse_string_as_simfile("#VERSION:;").unwrap();
+    assert_eq!(sim.version, None);
+}
+
+#[test]
+fn parses_origin() {
+    let sim = parse_string_as_simfile("#ORIGIN:The Origin;").unwrap();
+    assert_eq!(sim.origin, Some("The Origin".to_string()));
+}
+
+#[test]
+fn parses_empty_origin() {
+    let sim = parse_string_as_simfile("#ORIGIN:;").unwrap();
+    assert_eq!(sim.origin, None);
+}
+
+#[test]
+fn parses_preview() {
+    let sim = parse_string_as_simfile("#PREVIEW:ThePreview.ogg;").unwrap();
+    assert_eq!(sim.preview_path, Some("ThePreview.ogg".to_string()));
+}
+
+#[test]
+fn parses_empty_preview() {
+    let sim = parse_string_as_simfile("#PREVIEW:;").unwrap();
+    assert_eq!(sim.preview_path, None);
+}
+
+#[test]
+fn parses_preview_video() {
+    let sim = parse_string_as_simfile("#PREVIEWVID:ThePreview.avi;").unwrap();
+    assert_eq!(sim.preview_video_path, Some("ThePreview.avi".to_string()));
+}
+
+#[test]
+fn parses_empty_preview_video() {
+    let sim = parse_string_as_simfile("#PREVIEWVID:;").unwrap();
+    assert_eq!(sim.preview_video_path, None);
+}
+
+#[test]
+fn parses_cd_image() {
+    let sim = parse_string_as_simfile("#CDIMAGE:TheCDImage.png;").unwrap();
+    assert_eq!(sim.cd_image_path, Some("TheCDImage.png".to_string()));
+}
+
+#[test]
+fn parses_disc_image() {
+    let sim = parse_string_as_simfile("#DISCIMAGE:TheDiscImage.png;").unwrap();
+    assert_eq!(sim.disc_image_path, Some("TheDiscImage.png".to_string()));
+}
+
+#[test]
+fn parses_empty_disc_image() {
+    let sim = parse_string_as_simfile("#DISCIMAGE:;").unwrap();
+    assert_eq!(sim.disc_image_path, None);
+}
+
+#[test]
+fn parses_instrument_tracks() {
+    let sim =
+        parse_string_as_simfile("#INSTRUMENTTRACK:guitar=Guitar.ogg,rhythm=Rhythm.ogg;").unwrap();
+    assert_eq!(sim.instrument_tracks.len(), 2);
+    assert_eq!(sim.instrument_tracks[0].instrument, "guitar");
+    assert_eq!(sim.instrument_tracks[0].path, "Guitar.ogg");
+    assert_eq!(sim.instrument_tracks[1].instrument, "rhythm");
+    assert_eq!(sim.instrument_tracks[1].path, "Rhythm.ogg");
+}
+
+#[test]
+fn parses_empty_instrument_tracks() {
+    let sim = parse_string_as_simfile("#INSTRUMENTTRACK:;").unwrap();
+    assert_eq!(sim.instrument_tracks.len(), 0);
+}
+
+#[test]
+fn parses_music_length() {
+    let sim = parse_string_as_simfile("#MUSICLENGTH:123.456;").unwrap();
+    assert_eq!(sim.music_length, Some(123.456));
+}
+
+#[test]
+fn parses_last_beat_hint() {
+    let sim = parse_string_as_simfile("#LASTBEATHINT:512.000;").unwrap();
+    assert_eq!(sim.last_beat_hint, Some(512.0));
+}
+
+#[test]
+fn parses_last_second_hint() {
+    let sim = parse_string_as_simfile("#LASTSECONDHINT:98.5;").unwrap();
+    assert_eq!(sim.last_second_hint, Some(98.5));
+}
+
+#[test]
+fn parses_keysounds() {
+    let sim = parse_string_as_simfile("#KEYSOUNDS:kick.wav,snare.wav;").unwrap();
+    assert_eq!(
+        sim.keysounds,
+        vec!["kick.wav".to_string(), "snare.wav".to_string()]
+    );
+}
+
+#[test]
+fn parses_empty_keysounds() {
+    let sim = parse_string_as_simfile("#KEYSOUNDS:;").unwrap();
+    assert_eq!(sim.keysounds.len(), 0);
+}
+
+#[test]
+fn parses_animations_as_bg_changes() {
+    let sim = parse_string_as_simfile("#ANIMATIONS:6.167=bg.avi=1.000=1=0=0;").unwrap();
+    assert_eq!(sim.bg_changes.len(), 1);
+    assert_eq!(sim.bg_changes[0].file_name, String::from("bg.avi"));
+}
+
+#[test]
+fn parses_fg_changes() {
+    let sim =
+        parse_string_as_simfile("#FGCHANGES:0.000=fg.lua=1.000=0=0=0,64.000=fg2.lua;").unwrap();
+    assert_eq!(sim.fg_changes.len(), 2);
+    assert_eq!(sim.fg_changes[0].start_beat, 0.0);
+    assert_eq!(sim.fg_changes[0].path, String::from("fg.lua"));
+    assert_eq!(sim.fg_changes[1].start_beat, 64.0);
+    assert_eq!(sim.fg_changes[1].path, String::from("fg2.lua"));
+}
+
+#[test]
+fn trims_fg_change_paths() {
+    let sim = parse_string_as_simfile("#FGCHANGES:0.000= fg.lua ;").unwrap();
+    assert_eq!(sim.fg_changes[0].path, String::from("fg.lua"));
+}
+
 #[test]
 fn parses_bpms() {
     let sim = parse_string_as_simfile(
@@ -275,6 +457,21 @@ fn parses_bpms() {
 }
 
 #[test]
+fn parses_bpm_lock() {
+    let sim = parse_string_as_simfile("#BPMLOCK:YES;").unwrap();
+    assert_eq!(sim.bpm_lock, Some(true));
+    let sim = parse_string_as_simfile("#BPMLOCK:no;").unwrap();
+    assert_eq!(sim.bpm_lock, Some(false));
+}
+
+#[test]
+fn parses_empty_bpm_lock() {
+    let sim = parse_string_as_simfile("#BPMLOCK:;").unwrap();
+    assert_eq!(sim.bpm_lock, None);
+}
+
+#[test]
+#[allow(clippy::bool_assert_comparison)]
 fn parses_display_bpm_none() {
     let sim = parse_string_as_simfile("#DISPLAYBPM:;").unwrap();
     assert_eq!(sim.display_bpm.is_none(), true);
@@ -283,25 +480,50 @@ fn parses_display_bpm_none() {
 #[test]
 fn parses_display_bpm_single() {
     let sim = parse_string_as_simfile("#DISPLAYBPM:66.000;").unwrap();
-    let display_bpm = sim.display_bpm.unwrap();
-    assert_eq!(display_bpm.display_type, BPMDisplayType::Single);
-    assert_eq!(display_bpm.value, 66.0);
+    assert_eq!(sim.display_bpm, Some(DisplayBPM::Single(66.0)));
 }
 
 #[test]
 fn parses_display_bpm_range() {
     let sim = parse_string_as_simfile("#DISPLAYBPM:66.000:132.000;").unwrap();
-    let display_bpm = sim.display_bpm.unwrap();
-    assert_eq!(display_bpm.display_type, BPMDisplayType::Range);
-    assert_eq!(display_bpm.value, 66.0);
-    assert_eq!(display_bpm.value2, 132.0);
+    assert_eq!(sim.display_bpm, Some(DisplayBPM::Range(66.0, 132.0)));
 }
 
 #[test]
 fn parses_display_bpm_random() {
     let sim = parse_string_as_simfile("#DISPLAYBPM:*;").unwrap();
-    let display_bpm = sim.display_bpm.unwrap();
-    assert_eq!(display_bpm.display_type, BPMDisplayType::Random);
+    assert_eq!(sim.display_bpm, Some(DisplayBPM::Random));
+}
+
+#[test]
+fn parses_non_numerical_display_bpm_as_random() {
+    let sim = parse_string_as_simfile("#DISPLAYBPM:abc;").unwrap();
+    assert_eq!(sim.display_bpm, Some(DisplayBPM::Random));
+}
+
+#[test]
+fn display_bpm_range_uses_display_bpm() {
+    let sim = parse_string_as_simfile("#BPMS:0.000=150.000;#DISPLAYBPM:66.000:132.000;").unwrap();
+    assert_eq!(sim.display_bpm_range(), Some((66.0, 132.0)));
+    let sim = parse_string_as_simfile("#BPMS:0.000=150.000;#DISPLAYBPM:200;").unwrap();
+    assert_eq!(sim.display_bpm_range(), Some((200.0, 200.0)));
+    let sim = parse_string_as_simfile("#BPMS:0.000=150.000;#DISPLAYBPM:*;").unwrap();
+    assert_eq!(sim.display_bpm_range(), None);
+}
+
+#[test]
+fn display_bpm_range_is_computed_from_bpms_when_absent() {
+    let sim = parse_string_as_simfile(
+        "#BPMS:0.000=132.000,237.000=33.000,262.500=-132.001,266.500=396.000;",
+    )
+    .unwrap();
+    assert_eq!(sim.display_bpm_range(), Some((33.0, 396.0)));
+}
+
+#[test]
+fn display_bpm_range_is_none_without_bpms() {
+    let sim = parse_string_as_simfile("#DISPLAYBPM:;").unwrap();
+    assert_eq!(sim.display_bpm_range(), None);
 }
 
 #[test]
@@ -325,6 +547,125 @@ fn parses_stops() {
     assert_stop(&sim.stops[4], 239.000, 0.114);
 }
 
+#[test]
+fn parses_delays() {
+    let sim = parse_string_as_simfile("#DELAYS:12.000=0.500;").unwrap();
+
+    assert_eq!(sim.delays.len(), 1);
+    assert_eq!(sim.delays[0].beat, 12.0);
+    assert_eq!(sim.delays[0].time, 0.5);
+}
+
+#[test]
+fn parses_multiple_delays_with_whitespace() {
+    let sim = parse_string_as_simfile("#DELAYS:\n4.000 = 0.250,\n8.500 = 1.000\n;").unwrap();
+
+    assert_eq!(sim.delays.len(), 2);
+    assert_eq!(sim.delays[0].beat, 4.0);
+    assert_eq!(sim.delays[0].time, 0.25);
+    assert_eq!(sim.delays[1].beat, 8.5);
+    assert_eq!(sim.delays[1].time, 1.0);
+}
+
+#[test]
+fn parses_empty_delays() {
+    let sim = parse_string_as_simfile("#DELAYS:;").unwrap();
+    assert_eq!(sim.delays.len(), 0);
+}
+
+#[test]
+fn parses_scrolls() {
+    let sim = parse_string_as_simfile("#SCROLLS:0.000=1.000,16.000=0.500;").unwrap();
+
+    assert_eq!(sim.scrolls.len(), 2);
+    assert_eq!(sim.scrolls[1].beat, 16.0);
+    assert_eq!(sim.scrolls[1].factor, 0.5);
+}
+
+#[test]
+fn parses_scrolls_with_whitespace_and_negative_factors() {
+    let sim = parse_string_as_simfile("#SCROLLS:\n0.000 = 1.000,\n8.500 = -2.000;").unwrap();
+
+    assert_eq!(sim.scrolls.len(), 2);
+    assert_eq!(sim.scrolls[0].beat, 0.0);
+    assert_eq!(sim.scrolls[0].factor, 1.0);
+    assert_eq!(sim.scrolls[1].beat, 8.5);
+    assert_eq!(sim.scrolls[1].factor, -2.0);
+}
+
+#[test]
+fn parses_empty_scrolls() {
+    let sim = parse_string_as_simfile("#SCROLLS:;").unwrap();
+    assert_eq!(sim.scrolls.len(), 0);
+}
+
+#[test]
+fn parses_warps() {
+    let sim = parse_string_as_simfile("#WARPS:16.000=2.000,\n32.500 = 0.500;").unwrap();
+
+    assert_eq!(sim.warps.len(), 2);
+    assert_eq!(sim.warps[1].beat, 32.5);
+    assert_eq!(sim.warps[1].length, 0.5);
+}
+
+#[test]
+fn parses_fakes() {
+    let sim = parse_string_as_simfile("#FAKES:8.000=4.000;").unwrap();
+
+    assert_eq!(sim.fakes.len(), 1);
+    assert_eq!(sim.fakes[0].beat, 8.0);
+    assert_eq!(sim.fakes[0].length, 4.0);
+}
+
+#[test]
+fn parses_combos() {
+    let sim = parse_string_as_simfile("#COMBOS:0.000=1,\n64.000 = 4 = 2;").unwrap();
+
+    assert_eq!(sim.combos.len(), 2);
+    assert_eq!(sim.combos[0].hit_multiplier, 1);
+    assert_eq!(sim.combos[0].miss_multiplier, 1);
+    assert_eq!(sim.combos[1].beat, 64.0);
+    assert_eq!(sim.combos[1].hit_multiplier, 4);
+    assert_eq!(sim.combos[1].miss_multiplier, 2);
+}
+
+#[test]
+fn parses_labels() {
+    let sim =
+        parse_string_as_simfile("#LABELS:0.000=Song Start,\n48.000 = Break = Part 2;").unwrap();
+
+    assert_eq!(sim.labels.len(), 2);
+    assert_eq!(sim.labels[0].label, "Song Start");
+    assert_eq!(sim.labels[1].beat, 48.0);
+    assert_eq!(sim.labels[1].label, "Break = Part 2");
+}
+
+#[test]
+fn parses_time_signatures() {
+    let sim = parse_string_as_simfile("#TIMESIGNATURES:0.000=4=4,32.000=7=8;").unwrap();
+
+    assert_eq!(sim.time_signatures.len(), 2);
+    assert_eq!(sim.time_signatures[1].beat, 32.0);
+    assert_eq!(sim.time_signatures[1].numerator, 7);
+    assert_eq!(sim.time_signatures[1].denominator, 8);
+}
+
+#[test]
+fn parses_time_signatures_with_whitespace() {
+    let sim = parse_string_as_simfile("#TIMESIGNATURES:\n0.000 = 3 = 4,\n;").unwrap();
+
+    assert_eq!(sim.time_signatures.len(), 1);
+    assert_eq!(sim.time_signatures[0].beat, 0.0);
+    assert_eq!(sim.time_signatures[0].numerator, 3);
+    assert_eq!(sim.time_signatures[0].denominator, 4);
+}
+
+#[test]
+fn parses_empty_time_signatures() {
+    let sim = parse_string_as_simfile("#TIMESIGNATURES:;").unwrap();
+    assert_eq!(sim.time_signatures.len(), 0);
+}
+
 const TEST_CHART: &str = "
 #NOTES:
      dance-single:
@@ -357,6 +698,100 @@ fn parses_chart_difficulty() {
     assert_eq!(sim.charts[0].difficulty, ChartDifficulty::Challenge);
 }
 
+fn parse_chart_header(description: &str, difficulty: &str, meter: &str) -> Simfile {
+    parse_string_as_simfile(&format!(
+        "#NOTES:dance-single:{}:{}:{}:0,0,0,0,0:0000;",
+        description, difficulty, meter
+    ))
+    .unwrap()
+}
+
+#[test]
+fn parses_chart_difficulty_name() {
+    let sim = parse_chart_header("", "Heavy", "8");
+    assert_eq!(sim.charts[0].difficulty, ChartDifficulty::Hard);
+    assert_eq!(sim.charts[0].difficulty_name, "Heavy");
+}
+
+#[test]
+fn parses_legacy_chart_difficulties() {
+    let difficulties = vec![
+        ("Light", ChartDifficulty::Easy),
+        ("Basic", ChartDifficulty::Easy),
+        ("Standard", ChartDifficulty::Medium),
+        ("Another", ChartDifficulty::Medium),
+        ("Trick", ChartDifficulty::Medium),
+        ("Difficult", ChartDifficulty::Medium),
+        ("Heavy", ChartDifficulty::Hard),
+        ("Maniac", ChartDifficulty::Hard),
+        ("SSR", ChartDifficulty::Hard),
+        ("Expert", ChartDifficulty::Challenge),
+        ("Smaniac", ChartDifficulty::Challenge),
+        ("Oni", ChartDifficulty::Challenge),
+    ];
+
+    for (name, difficulty) in difficulties {
+        let sim = parse_chart_header("", name, "5");
+        assert_eq!(sim.charts[0].difficulty, difficulty, "{}", name);
+    }
+}
+
+#[test]
+fn parses_chart_difficulty_case_insensitively() {
+    let sim = parse_chart_header("", "beginner", "1");
+    assert_eq!(sim.charts[0].difficulty, ChartDifficulty::Beginner);
+    let sim = parse_chart_header("", "CHALLENGE", "12");
+    assert_eq!(sim.charts[0].difficulty, ChartDifficulty::Challenge);
+}
+
+#[test]
+fn parses_hard_chart_with_smaniac_description_as_challenge() {
+    let sim = parse_chart_header("smaniac", "Hard", "10");
+    assert_eq!(sim.charts[0].difficulty, ChartDifficulty::Challenge);
+    let sim = parse_chart_header("Challenge", "Hard", "10");
+    assert_eq!(sim.charts[0].difficulty, ChartDifficulty::Challenge);
+}
+
+#[test]
+fn infers_blank_chart_difficulty_from_meter() {
+    let sim = parse_chart_header("", "", "0");
+    assert_eq!(sim.charts[0].difficulty, ChartDifficulty::Easy);
+    let sim = parse_chart_header("", "", "1");
+    assert_eq!(sim.charts[0].difficulty, ChartDifficulty::Beginner);
+    let sim = parse_chart_header("", "", "3");
+    assert_eq!(sim.charts[0].difficulty, ChartDifficulty::Easy);
+    let sim = parse_chart_header("", "", "6");
+    assert_eq!(sim.charts[0].difficulty, ChartDifficulty::Medium);
+    let sim = parse_chart_header("", "", "9");
+    assert_eq!(sim.charts[0].difficulty, ChartDifficulty::Hard);
+}
+
+#[test]
+fn infers_blank_chart_difficulty_from_description() {
+    let sim = parse_chart_header("Expert", "", "9");
+    assert_eq!(sim.charts[0].difficulty, ChartDifficulty::Challenge);
+}
+
+#[test]
+fn infers_unknown_chart_difficulty_from_description() {
+    let sim = parse_chart_header("Expert", "Insane", "9");
+    assert_eq!(sim.charts[0].difficulty, ChartDifficulty::Challenge);
+    let sim = parse_chart_header("", "Insane", "2");
+    assert_eq!(sim.charts[0].difficulty, ChartDifficulty::Easy);
+}
+
+#[test]
+fn parses_blank_chart_meter_as_one() {
+    let sim = parse_chart_header("", "Easy", "");
+    assert_eq!(sim.charts[0].meter, 1);
+}
+
+#[test]
+fn parses_chart_without_lasers() {
+    let sim = parse_string_as_simfile(TEST_CHART).unwrap();
+    assert_eq!(sim.charts[0].lasers.len(), 0);
+}
+
 #[test]
 fn parses_chart_meter() {
     let sim = parse_string_as_simfile(TEST_CHART).unwrap();
@@ -368,10 +803,69 @@ fn parses_chart_radar_values() {
     let sim = parse_string_as_simfile(TEST_CHART).unwrap();
     assert_eq!(
         sim.charts[0].radar_values,
-        vec![0.779, 0.891, 0.620, 0.091, 0.863]
+        vec![RadarValues {
+            stream: 0.779,
+            voltage: 0.891,
+            air: 0.620,
+            freeze: 0.091,
+            chaos: 0.863,
+            ..Default::default()
+        }]
     );
 }
 
+#[test]
+fn parses_empty_chart_radar_values() {
+    let sim = parse_string_as_simfile("#NOTES:dance-single::Hard:5::0000;").unwrap();
+    assert_eq!(sim.charts[0].radar_values.len(), 0);
+}
+
+#[test]
+fn parses_per_player_chart_radar_values() {
+    let sim = load_and_parse_simfile("goin_under.sm").unwrap();
+    let radar_values = &sim.charts[0].radar_values;
+    assert_eq!(radar_values.len(), 2);
+    assert_eq!(radar_values[0], radar_values[1]);
+    assert_eq!(radar_values[0].stream, 0.7338);
+    assert_eq!(radar_values[0].chaos, 0.060764);
+    assert_eq!(radar_values[0].notes, Some(634.0));
+    assert_eq!(radar_values[0].taps, Some(628.0));
+    assert_eq!(radar_values[0].jumps, Some(6.0));
+    assert_eq!(radar_values[0].holds, Some(105.0));
+    assert_eq!(radar_values[0].mines, Some(8.0));
+    assert_eq!(radar_values[0].hands, Some(0.0));
+    assert_eq!(radar_values[0].rolls, None);
+    assert_eq!(radar_values[0].lifts, None);
+    assert_eq!(radar_values[0].fakes, None);
+}
+
+#[test]
+fn parses_chart_radar_values_with_lifts_and_fakes() {
+    let sim = parse_string_as_simfile(
+        "#NOTES:dance-single::Hard:5:0.1,0.2,0.3,0.4,0.5,10,2,3,4,1,5,6,7:0000;",
+    )
+    .unwrap();
+    let radar_values = &sim.charts[0].radar_values[0];
+    assert_eq!(radar_values.notes, None);
+    assert_eq!(radar_values.taps, Some(10.0));
+    assert_eq!(radar_values.rolls, Some(5.0));
+    assert_eq!(radar_values.lifts, Some(6.0));
+    assert_eq!(radar_values.fakes, Some(7.0));
+}
+
+#[test]
+fn parses_chart_radar_values_with_notes() {
+    let sim = parse_string_as_simfile(
+        "#NOTES:dance-single::Hard:5:0.1,0.2,0.3,0.4,0.5,12,10,2,3,4,1,5,6,7:0000;",
+    )
+    .unwrap();
+    let radar_values = &sim.charts[0].radar_values[0];
+    assert_eq!(radar_values.notes, Some(12.0));
+    assert_eq!(radar_values.taps, Some(10.0));
+    assert_eq!(radar_values.jumps, Some(2.0));
+    assert_eq!(radar_values.fakes, Some(7.0));
+}
+
 #[test]
 fn parses_empty_bg_changes() {
     let sim = parse_string_as_simfile("#BGCHANGES:;").unwrap();
@@ -389,7 +883,6 @@ fn parses_long_bg_changes() {
     assert_eq!(bg_changes.transition_type, 1);
     assert_eq!(bg_changes.effect_flag, 0);
     assert_eq!(bg_changes.second_effect_flag, 0);
-    return; // TODO: Remove this once we implement support for these fields
     assert_eq!(bg_changes.effect_file, Some(String::from("StretchNoLoop")));
     assert_eq!(bg_changes.second_effect_file, None);
     assert_eq!(bg_changes.transition_file, Some(String::from("CrossFade")));
@@ -537,6 +1030,78 @@ fn parsing_stops_with_non_numerical_values_returns_error() {
     assert_eq!(sim.err().unwrap(), SimfileParseError::FailedToParseStops);
 }
 
+#[test]
+fn parsing_time_signatures_with_missing_values_returns_error() {
+    let sim = parse_string_as_simfile("#TIMESIGNATURES:0.000=4;");
+    assert_eq!(
+        sim.err().unwrap(),
+        SimfileParseError::FailedToParseTimeSignatures
+    );
+}
+
+#[test]
+fn parsing_time_signatures_with_non_numerical_values_returns_error() {
+    let sim = parse_string_as_simfile("#TIMESIGNATURES:0.000=4=four;");
+    assert_eq!(
+        sim.err().unwrap(),
+        SimfileParseError::FailedToParseTimeSignatures
+    );
+}
+
+#[test]
+fn parsing_delays_with_non_numerical_values_returns_error() {
+    let sim = parse_string_as_simfile("#DELAYS:AA=0.500;");
+    assert_eq!(sim.err().unwrap(), SimfileParseError::FailedToParseDelays);
+}
+
+#[test]
+fn parsing_delays_with_missing_values_returns_error() {
+    let sim = parse_string_as_simfile("#DELAYS:12.000;");
+    assert_eq!(sim.err().unwrap(), SimfileParseError::FailedToParseDelays);
+}
+
+#[test]
+fn parsing_scrolls_with_non_numerical_values_returns_error() {
+    let sim = parse_string_as_simfile("#SCROLLS:0.000=fast;");
+    assert_eq!(sim.err().unwrap(), SimfileParseError::FailedToParseScrolls);
+}
+
+#[test]
+fn parsing_scrolls_with_missing_values_returns_error() {
+    let sim = parse_string_as_simfile("#SCROLLS:0.000;");
+    assert_eq!(sim.err().unwrap(), SimfileParseError::FailedToParseScrolls);
+}
+
+#[test]
+fn parsing_warps_with_non_numerical_values_returns_error() {
+    let sim = parse_string_as_simfile("#WARPS:16.000=far;");
+    assert_eq!(sim.err().unwrap(), SimfileParseError::FailedToParseWarps);
+}
+
+#[test]
+fn parsing_fakes_with_missing_values_returns_error() {
+    let sim = parse_string_as_simfile("#FAKES:8.000;");
+    assert_eq!(sim.err().unwrap(), SimfileParseError::FailedToParseFakes);
+}
+
+#[test]
+fn parsing_combos_with_too_many_values_returns_error() {
+    let sim = parse_string_as_simfile("#COMBOS:0.000=1=1=1;");
+    assert_eq!(sim.err().unwrap(), SimfileParseError::FailedToParseCombos);
+}
+
+#[test]
+fn parsing_combos_with_non_numerical_values_returns_error() {
+    let sim = parse_string_as_simfile("#COMBOS:0.000=double;");
+    assert_eq!(sim.err().unwrap(), SimfileParseError::FailedToParseCombos);
+}
+
+#[test]
+fn parsing_labels_without_beat_returns_error() {
+    let sim = parse_string_as_simfile("#LABELS:Song Start;");
+    assert_eq!(sim.err().unwrap(), SimfileParseError::FailedToParseLabels);
+}
+
 #[test]
 fn parsing_display_bpm_with_too_many_values_returns_error() {
     let sim = parse_string_as_simfile("#DISPLAYBPM:66.000:132.000:64.00;");
@@ -546,6 +1111,15 @@ fn parsing_display_bpm_with_too_many_values_returns_error() {
     );
 }
 
+#[test]
+fn parsing_display_bpm_range_with_non_numerical_value_returns_error() {
+    let sim = parse_string_as_simfile("#DISPLAYBPM:abc:150;");
+    assert_eq!(
+        sim.err().unwrap(),
+        SimfileParseError::FailedToParseDisplayBPM
+    );
+}
+
 #[test]
 fn parsing_bg_changes_with_too_few_values_returns_error() {
     let sim = parse_string_as_simfile("#BGCHANGES:5.400=Diamond Happy.mp4=;");
@@ -556,6 +1130,21 @@ fn parsing_bg_changes_with_too_few_values_returns_error() {
     );
 }
 
+#[test]
+fn parsing_fg_changes_with_too_few_values_returns_error() {
+    let sim = parse_string_as_simfile("#FGCHANGES:5.400;");
+    assert_eq!(sim.err().unwrap(), SimfileParseError::InvalidFgChangeFormat);
+}
+
+#[test]
+fn parsing_invalid_instrument_track_returns_error() {
+    let sim = parse_string_as_simfile("#INSTRUMENTTRACK:guitar;");
+    assert_eq!(
+        sim.err().unwrap(),
+        SimfileParseError::InvalidInstrumentTrackFormat
+    );
+}
+
 #[test]
 fn parsing_empty_chart_returns_error() {
     let sim = parse_string_as_simfile("#NOTES:;");
@@ -580,7 +1169,7 @@ fn parsing_chart_with_invalid_header_returns_error() {
 }
 
 #[test]
-fn parsing_chart_with_undefined_difficulty_returns_error() {
+fn infers_undefined_chart_difficulty_from_meter() {
     let sim = parse_string_as_simfile("
         #NOTES:
              dance-single:
@@ -592,11 +1181,27 @@ fn parsing_chart_with_undefined_difficulty_returns_error() {
         0000
         0000
         0000;
-    ");
+    ")
+    .unwrap();
 
+    assert_eq!(sim.charts[0].difficulty, ChartDifficulty::Hard);
+}
+
+#[test]
+fn parsing_chart_with_non_numeric_radar_values_returns_error() {
+    let sim = parse_string_as_simfile("#NOTES:dance-single::Hard:5:0.1,0.2,abc,0.4,0.5:0000;");
+    assert_eq!(
+        sim.err().unwrap(),
+        SimfileParseError::FailedToParseRadarValues
+    );
+}
+
+#[test]
+fn parsing_chart_with_wrong_amount_of_radar_values_returns_error() {
+    let sim = parse_string_as_simfile("#NOTES:dance-single::Hard:5:0.1,0.2,0.3:0000;");
     assert_eq!(
         sim.err().unwrap(),
-        SimfileParseError::UnknownChartDifficulty
+        SimfileParseError::FailedToParseRadarValues
     );
 }
 
@@ -622,6 +1227,7 @@ fn parsing_chart_with_non_numeric_meter_returns_error() {
 }
 
 #[test]
+#[allow(clippy::excessive_precision)]
 fn parse_simfile_parses_correctly() {
     let sim = load_and_parse_simfile("goin_under.sm").unwrap();
 
@@ -635,7 +1241,7 @@ fn parse_simfile_parses_correctly() {
     assert_eq!(sim.offset, Some(0.0));
     assert_eq!(sim.sample_start, Some(45.714001));
     assert_eq!(sim.sample_length, Some(13.714000));
-    assert_eq!(sim.selectable, Some(true));
+    assert_eq!(sim.selectable, Some(Selectable::Yes));
     assert_eq!(sim.bpms.len(), 1);
 
     assert_bpm(&sim.bpms[0], 0.0, 210.0);
@@ -0,0 +1,144 @@
+extern crate sm_parser;
+
+use sm_parser::simfile::{Simfile, SpeedUnit};
+use sm_parser::sma::parse_sma;
+use sm_parser::timing::TimingData;
+use sm_parser::SimfileParseError;
+use std::io::BufReader;
+
+fn parse_string_as_sma(data: &str) -> Result<Simfile, SimfileParseError> {
+    parse_sma(&mut BufReader::new(data.as_bytes()))
+}
+
+const TEST_SMA: &str = "#TITLE:Test Song;
+#OFFSET:-0.25;
+#ROWSPERBEAT:24;
+#BEATSPERMEASURE:0=4,48r=3;
+#BPMS:0=120,96r=240;
+#STOPS:4=0.5;
+#DELAYS:;
+#TICKCOUNT:0=4,8=2;
+#SPEED:0=1,4=2=1.5s,6=0.5=2;
+#NOTES:
+     pump-single:
+     author:
+     Hard:
+     12:
+     0,0,0,0,0:
+10000
+00000
+00000
+00000
+;
+#BPMS:0=150;
+#TICKCOUNT:0=8;
+#NOTES:
+     pump-single:
+     :
+     Easy:
+     3:
+     :
+00100
+;
+";
+
+#[test]
+fn parses_song_header() {
+    let sim = parse_string_as_sma(TEST_SMA).unwrap();
+
+    assert_eq!(sim.title, Some("Test Song".to_string()));
+    assert_eq!(sim.offset, Some(-0.25));
+    assert_eq!(sim.charts.len(), 2);
+}
+
+#[test]
+fn parses_beats_given_in_rows() {
+    let sim = parse_string_as_sma(TEST_SMA).unwrap();
+
+    // There are 24 rows per beat, so row 96 is beat 4
+    assert_eq!(sim.bpms.len(), 2);
+    assert_eq!(sim.bpms[1].beat, 4.0);
+    assert_eq!(sim.bpms[1].bpm, 240.0);
+    assert_eq!(sim.time_signatures[1].beat, 2.0);
+    assert_eq!(sim.stops[0].beat, 4.0);
+    assert_eq!(sim.stops[0].time, 0.5);
+    assert!(sim.delays.is_empty());
+}
+
+#[test]
+fn parses_beats_per_measure() {
+    let sim = parse_string_as_sma(TEST_SMA).unwrap();
+
+    assert_eq!(sim.time_signatures.len(), 2);
+    assert_eq!(sim.time_signatures[1].numerator, 3);
+    assert_eq!(sim.time_signatures[1].denominator, 4);
+}
+
+#[test]
+fn parses_tick_counts_and_speeds() {
+    let sim = parse_string_as_sma(TEST_SMA).unwrap();
+
+    assert_eq!(sim.tick_counts.len(), 2);
+    assert_eq!(sim.tick_counts[1].beat, 8.0);
+    assert_eq!(sim.tick_counts[1].ticks, 2);
+
+    assert_eq!(sim.speeds.len(), 3);
+    assert_eq!(sim.speeds[0].duration, 0.0);
+    assert_eq!(sim.speeds[1].beat, 4.0);
+    assert_eq!(sim.speeds[1].ratio, 2.0);
+    assert_eq!(sim.speeds[1].duration, 1.5);
+    assert_eq!(sim.speeds[1].unit, SpeedUnit::Seconds);
+    assert_eq!(sim.speeds[2].duration, 2.0);
+    assert_eq!(sim.speeds[2].unit, SpeedUnit::Beats);
+}
+
+#[test]
+fn parses_chart_timing() {
+    let sim = parse_string_as_sma(TEST_SMA).unwrap();
+
+    // The timing tags after the first chart only apply to that chart
+    assert!(sim.charts[1].timing.is_none());
+    let timing = sim.charts[0].timing.as_ref().unwrap();
+    assert_eq!(timing.bpms.len(), 1);
+    assert_eq!(timing.bpms[0].bpm, 150.0);
+    assert_eq!(timing.tick_counts[0].ticks, 8);
+
+    // The tags the chart doesn't set come from the song
+    assert_eq!(timing.offset, Some(-0.25));
+    assert_eq!(timing.stops.len(), 1);
+    assert_eq!(timing.speeds.len(), 3);
+}
+
+#[test]
+fn uses_chart_timing() {
+    let sim = parse_string_as_sma(TEST_SMA).unwrap();
+
+    let chart_timing = TimingData::from_chart(&sim, &sim.charts[0]);
+    let song_timing = TimingData::from_chart(&sim, &sim.charts[1]);
+    assert_eq!(chart_timing.seconds_at_beat(2.0), 0.8 + 0.25);
+    assert_eq!(song_timing.seconds_at_beat(2.0), 1.0 + 0.25);
+}
+
+#[test]
+fn parsing_invalid_timing_returns_error() {
+    assert_eq!(
+        parse_string_as_sma("#BPMS:0=120=4;").unwrap_err(),
+        SimfileParseError::FailedToParseBPMs
+    );
+    assert_eq!(
+        parse_string_as_sma("#STOPS:xr=1;").unwrap_err(),
+        SimfileParseError::FailedToParseStops
+    );
+    assert_eq!(
+        parse_string_as_sma("#BEATSPERMEASURE:0;").unwrap_err(),
+        SimfileParseError::FailedToParseTimeSignatures
+    );
+    assert_eq!(
+        parse_string_as_sma("#TICKCOUNT:0=a;").unwrap_err(),
+        SimfileParseError::FailedToParseTickCounts
+    );
+    assert_eq!(
+        parse_string_as_sma("#SPEED:0=1=2x;").unwrap_err(),
+        SimfileParseError::FailedToParseSpeeds
+    );
+}
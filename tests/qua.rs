@@ -0,0 +1,253 @@
+extern crate sm_parser;
+
+mod common;
+
+use common::note;
+use sm_parser::parse_simfile;
+use sm_parser::qua::{parse_qua, write_qua};
+use sm_parser::simfile::{ChartDifficulty, NoteType, Simfile};
+use sm_parser::SimfileParseError;
+use std::io::BufReader;
+
+fn parse_string_as_qua(data: &str) -> Result<Simfile, SimfileParseError> {
+    parse_qua(&mut BufReader::new(data.as_bytes()))
+}
+
+fn write_to_string(sim: &Simfile) -> String {
+    let mut data = vec![];
+    write_qua(sim, &sim.charts[0], &mut data).unwrap();
+    String::from_utf8(data).unwrap()
+}
+
+// 120 BPM from 1 second in, then 240 BPM from 5 seconds in (beat 8)
+const TEST_QUA: &str = "AudioFile: audio.mp3
+SongPreviewTime: 12500
+BackgroundFile: bg.jpg
+MapId: -1
+MapSetId: -1
+Mode: Keys4
+Title: 'Song: The Remix'
+Artist: Someone
+Source: ''
+Tags: ''
+Creator: mapper
+DifficultyName: Hard
+Description: ''
+BPMDoesNotAffectScrollVelocity: true
+InitialScrollVelocity: 1
+HasScratchKey: false
+EditorLayers: []
+CustomAudioSamples: []
+SoundEffects: []
+TimingPoints:
+- StartTime: 1000
+  Bpm: 120
+- StartTime: 5000
+  Bpm: 240
+  Signature: Triple
+SliderVelocities:
+- StartTime: 3000
+  Multiplier: 0.5
+- StartTime: 4000
+HitObjects:
+- StartTime: 1000
+  Lane: 1
+  KeySounds: []
+- StartTime: 1250
+  Lane: 2
+  KeySounds:
+  - Sample: 1
+    Volume: 100
+- StartTime: 1667
+  Lane: 3
+  KeySounds: []
+- StartTime: 2000
+  Lane: 4
+  EndTime: 3000
+  KeySounds: []
+- StartTime: 5125
+  Lane: 1
+  KeySounds: []
+";
+
+#[test]
+fn parses_metadata() {
+    let sim = parse_string_as_qua(TEST_QUA).unwrap();
+
+    assert_eq!(sim.title, Some("Song: The Remix".to_string()));
+    assert_eq!(sim.artist, Some("Someone".to_string()));
+    assert_eq!(sim.origin, None);
+    assert_eq!(sim.credit, Some("mapper".to_string()));
+    assert_eq!(sim.music_path, Some("audio.mp3".to_string()));
+    assert_eq!(sim.background_path, Some("bg.jpg".to_string()));
+    assert_eq!(sim.sample_start, Some(12.5));
+}
+
+#[test]
+fn parses_timing_points() {
+    let sim = parse_string_as_qua(TEST_QUA).unwrap();
+
+    assert_eq!(sim.offset, Some(-1.0));
+    assert_eq!(sim.bpms.len(), 2);
+    assert_eq!(sim.bpms[0].bpm, 120.0);
+    assert_eq!(sim.bpms[1].beat, 8.0);
+    assert_eq!(sim.bpms[1].bpm, 240.0);
+}
+
+#[test]
+fn parses_slider_velocities() {
+    let sim = parse_string_as_qua(TEST_QUA).unwrap();
+
+    // The multiplier is left out when it's 0
+    assert_eq!(sim.scrolls.len(), 2);
+    assert_eq!(sim.scrolls[0].beat, 4.0);
+    assert_eq!(sim.scrolls[0].factor, 0.5);
+    assert_eq!(sim.scrolls[1].beat, 6.0);
+    assert_eq!(sim.scrolls[1].factor, 0.0);
+}
+
+#[test]
+fn parses_chart() {
+    let sim = parse_string_as_qua(TEST_QUA).unwrap();
+    let chart = &sim.charts[0];
+
+    assert_eq!(chart.chart_type, "dance-single");
+    assert_eq!(chart.author, Some("mapper".to_string()));
+    assert_eq!(chart.difficulty, ChartDifficulty::Hard);
+    assert_eq!(chart.difficulty_name, "Hard");
+    assert_eq!(
+        chart.notes(),
+        vec![
+            note(0, 0, NoteType::Normal),
+            note(24, 1, NoteType::Normal),
+            note(64, 2, NoteType::Normal),
+            note(96, 3, NoteType::HoldHead),
+            note(192, 3, NoteType::HoldOrRollTail),
+            note(408, 0, NoteType::Normal),
+        ]
+    );
+}
+
+#[test]
+fn parses_scratch_key_and_initial_scroll_velocity() {
+    let sim = parse_string_as_qua(
+        "Mode: Keys7
+HasScratchKey: true
+InitialScrollVelocity: 2
+TimingPoints:
+  - Bpm: 150
+HitObjects:
+  - Lane: 8
+    StartTime: 400",
+    )
+    .unwrap();
+
+    assert_eq!(sim.charts[0].chart_type, "beat-single7");
+    assert_eq!(sim.scrolls[0].beat, 0.0);
+    assert_eq!(sim.scrolls[0].factor, 2.0);
+    assert_eq!(sim.charts[0].notes(), vec![note(48, 0, NoteType::Normal)]);
+}
+
+#[test]
+fn fails_on_invalid_data() {
+    assert_eq!(
+        parse_string_as_qua("Title: Nothing").unwrap_err(),
+        SimfileParseError::InvalidQuaData
+    );
+    assert_eq!(
+        parse_string_as_qua("Mode: Keys11").unwrap_err(),
+        SimfileParseError::UnsupportedKeyCount
+    );
+    assert_eq!(
+        parse_string_as_qua("Mode: Keys4\nHasScratchKey: true").unwrap_err(),
+        SimfileParseError::UnsupportedKeyCount
+    );
+    assert_eq!(
+        parse_string_as_qua("Mode: Keys4\nTimingPoints: []").unwrap_err(),
+        SimfileParseError::InvalidQuaData
+    );
+    assert_eq!(
+        parse_string_as_qua("Mode: Keys4\nTimingPoints:\n- Bpm: 120\nHitObjects:\n- Lane: 5")
+            .unwrap_err(),
+        SimfileParseError::InvalidQuaData
+    );
+}
+
+#[test]
+fn writes_map() {
+    let sim = parse_string_as_qua(TEST_QUA).unwrap();
+    let data = write_to_string(&sim);
+
+    assert!(data.contains("Mode: Keys4\n"));
+    assert!(data.contains("Title: 'Song: The Remix'\n"));
+    assert!(data.contains("SongPreviewTime: 12500\n"));
+    assert!(data.contains("DifficultyName: Hard\n"));
+    assert!(data.contains("TimingPoints:\n- StartTime: 1000\n  Bpm: 120\n"));
+    assert!(data.contains("SliderVelocities:\n- StartTime: 3000\n  Multiplier: 0.5\n"));
+    assert!(data.contains("- StartTime: 2000\n  Lane: 4\n  EndTime: 3000\n"));
+}
+
+#[test]
+fn writes_scratch_key() {
+    let sim = parse_simfile(&mut BufReader::new(
+        "#OFFSET:0;#BPMS:0=120;
+        #NOTES:beat-single7::Hard:5::
+        10000001
+        ;"
+        .as_bytes(),
+    ))
+    .unwrap();
+    let data = write_to_string(&sim);
+
+    assert!(data.contains("Mode: Keys7\n"));
+    assert!(data.contains("HasScratchKey: true\n"));
+    assert!(data.contains("- StartTime: 0\n  Lane: 8\n"));
+    assert!(data.contains("- StartTime: 0\n  Lane: 7\n"));
+    let written = parse_string_as_qua(&data).unwrap();
+    assert_eq!(written.charts[0].notes(), sim.charts[0].notes());
+}
+
+#[test]
+fn fails_to_write_unsupported_key_counts() {
+    let sim = parse_simfile(&mut BufReader::new(
+        "#BPMS:0=120;#NOTES:pump-single::Hard:5::00000;".as_bytes(),
+    ))
+    .unwrap();
+    let mut data = vec![];
+
+    assert_eq!(
+        write_qua(&sim, &sim.charts[0], &mut data)
+            .unwrap_err()
+            .kind(),
+        std::io::ErrorKind::InvalidInput
+    );
+}
+
+#[test]
+fn round_trips_map() {
+    let sim = parse_string_as_qua(TEST_QUA).unwrap();
+    let written = parse_string_as_qua(&write_to_string(&sim)).unwrap();
+
+    assert_eq!(written.title, sim.title);
+    assert_eq!(written.offset, sim.offset);
+    assert_eq!(written.bpms.len(), sim.bpms.len());
+    assert_eq!(written.scrolls.len(), sim.scrolls.len());
+    assert_eq!(written.charts[0].notes(), sim.charts[0].notes());
+}
+
+#[test]
+fn writes_simfile_scrolls() {
+    let sim = parse_simfile(&mut BufReader::new(
+        "#OFFSET:0;#BPMS:0=120;#SCROLLS:0=1.5,4=1;
+        #NOTES:dance-single::Hard:5::
+        1000
+        ;"
+        .as_bytes(),
+    ))
+    .unwrap();
+    let data = write_to_string(&sim);
+
+    assert!(data.contains("InitialScrollVelocity: 1.5\n"));
+    assert!(data.contains("SliderVelocities:\n- StartTime: 2000\n  Multiplier: 1\n"));
+    assert!(data.contains("HitObjects:\n- StartTime: 0\n  Lane: 1\n"));
+}
@@ -0,0 +1,113 @@
+extern crate sm_parser;
+
+mod common;
+
+use common::test_dir;
+use sm_parser::song_directory::{find_simfile, resolve_path, SongDirectory};
+use sm_parser::SimfileParseError;
+use std::fs;
+
+#[test]
+fn prefers_ssc_over_sm() {
+    let dir = test_dir(
+        "prefers_ssc",
+        &[
+            ("a.sm", "#TITLE:From SM;"),
+            ("b.ssc", "#TITLE:From SSC;"),
+            ("c.dwi", "#TITLE:From DWI;"),
+        ],
+    );
+
+    assert_eq!(find_simfile(&dir), Some(dir.join("b.ssc")));
+    let song = SongDirectory::load(&dir).unwrap();
+    assert_eq!(song.simfile.title, Some("From SSC".to_string()));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn uses_assets_set_in_simfile() {
+    let dir = test_dir(
+        "set_assets",
+        &[
+            (
+                "song.sm",
+                "#BANNER:Images\\Banner.PNG;#BACKGROUND:bg.png;#MUSIC:song.ogg;#JACKET:../jacket.png;",
+            ),
+            ("images/banner.png", ""),
+            ("bg.png", ""),
+            ("other-bn.png", ""),
+            ("song.ogg", ""),
+        ],
+    );
+    fs::write(dir.join("../jacket.png"), "").unwrap();
+
+    let song = SongDirectory::load(&dir).unwrap();
+    assert_eq!(song.banner, Some(dir.join("images/banner.png")));
+    assert_eq!(song.background, Some(dir.join("bg.png")));
+    assert_eq!(song.music, Some(dir.join("song.ogg")));
+    assert_eq!(song.jacket, Some(dir.join("../jacket.png")));
+    assert_eq!(song.cd_title, None);
+
+    fs::remove_file(dir.join("../jacket.png")).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn guesses_missing_assets() {
+    let dir = test_dir(
+        "guessed_assets",
+        &[
+            ("song.sm", "#BANNER:missing.png;#MUSIC:;"),
+            ("Song-BN.png", ""),
+            ("song-bg.jpg", ""),
+            ("cdtitle.png", ""),
+            ("notes.txt", ""),
+            ("song.mp3", ""),
+        ],
+    );
+
+    let song = SongDirectory::load(&dir).unwrap();
+    assert_eq!(song.banner, Some(dir.join("Song-BN.png")));
+    assert_eq!(song.background, Some(dir.join("song-bg.jpg")));
+    assert_eq!(song.cd_title, Some(dir.join("cdtitle.png")));
+    assert_eq!(song.music, Some(dir.join("song.mp3")));
+    assert_eq!(song.jacket, None);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn resolves_paths_ignoring_case() {
+    let dir = test_dir("resolve", &[("Folder/File.png", "")]);
+
+    assert_eq!(
+        resolve_path(&dir, "folder/file.PNG"),
+        Some(dir.join("Folder/File.png"))
+    );
+    assert_eq!(
+        resolve_path(&dir, "./Folder/File.png"),
+        Some(dir.join("Folder/File.png"))
+    );
+    assert_eq!(resolve_path(&dir, "folder"), None);
+    assert_eq!(resolve_path(&dir, "other.png"), None);
+    assert_eq!(resolve_path(&dir, ""), None);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn loading_folder_without_simfile_returns_error() {
+    let dir = test_dir("no_simfile", &[("song.ogg", "")]);
+
+    assert_eq!(
+        SongDirectory::load(&dir).unwrap_err(),
+        SimfileParseError::NoSimfileInDirectory
+    );
+    assert_eq!(
+        SongDirectory::load(dir.join("missing")).unwrap_err(),
+        SimfileParseError::BufReadError
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,321 @@
+extern crate sm_parser;
+
+use sm_parser::simfile::{ChartDifficulty, ChartTiming, NoteType, Simfile, SpeedUnit};
+use sm_parser::ssc::{parse_ssc, write_ssc};
+use sm_parser::timing::TimingData;
+use sm_parser::{parse_simfile, SimfileParseError};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::PathBuf;
+
+fn parse_string_as_ssc(data: &str) -> Result<Simfile, SimfileParseError> {
+    parse_ssc(&mut BufReader::new(data.as_bytes()))
+}
+
+fn write_to_string(sim: &Simfile) -> String {
+    let mut data = vec![];
+    write_ssc(sim, &mut data).unwrap();
+    String::from_utf8(data).unwrap()
+}
+
+const TEST_SSC: &str = "#VERSION:0.83;
+#TITLE:Test Song;
+#ARTIST:Someone;
+#OFFSET:-0.1;
+#BPMS:0=120,8=240;
+#STOPS:4=0.5;
+#DELAYS:2=0.25;
+#TIMESIGNATURES:0=4=4;
+#TICKCOUNTS:0=4;
+#SPEEDS:0=1=0=0,4=2=1.5=1;
+#SCROLLS:0=1;
+#WARPS:6=1;
+#FAKES:7=0.5;
+#COMBOS:0=1,4=2=3;
+#LABELS:0=Song Start,4=Drop;
+
+//---------------dance-single - ----------------
+#NOTEDATA:;
+#CHARTNAME:;
+#STEPSTYPE:dance-single;
+#DESCRIPTION:smaniac;
+#CHARTSTYLE:;
+#DIFFICULTY:Hard;
+#METER:12;
+#RADARVALUES:0.1,0.2,0.3,0.4,0.5,2,1,0,0,0,0,0,0,0;
+#CREDIT:mapper;
+#NOTES:
+1000
+0100
+0010
+0001
+;
+
+//---------------dance-double - ----------------
+#NOTEDATA:;
+#STEPSTYPE:dance-double;
+#DIFFICULTY:Edit;
+#METER:5;
+#BPMS:0=150;
+#TICKCOUNTS:0=2;
+#NOTES:
+00000000
+00000000
+00000000
+00000001
+;
+";
+
+#[test]
+fn parses_song_tags() {
+    let sim = parse_string_as_ssc(TEST_SSC).unwrap();
+
+    assert_eq!(sim.version, Some(0.83));
+    assert_eq!(sim.title, Some("Test Song".to_string()));
+    assert_eq!(sim.artist, Some("Someone".to_string()));
+    assert_eq!(sim.offset, Some(-0.1));
+    assert_eq!(sim.bpms.len(), 2);
+    assert_eq!(sim.stops.len(), 1);
+    assert_eq!(sim.delays.len(), 1);
+    assert_eq!(sim.time_signatures.len(), 1);
+    assert_eq!(sim.scrolls.len(), 1);
+}
+
+#[test]
+fn parses_tick_counts_and_speeds() {
+    let sim = parse_string_as_ssc(TEST_SSC).unwrap();
+
+    assert_eq!(sim.tick_counts.len(), 1);
+    assert_eq!(sim.tick_counts[0].ticks, 4);
+    assert_eq!(sim.speeds.len(), 2);
+    assert_eq!(sim.speeds[0].unit, SpeedUnit::Beats);
+    assert_eq!(sim.speeds[1].beat, 4.0);
+    assert_eq!(sim.speeds[1].ratio, 2.0);
+    assert_eq!(sim.speeds[1].duration, 1.5);
+    assert_eq!(sim.speeds[1].unit, SpeedUnit::Seconds);
+}
+
+#[test]
+fn parses_charts() {
+    let sim = parse_string_as_ssc(TEST_SSC).unwrap();
+    assert_eq!(sim.charts.len(), 2);
+
+    let chart = &sim.charts[0];
+    assert_eq!(chart.chart_type, "dance-single");
+    assert_eq!(chart.author, Some("smaniac".to_string()));
+    assert_eq!(chart.credit, Some("mapper".to_string()));
+    assert_eq!(chart.difficulty_name, "Hard");
+    assert_eq!(chart.difficulty, ChartDifficulty::Challenge);
+    assert_eq!(chart.meter, 12);
+    assert_eq!(chart.radar_values[0].notes, Some(2.0));
+    assert_eq!(chart.note_data[0][0], NoteType::Normal);
+    assert!(chart.timing.is_none());
+
+    let chart = &sim.charts[1];
+    assert_eq!(chart.chart_type, "dance-double");
+    assert_eq!(chart.author, None);
+    assert_eq!(chart.credit, None);
+    assert_eq!(chart.difficulty, ChartDifficulty::Edit);
+    assert_eq!(chart.note_data[0][31], NoteType::Normal);
+}
+
+#[test]
+fn parses_chart_timing() {
+    let sim = parse_string_as_ssc(TEST_SSC).unwrap();
+    let timing = sim.charts[1].timing.as_ref().unwrap();
+
+    assert_eq!(timing.bpms.len(), 1);
+    assert_eq!(timing.bpms[0].bpm, 150.0);
+    assert_eq!(timing.tick_counts[0].ticks, 2);
+    // The tags the chart doesn't set come from the song
+    assert_eq!(timing.offset, Some(-0.1));
+    assert_eq!(timing.stops.len(), 1);
+
+    let chart_timing = TimingData::from_chart(&sim, &sim.charts[1]);
+    assert_eq!(chart_timing.bpm_at_beat(10.0), 150.0);
+}
+
+#[test]
+fn parses_charts_without_notes() {
+    let sim = parse_string_as_ssc("#NOTEDATA:;#STEPSTYPE:pump-single;#DIFFICULTY:Easy;").unwrap();
+
+    assert_eq!(sim.charts[0].meter, 1);
+    assert!(sim.charts[0].note_data.is_empty());
+}
+
+#[test]
+fn parsing_invalid_charts_returns_error() {
+    assert_eq!(
+        parse_string_as_ssc("#NOTEDATA:;#DIFFICULTY:Easy;").unwrap_err(),
+        SimfileParseError::InvalidChartFormat
+    );
+    assert_eq!(
+        parse_string_as_ssc("#NOTEDATA:;#STEPSTYPE:dance-single;#METER:hard;").unwrap_err(),
+        SimfileParseError::FailedToParseChartMeter
+    );
+}
+
+#[test]
+fn parsing_invalid_timing_returns_error() {
+    assert_eq!(
+        parse_string_as_ssc("#TICKCOUNTS:0=a;").unwrap_err(),
+        SimfileParseError::FailedToParseTickCounts
+    );
+    assert_eq!(
+        parse_string_as_ssc("#SPEEDS:0=1=0=2;").unwrap_err(),
+        SimfileParseError::FailedToParseSpeeds
+    );
+    assert_eq!(
+        parse_string_as_ssc("#NOTEDATA:;#STEPSTYPE:dance-single;#BPMS:0;").unwrap_err(),
+        SimfileParseError::FailedToParseBPMs
+    );
+}
+
+#[test]
+fn writes_song_tags_and_timing() {
+    let sim = parse_string_as_ssc(TEST_SSC).unwrap();
+    let data = write_to_string(&sim);
+
+    assert!(data.starts_with("#VERSION:0.83;\n#TITLE:Test Song;\n"));
+    assert!(data.contains("#OFFSET:-0.100000;\n"));
+    assert!(data.contains("#BPMS:0.000000=120.000000,8.000000=240.000000;\n"));
+    assert!(data.contains("#DELAYS:2.000000=0.250000;\n"));
+    assert!(data.contains("#TIMESIGNATURES:0.000000=4=4;\n"));
+    assert!(data.contains("#TICKCOUNTS:0.000000=4;\n"));
+    assert!(data.contains("#SPEEDS:0.000000=1.000000=0.000000=0,4.000000=2.000000=1.500000=1;\n"));
+    assert!(data.contains("#WARPS:6.000000=1.000000;\n"));
+    assert!(data.contains("#FAKES:7.000000=0.500000;\n"));
+    assert!(data.contains("#COMBOS:0.000000=1=1,4.000000=2=3;\n"));
+    assert!(data.contains("#LABELS:0.000000=Song Start,4.000000=Drop;\n"));
+}
+
+#[test]
+fn parses_warps_fakes_combos_and_labels() {
+    let sim = parse_string_as_ssc(TEST_SSC).unwrap();
+
+    assert_eq!(sim.warps.len(), 1);
+    assert_eq!(sim.warps[0].beat, 6.0);
+    assert_eq!(sim.warps[0].length, 1.0);
+    assert_eq!(sim.fakes.len(), 1);
+    assert_eq!(sim.fakes[0].length, 0.5);
+    assert_eq!(sim.combos.len(), 2);
+    // The miss multiplier is the hit multiplier when it's left out
+    assert_eq!(sim.combos[0].hit_multiplier, 1);
+    assert_eq!(sim.combos[0].miss_multiplier, 1);
+    assert_eq!(sim.combos[1].hit_multiplier, 2);
+    assert_eq!(sim.combos[1].miss_multiplier, 3);
+    assert_eq!(sim.labels.len(), 2);
+    assert_eq!(sim.labels[1].beat, 4.0);
+    assert_eq!(sim.labels[1].label, "Drop");
+}
+
+#[test]
+fn writes_version_as_parsed() {
+    let sim = parse_string_as_ssc("#VERSION:0.81;#TITLE:Old Song;").unwrap();
+    assert!(write_to_string(&sim).starts_with("#VERSION:0.81;\n"));
+
+    let sim = parse_string_as_ssc("#TITLE:No Version;").unwrap();
+    assert!(write_to_string(&sim).starts_with("#VERSION:0.83;\n"));
+}
+
+#[test]
+fn writes_difficulty_as_parsed() {
+    let sim = parse_string_as_ssc(
+        "#NOTEDATA:;#STEPSTYPE:dance-single;#DIFFICULTY:expert;#NOTES:0000;\
+         #NOTEDATA:;#STEPSTYPE:dance-single;#DIFFICULTY:Medium;#NOTES:0000;",
+    )
+    .unwrap();
+    let mut changed = sim.clone();
+    changed.charts[1].difficulty = ChartDifficulty::Hard;
+
+    assert!(write_to_string(&sim).contains("#DIFFICULTY:expert;\n"));
+    // A name that doesn't match the difficulty anymore isn't written
+    assert!(write_to_string(&changed).contains("#DIFFICULTY:Hard;\n"));
+}
+
+#[test]
+fn writes_charts() {
+    let sim = parse_string_as_ssc(TEST_SSC).unwrap();
+    let data = write_to_string(&sim);
+
+    assert!(data.contains(
+        "#NOTEDATA:;
+#STEPSTYPE:dance-single;
+#DESCRIPTION:smaniac;
+#DIFFICULTY:Hard;
+#METER:12;
+#RADARVALUES:0.100000,0.200000,0.300000,0.400000,0.500000,2.000000,1.000000,0.000000,0.000000,0.000000,0.000000,0.000000,0.000000,0.000000;
+#CREDIT:mapper;
+#NOTES:
+1000
+0100
+0010
+0001
+;
+"
+    ));
+}
+
+#[test]
+fn writes_chart_timing_when_it_differs() {
+    let mut sim = parse_string_as_ssc(TEST_SSC).unwrap();
+    // Chart timing that is the same as the song timing is left out
+    sim.charts[0].timing = Some(ChartTiming::from_simfile(&sim));
+    let data = write_to_string(&sim);
+
+    assert_eq!(data.matches("#BPMS:").count(), 2);
+    assert!(data.contains("#BPMS:0.000000=150.000000;\n"));
+    assert!(data.contains("#TICKCOUNTS:0.000000=2;\n"));
+}
+
+#[test]
+fn round_trips_simfile() {
+    let sim = parse_string_as_ssc(TEST_SSC).unwrap();
+    let written = parse_string_as_ssc(&write_to_string(&sim)).unwrap();
+
+    assert_eq!(written.title, sim.title);
+    assert_eq!(written.bpms, sim.bpms);
+    assert_eq!(written.speeds, sim.speeds);
+    assert_eq!(written.warps, sim.warps);
+    assert_eq!(written.fakes, sim.fakes);
+    assert_eq!(written.combos, sim.combos);
+    assert_eq!(written.labels, sim.labels);
+    assert_eq!(written.charts.len(), sim.charts.len());
+    for (written, chart) in written.charts.iter().zip(sim.charts.iter()) {
+        assert_eq!(written.author, chart.author);
+        assert_eq!(written.credit, chart.credit);
+        assert_eq!(written.difficulty_name, chart.difficulty_name);
+        assert_eq!(written.difficulty, chart.difficulty);
+        assert_eq!(written.radar_values, chart.radar_values);
+        assert_eq!(written.note_data, chart.note_data);
+        assert_eq!(written.timing, chart.timing);
+    }
+}
+
+#[test]
+fn converts_sm_to_ssc() {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    path.push("example_files/goin_under.sm");
+    let sim = parse_simfile(&mut BufReader::new(File::open(path).unwrap())).unwrap();
+    let written = parse_string_as_ssc(&write_to_string(&sim)).unwrap();
+
+    assert_eq!(written.title, sim.title);
+    assert_eq!(written.offset, sim.offset);
+    assert_eq!(written.bpms, sim.bpms);
+    assert_eq!(written.stops, sim.stops);
+    assert_eq!(written.charts.len(), sim.charts.len());
+    for (written, chart) in written.charts.iter().zip(sim.charts.iter()) {
+        assert_eq!(written.chart_type, chart.chart_type);
+        assert_eq!(written.difficulty, chart.difficulty);
+        assert_eq!(written.meter, chart.meter);
+        assert_eq!(written.note_data, chart.note_data);
+    }
+}
+
+#[test]
+fn fails_to_write_unknown_chart_types() {
+    let sim = parse_string_as_ssc("#NOTEDATA:;#STEPSTYPE:guitar-five;#NOTES:00000;").unwrap();
+    let mut data = vec![];
+
+    assert!(write_ssc(&sim, &mut data).is_err());
+}
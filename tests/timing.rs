@@ -0,0 +1,96 @@
+extern crate sm_parser;
+
+use sm_parser::parse_simfile;
+use sm_parser::simfile::Simfile;
+use sm_parser::timing::TimingData;
+use std::io::BufReader;
+
+fn parse_string_as_simfile(data: &str) -> Simfile {
+    parse_simfile(&mut BufReader::new(data.as_bytes())).unwrap()
+}
+
+#[test]
+fn converts_beats_to_seconds() {
+    let sim = parse_string_as_simfile("#OFFSET:0.000;#BPMS:0.000=120.000;");
+    let timing = TimingData::from_simfile(&sim);
+
+    assert_eq!(timing.seconds_at_beat(0.0), 0.0);
+    assert_eq!(timing.seconds_at_beat(4.0), 2.0);
+}
+
+#[test]
+fn applies_offset() {
+    let sim = parse_string_as_simfile("#OFFSET:-0.500;#BPMS:0.000=120.000;");
+    let timing = TimingData::from_simfile(&sim);
+
+    assert_eq!(timing.seconds_at_beat(0.0), 0.5);
+    assert_eq!(timing.seconds_at_beat(2.0), 1.5);
+}
+
+#[test]
+fn applies_bpm_changes() {
+    let sim = parse_string_as_simfile("#BPMS:0.000=120.000,4.000=60.000;");
+    let timing = TimingData::from_simfile(&sim);
+
+    assert_eq!(timing.bpm_at_beat(3.0), 120.0);
+    assert_eq!(timing.bpm_at_beat(4.0), 60.0);
+    assert_eq!(timing.seconds_at_beat(4.0), 2.0);
+    assert_eq!(timing.seconds_at_beat(6.0), 4.0);
+}
+
+#[test]
+fn applies_stops_after_their_beat() {
+    let sim = parse_string_as_simfile("#BPMS:0.000=120.000;#STOPS:2.000=1.000;");
+    let timing = TimingData::from_simfile(&sim);
+
+    assert_eq!(timing.seconds_at_beat(2.0), 1.0);
+    assert_eq!(timing.seconds_at_beat(3.0), 2.5);
+}
+
+#[test]
+fn converts_seconds_to_beats() {
+    let sim = parse_string_as_simfile(
+        "#OFFSET:-0.500;#BPMS:0.000=120.000,4.000=60.000;#STOPS:6.000=1.000;",
+    );
+    let timing = TimingData::from_simfile(&sim);
+
+    assert_eq!(timing.beat_at_seconds(0.0), -1.0);
+    assert_eq!(timing.beat_at_seconds(1.5), 2.0);
+    assert_eq!(timing.beat_at_seconds(3.5), 5.0);
+    // During the stop on beat 6
+    assert_eq!(timing.beat_at_seconds(4.5), 6.0);
+    assert_eq!(timing.beat_at_seconds(5.5), 6.0);
+    assert_eq!(timing.beat_at_seconds(6.5), 7.0);
+}
+
+#[test]
+fn quantizes_seconds_to_beats() {
+    let sim = parse_string_as_simfile("#BPMS:0.000=120.000;");
+    let timing = TimingData::from_simfile(&sim);
+
+    assert_eq!(timing.quantized_beat_at_seconds(0.667), 4.0 / 3.0);
+    assert_eq!(timing.quantized_beat_at_seconds(0.126), 0.25);
+    // 0.115 seconds isn't near a 16th or 24th note, so it's snapped to the nearest 48th
+    assert_eq!(timing.quantized_beat_at_seconds(0.115), 11.0 / 48.0);
+}
+
+#[test]
+fn applies_delays_before_their_beat() {
+    let sim = parse_string_as_simfile("#BPMS:0.000=120.000;#DELAYS:2.000=1.000;");
+    let timing = TimingData::from_simfile(&sim);
+
+    assert_eq!(timing.seconds_at_beat(1.0), 0.5);
+    assert_eq!(timing.seconds_at_beat(2.0), 2.0);
+    assert_eq!(timing.beat_at_seconds(1.5), 2.0);
+    assert_eq!(timing.beat_at_seconds(2.5), 3.0);
+}
+
+#[test]
+fn handles_nan_timing_values() {
+    let sim = parse_string_as_simfile("#BPMS:0=120,nan=150;#STOPS:nan=1,4=0.5;#DELAYS:nan=1,2=1;");
+    let timing = TimingData::from_simfile(&sim);
+    let timing = TimingData::from_timed_bpms(&timing.timed_bpms(), 0.0).unwrap();
+
+    assert_eq!(timing.bpm_at_beat(0.0), 120.0);
+    timing.beat_at_seconds(1.0);
+}
@@ -0,0 +1,246 @@
+extern crate sm_parser;
+
+mod common;
+
+use common::note;
+use sm_parser::parse_simfile;
+use sm_parser::simfile::{ChartDifficulty, NoteType, Simfile};
+use sm_parser::ucs::{parse_ucs, write_ucs};
+use sm_parser::SimfileParseError;
+use std::io::BufReader;
+
+fn parse_string_as_ucs(data: &str) -> Result<Simfile, SimfileParseError> {
+    parse_ucs(&mut BufReader::new(data.as_bytes()))
+}
+
+fn write_to_string(sim: &Simfile) -> String {
+    let mut data = vec![];
+    write_ucs(sim, &sim.charts[0], &mut data).unwrap();
+    String::from_utf8(data).unwrap()
+}
+
+const TEST_UCS: &str = ":Format=1
+:Mode=Single
+:BPM=120
+:Delay=500
+:Beat=4
+:Split=2
+X....
+.M...
+.H...
+.W..X
+:BPM=240
+:Delay=250
+:Beat=3
+:Split=4
+....x
+X....
+";
+
+#[test]
+fn parses_chart() {
+    let sim = parse_string_as_ucs(TEST_UCS).unwrap();
+    let chart = &sim.charts[0];
+
+    assert_eq!(chart.chart_type, "pump-single");
+    assert_eq!(chart.difficulty, ChartDifficulty::Edit);
+    assert_eq!(chart.meter, 1);
+    assert_eq!(
+        chart.notes(),
+        vec![
+            note(0, 0, NoteType::Normal),
+            note(24, 1, NoteType::HoldHead),
+            note(72, 1, NoteType::HoldOrRollTail),
+            note(72, 4, NoteType::Normal),
+            note(96, 4, NoteType::Normal),
+            note(108, 0, NoteType::Normal),
+        ]
+    );
+}
+
+#[test]
+fn parses_block_headers() {
+    let sim = parse_string_as_ucs(TEST_UCS).unwrap();
+
+    // The delay of the first block is the offset
+    assert_eq!(sim.offset, Some(-0.5));
+    assert_eq!(sim.bpms.len(), 2);
+    assert_eq!(sim.bpms[1].beat, 2.0);
+    assert_eq!(sim.bpms[1].bpm, 240.0);
+    assert_eq!(sim.delays.len(), 1);
+    assert_eq!(sim.delays[0].beat, 2.0);
+    assert_eq!(sim.delays[0].time, 0.25);
+    assert_eq!(sim.time_signatures.len(), 2);
+    assert_eq!(sim.time_signatures[1].beat, 2.0);
+    assert_eq!(sim.time_signatures[1].numerator, 3);
+}
+
+#[test]
+fn parses_double_charts() {
+    let sim = parse_string_as_ucs(
+        ":Mode=Double
+:BPM=150
+:Delay=0
+:Split=1
+M........X
+H.........",
+    )
+    .unwrap();
+
+    // A hold that never ends becomes a normal note
+    assert_eq!(sim.charts[0].chart_type, "pump-double");
+    assert_eq!(
+        sim.charts[0].notes(),
+        vec![note(0, 0, NoteType::Normal), note(0, 9, NoteType::Normal)]
+    );
+}
+
+#[test]
+fn fails_on_invalid_data() {
+    assert_eq!(
+        parse_string_as_ucs(":BPM=120\nX....").unwrap_err(),
+        SimfileParseError::InvalidUcsData
+    );
+    assert_eq!(
+        parse_string_as_ucs(":Mode=Triple").unwrap_err(),
+        SimfileParseError::InvalidUcsData
+    );
+    assert_eq!(
+        parse_string_as_ucs(":Mode=Single\nX...").unwrap_err(),
+        SimfileParseError::InvalidUcsData
+    );
+    assert_eq!(
+        parse_string_as_ucs(":Mode=Single\nX...1").unwrap_err(),
+        SimfileParseError::InvalidUcsData
+    );
+    assert_eq!(
+        parse_string_as_ucs(":Mode=Single\n:BPM=fast").unwrap_err(),
+        SimfileParseError::InvalidUcsData
+    );
+}
+
+#[test]
+fn writes_chart() {
+    let sim = parse_string_as_ucs(TEST_UCS).unwrap();
+
+    // The last block is filled up to the end of the measure
+    assert_eq!(
+        write_to_string(&sim),
+        ":Format=1
+:Mode=Single
+:BPM=120
+:Delay=500
+:Beat=4
+:Split=2
+X....
+.M...
+.H...
+.W..X
+:BPM=240
+:Delay=250
+:Beat=3
+:Split=4
+....X
+X....
+.....
+.....
+.....
+.....
+.....
+.....
+"
+    );
+}
+
+#[test]
+fn round_trips_chart() {
+    let sim = parse_string_as_ucs(TEST_UCS).unwrap();
+    let written = parse_string_as_ucs(&write_to_string(&sim)).unwrap();
+
+    assert_eq!(written.offset, sim.offset);
+    assert_eq!(written.bpms.len(), sim.bpms.len());
+    assert_eq!(written.delays.len(), sim.delays.len());
+    assert_eq!(written.charts[0].notes(), sim.charts[0].notes());
+}
+
+#[test]
+fn writes_stops_as_delays() {
+    let sim = parse_simfile(&mut BufReader::new(
+        "#OFFSET:0;#BPMS:0=60;#STOPS:1=0.5;
+        #NOTES:pump-single::Hard:5::
+        10000
+        00000
+        00000
+        00001
+        ;"
+        .as_bytes(),
+    ))
+    .unwrap();
+    let data = write_to_string(&sim);
+
+    // The stop is moved to the next note, which is still hit at the same time
+    assert_eq!(
+        data.lines().take(14).collect::<Vec<&str>>().join("\n"),
+        ":Format=1
+:Mode=Single
+:BPM=60
+:Delay=0
+:Beat=4
+:Split=1
+X....
+.....
+.....
+:BPM=60
+:Delay=500
+:Beat=4
+:Split=1
+....X"
+    );
+}
+
+#[test]
+fn leaves_out_mines() {
+    let sim = parse_simfile(&mut BufReader::new(
+        "#OFFSET:0;#BPMS:0=60;
+        #NOTES:pump-single::Hard:5::
+        1M000
+        00M00
+        00000
+        00000
+        00000
+        00000
+        00000
+        00000
+        ;"
+        .as_bytes(),
+    ))
+    .unwrap();
+    let data = write_to_string(&sim);
+
+    // The mine half a beat in doesn't need a finer split either
+    assert_eq!(
+        data,
+        ":Format=1
+:Mode=Single
+:BPM=60
+:Delay=0
+:Beat=4
+:Split=1
+X....
+.....
+.....
+.....
+"
+    );
+}
+
+#[test]
+fn fails_to_write_other_chart_types() {
+    let sim = parse_simfile(&mut BufReader::new(
+        "#BPMS:0=120;#NOTES:dance-single::Hard:5::1000;".as_bytes(),
+    ))
+    .unwrap();
+    let mut data = vec![];
+
+    assert!(write_ucs(&sim, &sim.charts[0], &mut data).is_err());
+}